@@ -1,9 +1,11 @@
 use printnanny_services::error::ServiceError;
 use printnanny_services::printnanny_api::ApiService;
+use printnanny_services::rtmp_ingest;
 use printnanny_services::video_recording_sync::handle_sync_video_recordings;
 use std::io::{self, Write};
 
-use printnanny_edge_db::cloud::Pi;
+use printnanny_edge_db::cloud::{EmailAlertSettings, Pi};
+use printnanny_edge_db::pretty_json::ToPrettyJson;
 
 pub struct CloudDataCommand;
 
@@ -15,12 +17,25 @@ impl CloudDataCommand {
                 service.sync().await?;
             }
             Some(("sync-video-recordings", _args)) => handle_sync_video_recordings().await?,
+            Some(("record", args)) => {
+                let port: u16 = args.value_of_t("port").unwrap_or(1935);
+                let reserve_bytes: i64 = args.value_of_t("reserve-bytes").unwrap_or(1 << 30);
+                let connection_str = args
+                    .value_of("connection-str")
+                    .expect("--connection-str is required")
+                    .to_string();
+                let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+                rtmp_ingest::serve(connection_str, addr, reserve_bytes).await?;
+            }
             Some(("show", _args)) => {
                 let pi = Pi::get()?;
-                let v = serde_json::to_vec_pretty(&pi)?;
-                io::stdout().write_all(&v)?;
+                io::stdout().write_all(pi.to_pretty_json().as_bytes())?;
+            }
+            Some(("show-email-alert-settings", _args)) => {
+                let settings = EmailAlertSettings::get()?;
+                io::stdout().write_all(settings.to_pretty_json().as_bytes())?;
             }
-            _ => panic!("Expected get|sync|show subcommand"),
+            _ => panic!("Expected sync-models|sync-video-recordings|record|show|show-email-alert-settings subcommand"),
         };
         Ok(())
     }
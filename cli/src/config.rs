@@ -5,6 +5,38 @@ use std::io::{self, Write};
 
 pub struct ConfigCommand;
 
+// Coerces a raw CLI string into the type it looks like, so `set paths.issue_txt /etc/issue`
+// round-trips as a string but `set some.flag true` round-trips as a bool/int/float rather
+// than always landing in the settings tree as a string.
+fn coerce_value(raw: &str) -> serde_json::Value {
+    if let Ok(b) = raw.parse::<bool>() {
+        serde_json::Value::Bool(b)
+    } else if let Ok(i) = raw.parse::<i64>() {
+        serde_json::Value::Number(i.into())
+    } else if let Ok(f) = raw.parse::<f64>() {
+        match serde_json::Number::from_f64(f) {
+            Some(n) => serde_json::Value::Number(n),
+            None => serde_json::Value::String(raw.to_string()),
+        }
+    } else {
+        serde_json::Value::String(raw.to_string())
+    }
+}
+
+// Builds the nested object a dotted key (e.g. "paths.issue_txt") implies, with `leaf` at
+// the innermost segment, so a dotted `set` merges into the settings tree at the right
+// depth instead of only ever supporting top-level keys.
+fn nested_value(segments: &[&str], leaf: serde_json::Value) -> serde_json::Value {
+    match segments.split_first() {
+        Some((head, rest)) => {
+            let mut map = serde_json::Map::new();
+            map.insert(head.to_string(), nested_value(rest, leaf));
+            serde_json::Value::Object(map)
+        }
+        None => leaf,
+    }
+}
+
 impl ConfigCommand {
     pub async fn handle(sub_m: &clap::ArgMatches) -> Result<(), ServiceError> {
         let config: PrintNannySettings = PrintNannySettings::new()?;
@@ -33,15 +65,28 @@ impl ConfigCommand {
                             toml::ser::to_vec(&data)?
                         }
                     },
+                    ConfigFormat::Yaml => match key {
+                        Some(k) => {
+                            let data = PrintNannySettings::find_value(k)?;
+                            serde_yaml::to_string(&data)?.into_bytes()
+                        }
+                        None => {
+                            let data = PrintNannySettings::new()?;
+                            serde_yaml::to_string(&data)?.into_bytes()
+                        }
+                    },
                 };
                 io::stdout().write_all(&v)?;
             }
             Some(("set", args)) => {
                 let key = args.value_of("key").unwrap();
                 let value = args.value_of("value").unwrap();
+                let segments: Vec<&str> = key.split('.').collect();
+                let leaf = coerce_value(value);
+                let data = nested_value(&segments[1..], leaf);
                 let figment = PrintNannySettings::figment()?;
-                let data = figment::providers::Serialized::global(key, &value);
-                let figment = figment.merge(data);
+                let figment =
+                    figment.merge(figment::providers::Serialized::global(segments[0], data));
                 let config: PrintNannySettings = figment.extract()?;
                 config.try_save()?;
             }
@@ -55,6 +100,7 @@ impl ConfigCommand {
                 let v = match f {
                     ConfigFormat::Json => serde_json::to_vec_pretty(&config)?,
                     ConfigFormat::Toml => toml::ser::to_vec(&config)?,
+                    ConfigFormat::Yaml => serde_yaml::to_string(&config)?.into_bytes(),
                 };
                 io::stdout().write_all(&v)?;
             }
@@ -52,6 +52,7 @@ fn handle_system_info(args: &ArgMatches) -> Result<()> {
     let output = match format {
         ConfigFormat::Json => serde_json::to_string(&system_info)?,
         ConfigFormat::Toml => toml::ser::to_string(&system_info)?,
+        ConfigFormat::Yaml => serde_yaml::to_string(&system_info)?,
     };
     print!("{}", &output);
     Ok(())
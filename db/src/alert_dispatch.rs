@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::process::Command;
+use std::sync::Mutex;
+
+use log::info;
+
+use crate::cloud::{EmailAlertSettings, Pi};
+
+// lifecycle events a print job can raise; each maps 1:1 onto one of the
+// `*_enabled` flags on `EmailAlertSettings`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrintEventKind {
+    Started,
+    Progress,
+    Paused,
+    Done,
+    Cancelled,
+    QualityDegraded,
+}
+
+// a single occurrence of a lifecycle event for a print; `percent` is only
+// meaningful when `kind` is `Progress`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintEvent {
+    pub kind: PrintEventKind,
+    pub percent: Option<i32>,
+}
+
+// rendered notification, independent of how it's ultimately delivered. Kept as a
+// plain struct rather than `printnanny_settings::git2::Email` (imported in
+// db/src/cloud.rs) since that type is built from a `git2::Diff` via
+// `Email::create_from_diff` and has no constructor that fits a freeform alert body
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertMessage {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertDispatchError {
+    #[error(transparent)]
+    Diesel {
+        #[from]
+        error: diesel::result::Error,
+    },
+    #[error("failed to spawn {args:?} - {error}")]
+    Spawn {
+        args: Vec<String>,
+        error: std::io::Error,
+    },
+    #[error("{args:?} exited with non-zero status - {stderr}")]
+    Command { args: Vec<String>, stderr: String },
+}
+
+// delivery backend for a rendered `AlertMessage`; production sends over sendmail,
+// tests swap in a sink that just captures messages for assertions
+pub trait AlertSink {
+    fn send(&self, message: &AlertMessage) -> Result<(), AlertDispatchError>;
+}
+
+// shells out to sendmail the same way `VersionControlledSettings::git_notify_commit_email`
+// delivers its commit notifications, so alert mail and git-commit mail share one
+// delivery mechanism on the device
+pub struct SendmailAlertSink {
+    pub sendmail_bin: String,
+}
+
+impl AlertSink for SendmailAlertSink {
+    fn send(&self, message: &AlertMessage) -> Result<(), AlertDispatchError> {
+        let rendered = format!(
+            "From: {from}\nTo: {to}\nSubject: {subject}\n\n{body}\n",
+            from = message.from,
+            to = message.to.join(", "),
+            subject = message.subject,
+            body = message.body,
+        );
+
+        let mut child = Command::new(&self.sendmail_bin)
+            .arg("-t")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|error| AlertDispatchError::Spawn {
+                args: vec![self.sendmail_bin.clone(), "-t".into()],
+                error,
+            })?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(rendered.as_bytes())
+            .map_err(|error| AlertDispatchError::Spawn {
+                args: vec![self.sendmail_bin.clone(), "-t".into()],
+                error,
+            })?;
+        let output = child
+            .wait_with_output()
+            .map_err(|error| AlertDispatchError::Spawn {
+                args: vec![self.sendmail_bin.clone(), "-t".into()],
+                error,
+            })?;
+        if !output.status.success() {
+            return Err(AlertDispatchError::Command {
+                args: vec![self.sendmail_bin.clone(), "-t".into()],
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+// decides whether an event is newsworthy and hands a rendered `AlertMessage` to
+// `S`. Holds the per-Pi debounce state (last-notified progress percent, and which
+// one-shot events have already fired) so repeated events from the same print
+// don't spam recipients
+pub struct AlertDispatcher<S: AlertSink> {
+    sink: S,
+    last_notified_percent: Mutex<HashMap<i32, i32>>,
+    sent: Mutex<HashSet<(i32, PrintEventKind)>>,
+}
+
+impl<S: AlertSink> AlertDispatcher<S> {
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            last_notified_percent: Mutex::new(HashMap::new()),
+            sent: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // loads the (singleton) `EmailAlertSettings` row, checks `event` against its
+    // matching `*_enabled` flag and debounce state, and - if newsworthy - renders
+    // and sends a message `from`/`to` via the configured `AlertSink`
+    pub fn dispatch(
+        &self,
+        connection_str: &str,
+        event: PrintEvent,
+        pi: &Pi,
+        from: &str,
+        to: &[String],
+    ) -> Result<(), AlertDispatchError> {
+        let settings = EmailAlertSettings::get(connection_str)?;
+        if !self.is_enabled(&settings, event.kind) {
+            return Ok(());
+        }
+        if !self.should_notify(&settings, event, pi.id) {
+            return Ok(());
+        }
+
+        let message = self.render_message(event, pi, from, to);
+        self.sink.send(&message)?;
+        info!(
+            "printnanny_edge_db::alert_dispatch dispatched {:?} for pi {}",
+            event.kind, pi.id
+        );
+        Ok(())
+    }
+
+    fn is_enabled(&self, settings: &EmailAlertSettings, kind: PrintEventKind) -> bool {
+        match kind {
+            PrintEventKind::Started => settings.print_started_enabled,
+            PrintEventKind::Progress => settings.print_progress_enabled,
+            PrintEventKind::Paused => settings.print_paused_enabled,
+            PrintEventKind::Done => settings.print_done_enabled,
+            PrintEventKind::Cancelled => settings.print_cancelled_enabled,
+            PrintEventKind::QualityDegraded => settings.print_quality_enabled,
+        }
+    }
+
+    // progress events debounce against the configured `progress_percent` step size;
+    // every other event kind debounces against `(pi_id, event_kind)` so a repeated
+    // event for the same print doesn't re-send
+    fn should_notify(&self, settings: &EmailAlertSettings, event: PrintEvent, pi_id: i32) -> bool {
+        if event.kind == PrintEventKind::Progress {
+            let percent = event.percent.unwrap_or(0);
+            let step = settings.progress_percent.max(1);
+            let mut last_notified = self.last_notified_percent.lock().unwrap();
+            let crossed = match last_notified.get(&pi_id) {
+                Some(&prev) => percent - prev >= step,
+                None => true,
+            };
+            if crossed {
+                last_notified.insert(pi_id, percent);
+            }
+            crossed
+        } else {
+            let mut sent = self.sent.lock().unwrap();
+            sent.insert((pi_id, event.kind))
+        }
+    }
+
+    fn render_message(&self, event: PrintEvent, pi: &Pi, from: &str, to: &[String]) -> AlertMessage {
+        let subject = match event.kind {
+            PrintEventKind::Started => format!("{} started a print", pi.hostname),
+            PrintEventKind::Progress => format!(
+                "{} is {}% done",
+                pi.hostname,
+                event.percent.unwrap_or(0)
+            ),
+            PrintEventKind::Paused => format!("{} paused a print", pi.hostname),
+            PrintEventKind::Done => format!("{} finished a print", pi.hostname),
+            PrintEventKind::Cancelled => format!("{} cancelled a print", pi.hostname),
+            PrintEventKind::QualityDegraded => {
+                format!("{} detected a print quality issue", pi.hostname)
+            }
+        };
+        let body = format!(
+            "Pi: {hostname}\nOctoPrint: {octoprint_url}\nMission Control: {mission_control_url}\n",
+            hostname = pi.hostname,
+            octoprint_url = pi.octoprint_url,
+            mission_control_url = pi.mission_control_url,
+        );
+        AlertMessage {
+            from: from.to_string(),
+            to: to.to_vec(),
+            subject,
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    // captures every message handed to it instead of shelling out, so debounce
+    // behavior can be asserted on without a sendmail binary or a real DB row
+    #[derive(Default)]
+    struct MockAlertSink {
+        sent: StdMutex<Vec<AlertMessage>>,
+    }
+
+    impl AlertSink for MockAlertSink {
+        fn send(&self, message: &AlertMessage) -> Result<(), AlertDispatchError> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn email_alert_settings(progress_percent: i32) -> EmailAlertSettings {
+        EmailAlertSettings {
+            id: 1,
+            created_dt: chrono::Utc::now(),
+            updated_dt: chrono::Utc::now(),
+            progress_percent,
+            print_quality_enabled: true,
+            print_started_enabled: true,
+            print_done_enabled: true,
+            print_progress_enabled: true,
+            print_paused_enabled: true,
+            print_cancelled_enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_should_notify_progress_debounces_until_step_crossed() {
+        let dispatcher = AlertDispatcher::new(MockAlertSink::default());
+        let settings = email_alert_settings(10);
+
+        let first = PrintEvent {
+            kind: PrintEventKind::Progress,
+            percent: Some(5),
+        };
+        assert!(dispatcher.should_notify(&settings, first, 1));
+
+        // same pi, below the 10% step since the last notification - debounced
+        let not_yet = PrintEvent {
+            kind: PrintEventKind::Progress,
+            percent: Some(12),
+        };
+        assert!(!dispatcher.should_notify(&settings, not_yet, 1));
+
+        // crosses the step relative to the last *notified* percent (5), not the
+        // most recent event (12)
+        let crossed = PrintEvent {
+            kind: PrintEventKind::Progress,
+            percent: Some(16),
+        };
+        assert!(dispatcher.should_notify(&settings, crossed, 1));
+    }
+
+    #[test]
+    fn test_should_notify_one_shot_event_dedups_per_pi() {
+        let dispatcher = AlertDispatcher::new(MockAlertSink::default());
+        let settings = email_alert_settings(10);
+
+        let event = PrintEvent {
+            kind: PrintEventKind::Done,
+            percent: None,
+        };
+        assert!(dispatcher.should_notify(&settings, event, 1));
+        // same pi, same kind - already sent, so it's deduped
+        assert!(!dispatcher.should_notify(&settings, event, 1));
+        // a different pi hasn't been notified yet for this event kind
+        assert!(dispatcher.should_notify(&settings, event, 2));
+    }
+}
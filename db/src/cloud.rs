@@ -1,11 +1,16 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use diesel::prelude::*;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use printnanny_settings::git2::Email;
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 
 use chrono::{DateTime, Utc};
 use log::info;
 
 use crate::connection::establish_sqlite_connection;
+use crate::schema::consumed_unsubscribe_tokens;
+use crate::schema::email_alert_keys;
 use crate::schema::email_alert_settings;
 use crate::schema::pis;
 
@@ -108,6 +113,15 @@ impl From<printnanny_api_client::models::Pi> for Pi {
     }
 }
 
+// whether an `upsert` call inserted a brand-new row or overwrote an existing one,
+// so callers re-syncing from the cloud API can log the transition without an extra
+// read of their own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Created,
+    Updated,
+}
+
 impl Pi {
     pub fn get_id(connection_str: &str) -> Result<i32, diesel::result::Error> {
         use crate::schema::pis::dsl::*;
@@ -143,6 +157,51 @@ impl Pi {
         info!("printnanny_edge_db::cloud::Pi with id={} updated", &result);
         Ok(())
     }
+
+    // inserts `row`, or overwrites every column but `created_dt` if a row with the
+    // same id already exists, so re-syncing this effectively-singleton row from the
+    // cloud API on every reconnect is a single atomic statement instead of a
+    // get-then-update-or-insert race
+    pub fn upsert(connection_str: &str, row: Pi) -> Result<UpsertOutcome, diesel::result::Error> {
+        use crate::schema::pis::dsl;
+
+        let mut connection = establish_sqlite_connection(connection_str);
+        let outcome = connection.transaction(|connection| {
+            let existing = dsl::pis
+                .select(dsl::id)
+                .filter(dsl::id.eq(row.id))
+                .first::<i32>(connection)
+                .optional()?;
+
+            diesel::insert_into(dsl::pis)
+                .values(&row)
+                .on_conflict(dsl::id)
+                .do_update()
+                .set((
+                    dsl::last_boot.eq(&row.last_boot),
+                    dsl::hostname.eq(&row.hostname),
+                    dsl::moonraker_api_url.eq(&row.moonraker_api_url),
+                    dsl::mission_control_url.eq(&row.mission_control_url),
+                    dsl::octoprint_url.eq(&row.octoprint_url),
+                    dsl::swupdate_url.eq(&row.swupdate_url),
+                    dsl::syncthing_url.eq(&row.syncthing_url),
+                    dsl::preferred_dns.eq(&row.preferred_dns),
+                    dsl::octoprint_server_id.eq(&row.octoprint_server_id),
+                    dsl::system_info_id.eq(&row.system_info_id),
+                ))
+                .execute(connection)?;
+
+            Ok::<_, diesel::result::Error>(match existing {
+                Some(_) => UpsertOutcome::Updated,
+                None => UpsertOutcome::Created,
+            })
+        })?;
+        info!(
+            "printnanny_edge_db::cloud::Pi id={} upsert outcome={:?}",
+            row.id, &outcome
+        );
+        Ok(outcome)
+    }
 }
 
 #[derive(
@@ -219,4 +278,272 @@ impl EmailAlertSettings {
         );
         Ok(())
     }
+
+    // inserts `row`, or overwrites every column but `created_dt` if a row with the
+    // same id already exists, so re-syncing this effectively-singleton row from the
+    // cloud API on every reconnect is a single atomic statement instead of a
+    // get-then-update-or-insert race
+    pub fn upsert(
+        connection_str: &str,
+        row: EmailAlertSettings,
+    ) -> Result<UpsertOutcome, diesel::result::Error> {
+        use crate::schema::email_alert_settings::dsl;
+
+        let mut connection = establish_sqlite_connection(connection_str);
+        let outcome = connection.transaction(|connection| {
+            let existing = dsl::email_alert_settings
+                .select(dsl::id)
+                .filter(dsl::id.eq(row.id))
+                .first::<i32>(connection)
+                .optional()?;
+
+            diesel::insert_into(dsl::email_alert_settings)
+                .values(&row)
+                .on_conflict(dsl::id)
+                .do_update()
+                .set((
+                    dsl::updated_dt.eq(&row.updated_dt),
+                    dsl::progress_percent.eq(&row.progress_percent),
+                    dsl::print_quality_enabled.eq(&row.print_quality_enabled),
+                    dsl::print_started_enabled.eq(&row.print_started_enabled),
+                    dsl::print_done_enabled.eq(&row.print_done_enabled),
+                    dsl::print_progress_enabled.eq(&row.print_progress_enabled),
+                    dsl::print_paused_enabled.eq(&row.print_paused_enabled),
+                    dsl::print_cancelled_enabled.eq(&row.print_cancelled_enabled),
+                ))
+                .execute(connection)?;
+
+            Ok::<_, diesel::result::Error>(match existing {
+                Some(_) => UpsertOutcome::Updated,
+                None => UpsertOutcome::Created,
+            })
+        })?;
+        info!(
+            "printnanny_edge_db::cloud::EmailAlertSettings id={} upsert outcome={:?}",
+            row.id, &outcome
+        );
+        Ok(outcome)
+    }
+
+    // mints a compact, tamper-proof unsubscribe token for `alert_kind` scoped to this
+    // settings row, so a recipient can disable one alert straight from an email link
+    // without first logging in
+    pub fn unsubscribe_token(
+        connection_str: &str,
+        alert_kind: EmailAlertKind,
+    ) -> Result<String, EmailAlertError> {
+        let settings = EmailAlertSettings::get(connection_str)?;
+        let key = EmailAlertKey::get_or_create(connection_str)?;
+        let payload = UnsubscribePayload {
+            settings_id: settings.id,
+            alert_kind,
+            issued_at: Utc::now().timestamp(),
+        };
+        let payload_bytes =
+            serde_json::to_vec(&payload)
+                .map_err(|error| EmailAlertError::Encoding { error: Box::new(error) })?;
+        let signature = key.signing_key()?.sign(&payload_bytes);
+        let mut token_bytes = payload_bytes;
+        token_bytes.extend_from_slice(&signature.to_bytes());
+        Ok(URL_SAFE_NO_PAD.encode(token_bytes))
+    }
+
+    // verifies and consumes a token minted by `unsubscribe_token`, flipping the named
+    // alert flag to false on success. Rejects tokens that are malformed, incorrectly
+    // signed, expired, or already consumed (replayed).
+    pub fn apply_unsubscribe(connection_str: &str, token: &str) -> Result<(), EmailAlertError> {
+        let token_bytes = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|error| EmailAlertError::Encoding {
+                error: Box::new(error),
+            })?;
+        if token_bytes.len() <= 64 {
+            return Err(EmailAlertError::InvalidToken);
+        }
+        let (payload_bytes, signature_bytes) =
+            token_bytes.split_at(token_bytes.len() - 64);
+        let signature_bytes: [u8; 64] = signature_bytes
+            .try_into()
+            .map_err(|_| EmailAlertError::InvalidToken)?;
+        let signature = Signature::from_bytes(&signature_bytes);
+
+        let key = EmailAlertKey::get_or_create(connection_str)?;
+        key.verifying_key()?
+            .verify(payload_bytes, &signature)
+            .map_err(|_| EmailAlertError::InvalidToken)?;
+
+        let payload: UnsubscribePayload = serde_json::from_slice(payload_bytes)
+            .map_err(|error| EmailAlertError::Encoding { error: Box::new(error) })?;
+
+        if Utc::now().timestamp() - payload.issued_at > UNSUBSCRIBE_TOKEN_TTL.num_seconds() {
+            return Err(EmailAlertError::TokenExpired);
+        }
+
+        ConsumedUnsubscribeToken::consume(connection_str, &signature_bytes)?;
+
+        let false_value = false;
+        let changeset = payload.alert_kind.to_changeset(&false_value);
+        EmailAlertSettings::update(connection_str, payload.settings_id, changeset)?;
+        Ok(())
+    }
+}
+
+// the six alert types a recipient can individually opt out of, mapped to the
+// matching `email_alert_settings` boolean column
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmailAlertKind {
+    PrintQuality,
+    PrintStarted,
+    PrintDone,
+    PrintProgress,
+    PrintPaused,
+    PrintCancelled,
+}
+
+impl EmailAlertKind {
+    fn to_changeset(self, disabled: &bool) -> UpdateEmailAlertSettings {
+        let mut changeset = UpdateEmailAlertSettings {
+            updated_dt: None,
+            progress_percent: None,
+            print_quality_enabled: None,
+            print_started_enabled: None,
+            print_done_enabled: None,
+            print_progress_enabled: None,
+            print_paused_enabled: None,
+            print_cancelled_enabled: None,
+        };
+        match self {
+            EmailAlertKind::PrintQuality => changeset.print_quality_enabled = Some(disabled),
+            EmailAlertKind::PrintStarted => changeset.print_started_enabled = Some(disabled),
+            EmailAlertKind::PrintDone => changeset.print_done_enabled = Some(disabled),
+            EmailAlertKind::PrintProgress => changeset.print_progress_enabled = Some(disabled),
+            EmailAlertKind::PrintPaused => changeset.print_paused_enabled = Some(disabled),
+            EmailAlertKind::PrintCancelled => changeset.print_cancelled_enabled = Some(disabled),
+        }
+        changeset
+    }
+}
+
+// payload signed over by `email_alert_keys`; serialized as JSON before signing so the
+// token format stays human-debuggable even though it's opaque to recipients
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct UnsubscribePayload {
+    settings_id: i32,
+    alert_kind: EmailAlertKind,
+    issued_at: i64,
+}
+
+// unsubscribe tokens older than this are rejected outright, even with a valid
+// signature, so a leaked email archive can't be used to mint working unsubscribe
+// links indefinitely
+const UNSUBSCRIBE_TOKEN_TTL: chrono::Duration = chrono::Duration::days(30);
+
+#[derive(Queryable, Identifiable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = email_alert_keys)]
+pub struct EmailAlertKey {
+    pub id: i32,
+    pub signing_key: Vec<u8>,
+    pub verifying_key: Vec<u8>,
+    pub created_dt: DateTime<Utc>,
+}
+
+impl EmailAlertKey {
+    // lazily generates and persists the Ed25519 keypair used to sign/verify
+    // unsubscribe tokens on first use, so devices that never mint an unsubscribe
+    // link never pay the cost of provisioning one
+    pub fn get_or_create(connection_str: &str) -> Result<EmailAlertKey, EmailAlertError> {
+        use crate::schema::email_alert_keys::dsl::*;
+
+        let connection = &mut establish_sqlite_connection(connection_str);
+        let existing = email_alert_keys
+            .order_by(id)
+            .first::<EmailAlertKey>(connection)
+            .optional()?;
+        if let Some(row) = existing {
+            return Ok(row);
+        }
+
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let row = EmailAlertKey {
+            id: 0,
+            signing_key: signing_key.to_bytes().to_vec(),
+            verifying_key: signing_key.verifying_key().to_bytes().to_vec(),
+            created_dt: Utc::now(),
+        };
+        diesel::insert_into(email_alert_keys::dsl::email_alert_keys)
+            .values(&row)
+            .execute(connection)?;
+        info!("printnanny_edge_db::cloud::EmailAlertKey generated and persisted");
+        let result: EmailAlertKey = email_alert_keys.order_by(id).first(connection)?;
+        Ok(result)
+    }
+
+    fn signing_key(&self) -> Result<SigningKey, EmailAlertError> {
+        let bytes: [u8; 32] = self
+            .signing_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| EmailAlertError::InvalidToken)?;
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+
+    fn verifying_key(&self) -> Result<VerifyingKey, EmailAlertError> {
+        let bytes: [u8; 32] = self
+            .verifying_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| EmailAlertError::InvalidToken)?;
+        VerifyingKey::from_bytes(&bytes).map_err(|_| EmailAlertError::InvalidToken)
+    }
+}
+
+#[derive(Queryable, Identifiable, Insertable, Clone, Debug, PartialEq)]
+#[diesel(table_name = consumed_unsubscribe_tokens)]
+#[diesel(primary_key(signature))]
+struct ConsumedUnsubscribeToken {
+    signature: Vec<u8>,
+    consumed_dt: DateTime<Utc>,
+}
+
+impl ConsumedUnsubscribeToken {
+    // records `signature` as spent so a captured unsubscribe link can't be replayed
+    // after it's already been used once; a second use of the same token is rejected
+    // as `EmailAlertError::TokenReplayed` via the PRIMARY KEY conflict below
+    fn consume(connection_str: &str, signature_bytes: &[u8]) -> Result<(), EmailAlertError> {
+        let mut connection = establish_sqlite_connection(connection_str);
+        let row = ConsumedUnsubscribeToken {
+            signature: signature_bytes.to_vec(),
+            consumed_dt: Utc::now(),
+        };
+        diesel::insert_into(consumed_unsubscribe_tokens::dsl::consumed_unsubscribe_tokens)
+            .values(&row)
+            .execute(&mut connection)
+            .map_err(|error| match error {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    _,
+                ) => EmailAlertError::TokenReplayed,
+                error => EmailAlertError::Diesel { error },
+            })?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailAlertError {
+    #[error(transparent)]
+    Diesel {
+        #[from]
+        error: diesel::result::Error,
+    },
+    #[error("failed to encode/decode unsubscribe token - {error}")]
+    Encoding {
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("unsubscribe token is malformed or has an invalid signature")]
+    InvalidToken,
+    #[error("unsubscribe token has expired")]
+    TokenExpired,
+    #[error("unsubscribe token has already been used")]
+    TokenReplayed,
 }
@@ -0,0 +1,76 @@
+use std::sync::Once;
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use log::{info, warn};
+
+// bundles every .sql migration under db/migrations into the compiled binary, so a
+// freshly provisioned edge device doesn't need the migrations directory shipped
+// alongside it separately from whatever runs them
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectionError {
+    #[error("failed to open sqlite connection {connection_str} - {error}")]
+    Connection {
+        connection_str: String,
+        error: diesel::ConnectionError,
+    },
+    #[error("failed to run pending migrations against {connection_str} - {error}")]
+    Migration {
+        connection_str: String,
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+// once-per-process guard so `establish_sqlite_connection` only pays the migration
+// check on the first connection it opens, rather than re-scanning MIGRATIONS on
+// every query a caller makes
+static MIGRATIONS_RUN: Once = Once::new();
+
+// applies every migration in MIGRATIONS that isn't yet recorded against
+// `connection_str`, so a schema change shipped in a firmware update (e.g. a new
+// email_alert_settings flag) is applied on next boot instead of requiring manual DB
+// surgery. Returns how many migrations were actually applied.
+pub fn run_pending_migrations(connection_str: &str) -> Result<usize, ConnectionError> {
+    let mut connection = SqliteConnection::establish(connection_str).map_err(|error| {
+        ConnectionError::Connection {
+            connection_str: connection_str.to_string(),
+            error,
+        }
+    })?;
+    let applied = connection
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(|error| ConnectionError::Migration {
+            connection_str: connection_str.to_string(),
+            error,
+        })?;
+    let count = applied.len();
+    if count > 0 {
+        info!(
+            "Applied {} pending migration(s) to {}: {:?}",
+            count,
+            connection_str,
+            applied.iter().map(|m| m.to_string()).collect::<Vec<_>>()
+        );
+    }
+    Ok(count)
+}
+
+// every table in this crate (Pi, EmailAlertSettings, VideoRecording, StorageDir, ...)
+// opens its connection through here rather than calling SqliteConnection::establish
+// directly, so the schema is guaranteed to exist and be current before the first
+// query runs against a freshly provisioned device
+pub fn establish_sqlite_connection(connection_str: &str) -> SqliteConnection {
+    MIGRATIONS_RUN.call_once(|| {
+        if let Err(error) = run_pending_migrations(connection_str) {
+            warn!(
+                "Failed to run pending migrations against {} - {}",
+                connection_str, error
+            );
+        }
+    });
+    SqliteConnection::establish(connection_str)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", connection_str))
+}
@@ -0,0 +1,27 @@
+use std::io::IsTerminal;
+
+use serde::Serialize;
+
+// presentation-only helper so a `printnanny` subcommand can dump any queryable row
+// (`Pi`, `EmailAlertSettings`, ...) as JSON without re-implementing the
+// TTY-vs-pipe decision at every call site. Colorized output requires the
+// `cli-pretty-json` feature; without it - or when stdout isn't a TTY, e.g. when the
+// caller pipes output into `jq` for scripting - this falls back to plain compact JSON.
+pub trait ToPrettyJson: Serialize {
+    fn to_pretty_json(&self) -> String {
+        #[cfg(feature = "cli-pretty-json")]
+        {
+            if std::io::stdout().is_terminal() {
+                if let Ok(value) = serde_json::to_value(self) {
+                    if let Ok(colored) = colored_json::to_colored_json_auto(&value) {
+                        return colored;
+                    }
+                }
+            }
+        }
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl ToPrettyJson for crate::cloud::Pi {}
+impl ToPrettyJson for crate::cloud::EmailAlertSettings {}
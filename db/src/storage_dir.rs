@@ -0,0 +1,92 @@
+use diesel::prelude::*;
+use log::info;
+
+use crate::connection::establish_sqlite_connection;
+use crate::schema::storage_dirs;
+
+/// A directory `start_new` may write recordings into, e.g. the flash root plus any
+/// number of attached spinning disks. `total_bytes`/`used_bytes` are refreshed by the
+/// caller (statvfs or similar) before a selection is made, not tracked live here.
+#[derive(Queryable, Identifiable, Clone, Debug, PartialEq, Default)]
+#[diesel(table_name = storage_dirs)]
+pub struct StorageDir {
+    pub id: i32,
+    pub path: String,
+    pub total_bytes: i64,
+    pub used_bytes: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = storage_dirs)]
+pub struct NewStorageDir<'a> {
+    pub path: &'a str,
+    pub total_bytes: &'a i64,
+    pub used_bytes: &'a i64,
+}
+
+#[derive(Clone, Debug, PartialEq, AsChangeset)]
+#[diesel(table_name = storage_dirs)]
+pub struct UpdateStorageDir<'a> {
+    pub path: Option<&'a str>,
+    pub total_bytes: Option<&'a i64>,
+    pub used_bytes: Option<&'a i64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageDirError {
+    #[error("no StorageDir has at least {reserve_bytes} bytes free")]
+    NoDirWithFreeSpace { reserve_bytes: i64 },
+    #[error(transparent)]
+    DieselError(#[from] diesel::result::Error),
+}
+
+impl StorageDir {
+    pub fn free_bytes(&self) -> i64 {
+        (self.total_bytes - self.used_bytes).max(0)
+    }
+
+    pub fn get_all(connection_str: &str) -> Result<Vec<StorageDir>, diesel::result::Error> {
+        use crate::schema::storage_dirs::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        storage_dirs.order_by(id).load::<StorageDir>(connection)
+    }
+
+    pub fn get_by_id(
+        connection_str: &str,
+        row_id: i32,
+    ) -> Result<StorageDir, diesel::result::Error> {
+        use crate::schema::storage_dirs::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        storage_dirs
+            .filter(id.eq(row_id))
+            .first::<StorageDir>(connection)
+    }
+
+    pub fn update(
+        connection_str: &str,
+        row_id: i32,
+        row: UpdateStorageDir,
+    ) -> Result<(), diesel::result::Error> {
+        use crate::schema::storage_dirs::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        diesel::update(storage_dirs.filter(id.eq(row_id)))
+            .set(row)
+            .execute(connection)?;
+        info!("Updated StorageDir with id {}", row_id);
+        Ok(())
+    }
+
+    /// Picks the directory with the most free space, rejecting the whole pool if
+    /// every directory is below `reserve_bytes` (e.g. a nearly-full SD card should
+    /// stop accepting new recordings rather than fail partway through a write).
+    pub fn select_target(
+        connection_str: &str,
+        reserve_bytes: i64,
+    ) -> Result<StorageDir, StorageDirError> {
+        let dirs = Self::get_all(connection_str)?;
+        dirs.into_iter()
+            .filter(|d| d.free_bytes() >= reserve_bytes)
+            .max_by_key(|d| d.free_bytes())
+            .ok_or(StorageDirError::NoDirWithFreeSpace { reserve_bytes })
+    }
+}
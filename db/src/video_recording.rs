@@ -1,9 +1,10 @@
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use log::info;
+use log::{info, warn};
 use uuid;
 
 use printnanny_api_client::models;
@@ -12,6 +13,32 @@ use printnanny_asyncapi_models;
 use crate::connection::establish_sqlite_connection;
 use crate::schema::video_recording_parts;
 use crate::schema::video_recordings;
+use crate::storage_dir::{StorageDir, StorageDirError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RotationError {
+    #[error("no VideoRecording is currently capturing")]
+    NoActiveRecording,
+    #[error("VideoRecording {id} has already finished capturing")]
+    RecordingAlreadyDone { id: String },
+    #[error(transparent)]
+    DieselError(#[from] diesel::result::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    DieselError(#[from] diesel::result::Error),
+}
+
+/// Result of a [`VideoRecordingPart::verify_and_prune`] pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VerifyAndPruneSummary {
+    pub parts_kept: usize,
+    pub parts_pruned: usize,
+}
 
 #[derive(Queryable, Identifiable, Clone, Debug, PartialEq, Default)]
 #[diesel(table_name = video_recordings)]
@@ -23,6 +50,10 @@ pub struct VideoRecording {
     pub recording_start: Option<DateTime<Utc>>,
     pub recording_end: Option<DateTime<Utc>>,
     pub gcode_file_name: Option<String>,
+    // StorageDir this recording's files live under. `dir` is kept for display/back-compat
+    // but resolving a path should go through `dir_for`, which looks this up instead of
+    // trusting `dir`, so a recording survives its StorageDir being remounted elsewhere.
+    pub storage_dir_id: i32,
 }
 
 #[derive(Queryable, Identifiable, Clone, Debug, PartialEq, Default)]
@@ -36,6 +67,15 @@ pub struct VideoRecordingPart {
     pub sync_end: Option<DateTime<Utc>>,
     pub file_name: String,
     pub video_recording_id: String,
+    // S3-compatible multipart upload id for this part's mp4 chunk upload, so a sync
+    // interrupted by a crash or network drop resumes instead of restarting
+    pub upload_id: Option<String>,
+    // comma-separated `chunk_number:etag` entries, ascending by chunk number, for the
+    // chunks already PUT + ETag-acked for upload_id
+    pub completed_chunks: Option<String>,
+    // blake3 hex digest of the part's file contents, stamped by verify_and_prune so
+    // the cloud side can confirm the uploaded bytes match what was captured
+    pub checksum: Option<String>,
 }
 
 #[derive(Debug, Insertable)]
@@ -45,6 +85,7 @@ pub struct NewVideoRecording<'a> {
     pub capture_done: &'a bool,
     pub cloud_sync_done: &'a bool,
     pub dir: &'a str,
+    pub storage_dir_id: &'a i32,
 }
 
 #[derive(Debug, Insertable)]
@@ -67,6 +108,7 @@ pub struct UpdateVideoRecording<'a> {
     pub recording_start: Option<&'a DateTime<Utc>>,
     pub recording_end: Option<&'a DateTime<Utc>>,
     pub gcode_file_name: Option<&'a str>,
+    pub storage_dir_id: Option<&'a i32>,
 }
 
 #[derive(Clone, Debug, PartialEq, AsChangeset)]
@@ -79,6 +121,9 @@ pub struct UpdateVideoRecordingPart<'a> {
     pub sync_end: Option<&'a DateTime<Utc>>,
     pub file_name: Option<&'a str>,
     pub video_recording_id: Option<&'a str>,
+    pub upload_id: Option<&'a str>,
+    pub completed_chunks: Option<&'a str>,
+    pub checksum: Option<&'a str>,
 }
 
 impl VideoRecording {
@@ -107,6 +152,7 @@ impl VideoRecording {
             gcode_file_name: None,
             dir: None,
             cloud_sync_done: obj.cloud_sync_done.as_ref(),
+            storage_dir_id: None,
         };
 
         diesel::update(video_recordings.filter(id.eq(&obj.id.clone().unwrap())))
@@ -252,14 +298,19 @@ impl VideoRecording {
     //     Ok(())
     // }
 
+    /// Picks the StorageDir with the most free space (rejecting the pool entirely if
+    /// every directory is below `reserve_bytes`) and creates the recording's directory
+    /// there, so hours of footage can spread across several attached drives instead of
+    /// filling one partition.
     pub fn start_new(
         connection_str: &str,
-        video_path: PathBuf,
-    ) -> Result<VideoRecording, diesel::result::Error> {
+        reserve_bytes: i64,
+    ) -> Result<VideoRecording, StorageDirError> {
         use crate::schema::video_recordings::dsl::*;
         let connection = &mut establish_sqlite_connection(connection_str);
+        let storage_dir = StorageDir::select_target(connection_str, reserve_bytes)?;
         let row_id = uuid::Uuid::new_v4().to_string();
-        let dirname = video_path.join(&row_id);
+        let dirname = PathBuf::from(&storage_dir.path).join(&row_id);
         fs::create_dir(&dirname).expect(&format!(
             "Failed to create directory {}",
             &dirname.display()
@@ -270,6 +321,7 @@ impl VideoRecording {
             capture_done: &false,
             cloud_sync_done: &false,
             dir: &dirname.display().to_string(),
+            storage_dir_id: &storage_dir.id,
         };
         diesel::insert_into(video_recordings)
             .values(&row)
@@ -278,6 +330,120 @@ impl VideoRecording {
         let result = video_recordings.find(&row_id).first(connection)?;
         Ok(result)
     }
+
+    /// Resolves a recording's absolute directory via the StorageDir table instead of
+    /// trusting the `dir` column, so recordings keep resolving correctly after a disk
+    /// is unmounted and remounted somewhere else.
+    pub fn dir_for(connection_str: &str, row_id: &str) -> Result<PathBuf, StorageDirError> {
+        use crate::schema::video_recordings::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        let recording = video_recordings
+            .filter(id.eq(row_id))
+            .first::<VideoRecording>(connection)?;
+        let storage_dir = StorageDir::get_by_id(connection_str, recording.storage_dir_id)?;
+        Ok(PathBuf::from(storage_dir.path).join(&recording.id))
+    }
+}
+
+/// Result of a [`VideoRecording::collect_garbage`] pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GarbageCollectionSummary {
+    pub recordings_deleted: usize,
+    pub bytes_reclaimed: i64,
+}
+
+impl VideoRecording {
+    fn total_bytes(connection_str: &str) -> Result<i64, diesel::result::Error> {
+        use crate::schema::video_recording_parts::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        let sizes = video_recording_parts
+            .filter(deleted.eq(false))
+            .select(size)
+            .load::<i64>(connection)?;
+        Ok(sizes.iter().sum())
+    }
+
+    /// Enforces `quota_bytes` (summed over all non-deleted part sizes) and `max_age`
+    /// by deleting the oldest fully-synced recordings first: their part files from
+    /// disk, their part rows marked `deleted = true`, their directory, then the
+    /// recording row itself. A recording is never touched while `capture_done` is
+    /// still false or before `cloud_sync_done` is true, even if it is over quota or
+    /// past `max_age` — an unsynced recording is the one copy of that footage.
+    pub fn collect_garbage(
+        connection_str: &str,
+        quota_bytes: i64,
+        max_age: chrono::Duration,
+    ) -> Result<GarbageCollectionSummary, diesel::result::Error> {
+        use crate::schema::video_recordings::dsl as recordings_dsl;
+
+        let mut total_bytes = Self::total_bytes(connection_str)?;
+        let cutoff = Utc::now() - max_age;
+
+        let candidates = {
+            let connection = &mut establish_sqlite_connection(connection_str);
+            recordings_dsl::video_recordings
+                .filter(recordings_dsl::cloud_sync_done.eq(true))
+                .filter(recordings_dsl::capture_done.eq(true))
+                .order(recordings_dsl::recording_start.asc())
+                .load::<VideoRecording>(connection)?
+        };
+
+        let mut summary = GarbageCollectionSummary::default();
+
+        for recording in candidates {
+            let over_quota = total_bytes > quota_bytes;
+            let too_old = recording
+                .recording_start
+                .map(|start| start < cutoff)
+                .unwrap_or(false);
+            // oldest-first: once neither condition holds, nothing newer qualifies either
+            if !over_quota && !too_old {
+                break;
+            }
+
+            let parts =
+                VideoRecordingPart::get_parts_by_video_recording_id(connection_str, &recording.id)?;
+            let mut reclaimed = 0i64;
+            for part in parts.iter().filter(|p| !p.deleted) {
+                let path = PathBuf::from(&recording.dir).join(&part.file_name);
+                let _ = fs::remove_file(&path);
+                reclaimed += part.size;
+                VideoRecordingPart::update(
+                    connection_str,
+                    &part.id,
+                    UpdateVideoRecordingPart {
+                        part: None,
+                        size: None,
+                        deleted: Some(&true),
+                        sync_start: None,
+                        sync_end: None,
+                        file_name: None,
+                        video_recording_id: None,
+                        upload_id: None,
+                        completed_chunks: None,
+                        checksum: None,
+                    },
+                )?;
+            }
+            let _ = fs::remove_dir_all(&recording.dir);
+
+            let connection = &mut establish_sqlite_connection(connection_str);
+            diesel::delete(
+                recordings_dsl::video_recordings.filter(recordings_dsl::id.eq(&recording.id)),
+            )
+            .execute(connection)?;
+
+            info!(
+                "Garbage collected VideoRecording {} (reclaimed {} bytes)",
+                recording.id, reclaimed
+            );
+            total_bytes -= reclaimed;
+            summary.recordings_deleted += 1;
+            summary.bytes_reclaimed += reclaimed;
+        }
+
+        Ok(summary)
+    }
 }
 
 impl From<VideoRecording> for printnanny_asyncapi_models::VideoRecording {
@@ -334,6 +500,9 @@ impl VideoRecordingPart {
             sync_end: sync_end_value.as_ref(),
             video_recording_id: Some(&obj.video_recording),
             file_name: None,
+            upload_id: None,
+            completed_chunks: None,
+            checksum: None,
         };
         diesel::update(video_recording_parts.filter(id.eq(&obj.id)))
             .set(row_update)
@@ -352,6 +521,210 @@ impl VideoRecordingPart {
         Ok(result)
     }
 
+    pub fn update(
+        connection_str: &str,
+        row_id: &str,
+        row: UpdateVideoRecordingPart,
+    ) -> Result<(), diesel::result::Error> {
+        use crate::schema::video_recording_parts::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        diesel::update(video_recording_parts.filter(id.eq(row_id)))
+            .set(row)
+            .execute(connection)?;
+        info!("Updated VideoRecordingPart with id {}", row_id);
+        Ok(())
+    }
+
+    pub fn get_by_id(
+        connection_str: &str,
+        row_id: &str,
+    ) -> Result<VideoRecordingPart, diesel::result::Error> {
+        use crate::schema::video_recording_parts::dsl::*;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        video_recording_parts
+            .filter(id.eq(row_id))
+            .first::<VideoRecordingPart>(connection)
+    }
+
+    // ascending (chunk_number, etag) pairs already PUT + ETag-acked, parsed from the
+    // comma-separated `chunk_number:etag` entries in the `completed_chunks` column
+    pub fn completed_chunks_with_etags(&self) -> Vec<(i32, String)> {
+        self.completed_chunks
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .filter_map(|entry| {
+                let (number, etag) = entry.split_once(':')?;
+                Some((number.parse().ok()?, etag.to_string()))
+            })
+            .collect()
+    }
+
+    // ascending chunk numbers already PUT + ETag-acked
+    pub fn completed_chunk_numbers(&self) -> Vec<i32> {
+        self.completed_chunks_with_etags()
+            .into_iter()
+            .map(|(number, _)| number)
+            .collect()
+    }
+
+    /// Stamps `sync_start` the first time a part's upload begins, so
+    /// `get_ready_for_cloud_sync` (filtered on `sync_start.is_null()`) doesn't pick it
+    /// up again while a multipart upload is in flight.
+    pub fn mark_sync_started(
+        connection_str: &str,
+        row_id: &str,
+    ) -> Result<(), diesel::result::Error> {
+        let now = Utc::now();
+        Self::update(
+            connection_str,
+            row_id,
+            UpdateVideoRecordingPart {
+                part: None,
+                size: None,
+                deleted: None,
+                sync_start: Some(&now),
+                sync_end: None,
+                file_name: None,
+                video_recording_id: None,
+                upload_id: None,
+                completed_chunks: None,
+                checksum: None,
+            },
+        )
+    }
+
+    /// Records that `chunk_number` has been PUT and ETag-acked with `etag` for
+    /// `upload_id`, so a resumed upload can skip chunks already durably written on
+    /// the remote side while still replaying their etag into the completion request.
+    pub fn record_chunk_complete(
+        connection_str: &str,
+        row_id: &str,
+        upload_id: &str,
+        chunk_number: i32,
+        etag: &str,
+    ) -> Result<(), diesel::result::Error> {
+        let existing = Self::get_by_id(connection_str, row_id)?;
+        let mut chunks = existing.completed_chunks_with_etags();
+        chunks.retain(|(number, _)| *number != chunk_number);
+        chunks.push((chunk_number, etag.to_string()));
+        chunks.sort_unstable_by_key(|(number, _)| *number);
+        let completed_chunks = chunks
+            .iter()
+            .map(|(number, etag)| format!("{number}:{etag}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        Self::update(
+            connection_str,
+            row_id,
+            UpdateVideoRecordingPart {
+                part: None,
+                size: None,
+                deleted: None,
+                sync_start: None,
+                sync_end: None,
+                file_name: None,
+                video_recording_id: None,
+                upload_id: Some(upload_id),
+                completed_chunks: Some(&completed_chunks),
+                checksum: None,
+            },
+        )
+    }
+
+    /// Stamps `sync_end` once the completion call succeeds; only then is this part
+    /// considered durably synced.
+    pub fn mark_sync_complete(
+        connection_str: &str,
+        row_id: &str,
+    ) -> Result<(), diesel::result::Error> {
+        let now = Utc::now();
+        Self::update(
+            connection_str,
+            row_id,
+            UpdateVideoRecordingPart {
+                part: None,
+                size: None,
+                deleted: None,
+                sync_start: None,
+                sync_end: Some(&now),
+                file_name: None,
+                video_recording_id: None,
+                upload_id: None,
+                completed_chunks: None,
+                checksum: None,
+            },
+        )
+    }
+
+    /// Finalizes the currently-open part of the in-progress recording (stamping its
+    /// size from the file on disk) and opens the next one, so a long capture produces
+    /// a series of bounded-size files instead of one ever-growing one. Cut points
+    /// align to `interval` boundaries (rotate when `now` crosses a multiple of
+    /// `interval`, not at a fixed offset from capture start) so parts across
+    /// recordings line up, which matters once parts are uploaded incrementally.
+    pub fn rotate_current(
+        connection_str: &str,
+        interval: Duration,
+    ) -> Result<VideoRecordingPart, RotationError> {
+        let recording =
+            VideoRecording::get_current(connection_str)?.ok_or(RotationError::NoActiveRecording)?;
+        if recording.capture_done {
+            return Err(RotationError::RecordingAlreadyDone { id: recording.id });
+        }
+
+        let existing = Self::get_parts_by_video_recording_id(connection_str, &recording.id)?;
+        let current = existing.iter().max_by_key(|p| p.part);
+
+        if let Some(current) = current {
+            let path = PathBuf::from(&recording.dir).join(&current.file_name);
+            let size = fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0);
+            Self::update(
+                connection_str,
+                &current.id,
+                UpdateVideoRecordingPart {
+                    part: None,
+                    size: Some(&size),
+                    deleted: None,
+                    sync_start: None,
+                    sync_end: None,
+                    file_name: None,
+                    video_recording_id: None,
+                    upload_id: None,
+                    completed_chunks: None,
+                    checksum: None,
+                },
+            )?;
+        }
+
+        let next_part = current.map(|p| p.part + 1).unwrap_or(0);
+        let interval_secs = interval.as_secs().max(1) as i64;
+        let aligned = Utc::now().timestamp();
+        let aligned = aligned - (aligned % interval_secs);
+        let row_id = uuid::Uuid::new_v4().to_string();
+        let file_name = format!("{}-{:06}.mp4", aligned, next_part);
+        let row = NewVideoRecordingPart {
+            id: &row_id,
+            part: &next_part,
+            size: &0,
+            deleted: &false,
+            file_name: &file_name,
+            video_recording_id: &recording.id,
+        };
+
+        use crate::schema::video_recording_parts::dsl;
+        let connection = &mut establish_sqlite_connection(connection_str);
+        diesel::insert_into(dsl::video_recording_parts)
+            .values(&row)
+            .execute(connection)?;
+        info!(
+            "Rotated VideoRecording {} to part {} ({})",
+            recording.id, next_part, file_name
+        );
+        let result = dsl::video_recording_parts.find(&row_id).first(connection)?;
+        Ok(result)
+    }
+
     pub fn get_parts_by_video_recording_id(
         connection_str: &str,
         video_recording: &str,
@@ -363,6 +736,107 @@ impl VideoRecordingPart {
             .load::<VideoRecordingPart>(connection)?;
         Ok(result)
     }
+
+    /// For every part of `video_recording_id`: delete (file + row) any part that is
+    /// zero-length, unreadable, or fails a minimal mp4 container check (crash mid-write
+    /// leaves these behind), record a blake3 checksum of the survivors, and renumber
+    /// the remaining parts so `part` stays contiguous from 0. Keeps the part sequence
+    /// clean for later mp4 concatenation and stops garbage segments from being uploaded.
+    pub fn verify_and_prune(
+        connection_str: &str,
+        video_recording_id: &str,
+    ) -> Result<VerifyAndPruneSummary, VerifyError> {
+        let recording = VideoRecording::get_by_id(connection_str, video_recording_id)?;
+        let mut parts = Self::get_parts_by_video_recording_id(connection_str, video_recording_id)?;
+        parts.sort_by_key(|p| p.part);
+
+        let mut summary = VerifyAndPruneSummary::default();
+        let mut kept = Vec::with_capacity(parts.len());
+
+        for part in parts {
+            let path = PathBuf::from(&recording.dir).join(&part.file_name);
+            let valid = fs::metadata(&path)
+                .map(|meta| meta.len() > 0)
+                .unwrap_or(false)
+                && is_valid_mp4(&path).unwrap_or(false);
+
+            if valid {
+                let checksum = blake3_checksum(&path)?;
+                Self::update(
+                    connection_str,
+                    &part.id,
+                    UpdateVideoRecordingPart {
+                        part: None,
+                        size: None,
+                        deleted: None,
+                        sync_start: None,
+                        sync_end: None,
+                        file_name: None,
+                        video_recording_id: None,
+                        upload_id: None,
+                        completed_chunks: None,
+                        checksum: Some(&checksum),
+                    },
+                )?;
+                kept.push(part);
+            } else {
+                warn!(
+                    "Pruning empty/corrupt VideoRecordingPart {} ({})",
+                    part.id, part.file_name
+                );
+                let _ = fs::remove_file(&path);
+                use crate::schema::video_recording_parts::dsl;
+                let connection = &mut establish_sqlite_connection(connection_str);
+                diesel::delete(dsl::video_recording_parts.filter(dsl::id.eq(&part.id)))
+                    .execute(connection)?;
+                summary.parts_pruned += 1;
+            }
+        }
+
+        for (next_part, part) in kept.iter().enumerate() {
+            let next_part = next_part as i32;
+            if part.part != next_part {
+                Self::update(
+                    connection_str,
+                    &part.id,
+                    UpdateVideoRecordingPart {
+                        part: Some(&next_part),
+                        size: None,
+                        deleted: None,
+                        sync_start: None,
+                        sync_end: None,
+                        file_name: None,
+                        video_recording_id: None,
+                        upload_id: None,
+                        completed_chunks: None,
+                        checksum: None,
+                    },
+                )?;
+            }
+        }
+        summary.parts_kept = kept.len();
+
+        Ok(summary)
+    }
+}
+
+// reads just enough of the file to confirm it starts with a recognized mp4 box
+// (ftyp is the near-universal first box; moov can appear later in a streamed/fragmented
+// file) rather than fully demuxing it - good enough to catch a truncated/zero-byte part
+fn is_valid_mp4(path: &std::path::Path) -> std::io::Result<bool> {
+    use std::io::Read;
+    let mut file = std::fs::File::open(path)?;
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() {
+        return Ok(false);
+    }
+    let box_type = &header[4..8];
+    Ok(box_type == b"ftyp" || box_type == b"moov" || box_type == b"free" || box_type == b"mdat")
+}
+
+fn blake3_checksum(path: &std::path::Path) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(blake3::hash(&bytes).to_hex().to_string())
 }
 
 impl From<VideoRecordingPart> for models::VideoRecordingPartRequest {
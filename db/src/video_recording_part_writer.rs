@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use log::{error, info};
+
+use crate::connection::establish_sqlite_connection;
+use crate::schema::video_recording_parts;
+use crate::video_recording::{NewVideoRecordingPart, UpdateVideoRecordingPart, VideoRecordingPart};
+
+// Owned stand-in for NewVideoRecordingPart/UpdateVideoRecordingPart (both borrow their
+// fields, which doesn't survive sitting in a buffer between flushes). `is_new`
+// distinguishes a still-unpersisted row from one that only needs an UPDATE on flush.
+#[derive(Debug, Clone)]
+struct PendingPart {
+    part: i32,
+    size: i64,
+    deleted: bool,
+    sync_start: Option<DateTime<Utc>>,
+    sync_end: Option<DateTime<Utc>>,
+    file_name: String,
+    video_recording_id: String,
+    upload_id: Option<String>,
+    completed_chunks: Option<String>,
+    checksum: Option<String>,
+    is_new: bool,
+}
+
+/// Buffers `VideoRecordingPart` inserts/updates in memory, keyed by part id, and flushes
+/// them inside a single transaction once `max_batch` distinct parts are pending or
+/// `max_interval` has elapsed since the last flush, whichever comes first, instead of
+/// issuing one `establish_sqlite_connection` + statement per part. Both thresholds are
+/// checked only when a mutation is queued; there is no background timer, so a quiet
+/// buffer waits for the next queued mutation, an explicit `flush()` or drop.
+/// One of these is meant to live for the lifetime of the capture process.
+pub struct VideoRecordingPartWriter {
+    connection_str: String,
+    connection: Mutex<SqliteConnection>,
+    pending: Mutex<HashMap<String, PendingPart>>,
+    last_flush: Mutex<Instant>,
+    max_batch: usize,
+    max_interval: Duration,
+}
+
+impl VideoRecordingPartWriter {
+    pub fn new(connection_str: &str, max_batch: usize, max_interval: Duration) -> Self {
+        Self {
+            connection_str: connection_str.to_string(),
+            connection: Mutex::new(establish_sqlite_connection(connection_str)),
+            pending: Mutex::new(HashMap::new()),
+            last_flush: Mutex::new(Instant::now()),
+            max_batch,
+            max_interval,
+        }
+    }
+
+    pub fn queue_insert(&self, row: &NewVideoRecordingPart) -> Result<(), diesel::result::Error> {
+        self.pending.lock().unwrap().insert(
+            row.id.to_string(),
+            PendingPart {
+                part: *row.part,
+                size: *row.size,
+                deleted: *row.deleted,
+                sync_start: None,
+                sync_end: None,
+                file_name: row.file_name.to_string(),
+                video_recording_id: row.video_recording_id.to_string(),
+                upload_id: None,
+                completed_chunks: None,
+                checksum: None,
+                is_new: true,
+            },
+        );
+        self.maybe_flush()
+    }
+
+    pub fn queue_update(
+        &self,
+        row_id: &str,
+        row: &UpdateVideoRecordingPart,
+    ) -> Result<(), diesel::result::Error> {
+        let mut pending = self.pending.lock().unwrap();
+        let entry = pending.entry(row_id.to_string()).or_insert(PendingPart {
+            part: 0,
+            size: 0,
+            deleted: false,
+            sync_start: None,
+            sync_end: None,
+            file_name: String::new(),
+            video_recording_id: String::new(),
+            upload_id: None,
+            completed_chunks: None,
+            checksum: None,
+            is_new: false,
+        });
+        if let Some(v) = row.part {
+            entry.part = *v;
+        }
+        if let Some(v) = row.size {
+            entry.size = *v;
+        }
+        if let Some(v) = row.deleted {
+            entry.deleted = *v;
+        }
+        if let Some(v) = row.sync_start {
+            entry.sync_start = Some(*v);
+        }
+        if let Some(v) = row.sync_end {
+            entry.sync_end = Some(*v);
+        }
+        if let Some(v) = row.file_name {
+            entry.file_name = v.to_string();
+        }
+        if let Some(v) = row.video_recording_id {
+            entry.video_recording_id = v.to_string();
+        }
+        if let Some(v) = row.upload_id {
+            entry.upload_id = Some(v.to_string());
+        }
+        if let Some(v) = row.completed_chunks {
+            entry.completed_chunks = Some(v.to_string());
+        }
+        if let Some(v) = row.checksum {
+            entry.checksum = Some(v.to_string());
+        }
+        drop(pending);
+        self.maybe_flush()
+    }
+
+    fn maybe_flush(&self) -> Result<(), diesel::result::Error> {
+        let due = self.pending.lock().unwrap().len() >= self.max_batch
+            || self.last_flush.lock().unwrap().elapsed() >= self.max_interval;
+        if due {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes every pending mutation inside one transaction, regardless of whether
+    /// the batch/interval threshold has been reached.
+    pub fn flush(&self) -> Result<(), diesel::result::Error> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_empty() {
+            *self.last_flush.lock().unwrap() = Instant::now();
+            return Ok(());
+        }
+        let mut connection = self.connection.lock().unwrap();
+        connection.transaction(|connection| {
+            for (id, part) in pending.iter() {
+                if part.is_new {
+                    let row = NewVideoRecordingPart {
+                        id,
+                        part: &part.part,
+                        size: &part.size,
+                        deleted: &part.deleted,
+                        file_name: &part.file_name,
+                        video_recording_id: &part.video_recording_id,
+                    };
+                    diesel::insert_into(video_recording_parts::table)
+                        .values(&row)
+                        .execute(connection)?;
+                } else {
+                    use crate::schema::video_recording_parts::dsl;
+                    let row = UpdateVideoRecordingPart {
+                        part: Some(&part.part),
+                        size: Some(&part.size),
+                        deleted: Some(&part.deleted),
+                        sync_start: part.sync_start.as_ref(),
+                        sync_end: part.sync_end.as_ref(),
+                        file_name: Some(&part.file_name),
+                        video_recording_id: Some(&part.video_recording_id),
+                        upload_id: part.upload_id.as_deref(),
+                        completed_chunks: part.completed_chunks.as_deref(),
+                        checksum: part.checksum.as_deref(),
+                    };
+                    diesel::update(dsl::video_recording_parts.filter(dsl::id.eq(id)))
+                        .set(row)
+                        .execute(connection)?;
+                }
+            }
+            diesel::result::QueryResult::Ok(())
+        })?;
+        info!("Flushed {} pending VideoRecordingPart mutations", pending.len());
+        pending.clear();
+        *self.last_flush.lock().unwrap() = Instant::now();
+        Ok(())
+    }
+
+    /// Same rows `VideoRecordingPart::get_ready_for_cloud_sync` would return, but with
+    /// not-yet-flushed buffered mutations overlaid on top so a part written moments ago
+    /// doesn't look stale to the cloud-sync loop just because it hasn't hit disk yet.
+    pub fn get_ready_for_cloud_sync(&self) -> Result<Vec<VideoRecordingPart>, diesel::result::Error> {
+        let mut result = VideoRecordingPart::get_ready_for_cloud_sync(&self.connection_str)?;
+        let pending = self.pending.lock().unwrap();
+        for row in result.iter_mut() {
+            if let Some(part) = pending.get(&row.id) {
+                row.part = part.part;
+                row.size = part.size;
+                row.deleted = part.deleted;
+                row.sync_start = part.sync_start;
+                row.sync_end = part.sync_end;
+                row.file_name = part.file_name.clone();
+                row.video_recording_id = part.video_recording_id.clone();
+                row.upload_id = part.upload_id.clone();
+                row.completed_chunks = part.completed_chunks.clone();
+                row.checksum = part.checksum.clone();
+            }
+        }
+        let existing_ids: std::collections::HashSet<&String> =
+            result.iter().map(|r| &r.id).collect();
+        // a buffered row never carries a sync_start (only the cloud-sync path sets
+        // that, after a flush), so every not-yet-flushed insert is sync-eligible
+        for (id, part) in pending.iter() {
+            if part.is_new && !existing_ids.contains(id) {
+                result.push(VideoRecordingPart {
+                    id: id.clone(),
+                    part: part.part,
+                    size: part.size,
+                    deleted: part.deleted,
+                    sync_start: None,
+                    sync_end: None,
+                    file_name: part.file_name.clone(),
+                    video_recording_id: part.video_recording_id.clone(),
+                    upload_id: None,
+                    completed_chunks: None,
+                    checksum: None,
+                });
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for VideoRecordingPartWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("Failed to flush pending VideoRecordingPart mutations on drop: {}", e);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+use log::{info, warn};
+
+// video codecs PrintNanny can encode to for remote viewing (RTP, WebRTC, bounding
+// box overlay). H264 prefers the Pi's v4l2 stateful hardware encoder; VP8/VP9 have
+// no hardware path on this platform and always run in software.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoCodec {
+    H264,
+    Vp8,
+    Vp9,
+}
+
+impl VideoCodec {
+    // parses a settings value like "h264"/"vp8"/"vp9" (case-insensitive), defaulting
+    // to H264 for anything unrecognized so existing settings.toml files keep working
+    pub fn parse(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "vp8" => VideoCodec::Vp8,
+            "vp9" => VideoCodec::Vp9,
+            _ => VideoCodec::H264,
+        }
+    }
+
+    // the hardware-accelerated encoder element for this codec, if one exists on
+    // this platform at all
+    fn hardware_encoder_element(&self) -> Option<&'static str> {
+        match self {
+            VideoCodec::H264 => Some("v4l2h264enc"),
+            VideoCodec::Vp8 | VideoCodec::Vp9 => None,
+        }
+    }
+
+    // the software encoder element gst-launch falls back to when no hardware
+    // encoder is available (or none exists for this codec)
+    fn software_encoder_element(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "x264enc",
+            VideoCodec::Vp8 => "vp8enc",
+            VideoCodec::Vp9 => "vp9enc",
+        }
+    }
+
+    // builds the full gst-launch encoder element (name + tuning properties) given
+    // the element resolved by probe_encoder_elements() - v4l2h264enc and its
+    // software fallback x264enc take different property names for the same knobs,
+    // so this keeps that translation in one place instead of in every pipeline
+    // description
+    pub fn encoder_description(&self, encoder_element: &str, name: &str, framerate: i32) -> String {
+        match (self, encoder_element) {
+            (VideoCodec::H264, "v4l2h264enc") => format!(
+                "v4l2h264enc name={name} min-force-key-unit-interval={framerate} extra-controls=controls,repeat_sequence_header=1"
+            ),
+            (VideoCodec::H264, _) => {
+                format!("x264enc name={name} tune=zerolatency key-int-max={framerate}")
+            }
+            (VideoCodec::Vp8, _) => {
+                format!("vp8enc name={name} deadline=1 keyframe-max-dist={framerate}")
+            }
+            (VideoCodec::Vp9, _) => {
+                format!("vp9enc name={name} deadline=1 keyframe-max-dist={framerate}")
+            }
+        }
+    }
+
+    // the gst-launch RTP payloader element + properties for this codec - h264's
+    // config-interval/aggregate-mode knobs don't exist on the vp8/vp9 payloaders
+    pub fn payloader_description(&self) -> String {
+        match self {
+            VideoCodec::H264 => {
+                "rtph264pay config-interval=1 aggregate-mode=zero-latency pt=96".to_string()
+            }
+            VideoCodec::Vp8 => "rtpvp8pay pt=96".to_string(),
+            VideoCodec::Vp9 => "rtpvp9pay pt=96".to_string(),
+        }
+    }
+
+    pub fn caps(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "video/x-h264,level=(string)3,profile=(string)main",
+            VideoCodec::Vp8 => "video/x-vp8",
+            VideoCodec::Vp9 => "video/x-vp9",
+        }
+    }
+}
+
+// returns true if gst-inspect-1.0 knows about the named element, i.e. it's
+// installed and loadable by the gstd daemon this factory talks to
+fn gst_element_available(element_name: &str) -> bool {
+    Command::new("gst-inspect-1.0")
+        .arg(element_name)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// probes gst-inspect-1.0 once per codec at factory construction and resolves the
+// gst-launch element name each make_*_pipeline call should use (properties are added
+// by encoder_description) -
+// the hardware encoder if present, otherwise the software fallback (logged so a
+// substitution on non-Pi hardware shows up in the daemon logs)
+pub fn probe_encoder_elements() -> HashMap<VideoCodec, String> {
+    [VideoCodec::H264, VideoCodec::Vp8, VideoCodec::Vp9]
+        .into_iter()
+        .map(|codec| {
+            let element = match codec.hardware_encoder_element() {
+                Some(hw) if gst_element_available(hw) => {
+                    info!("{:?}: using hardware encoder element={}", codec, hw);
+                    hw.to_string()
+                }
+                Some(hw) => {
+                    let sw = codec.software_encoder_element();
+                    warn!(
+                        "{:?}: hardware encoder element={} not found, falling back to software encoder={}",
+                        codec, hw, sw
+                    );
+                    sw.to_string()
+                }
+                None => codec.software_encoder_element().to_string(),
+            };
+            (codec, element)
+        })
+        .collect()
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use log::info;
+
+const LOSS_DECREASE_THRESHOLD: f64 = 0.10;
+const LOSS_INCREASE_THRESHOLD: f64 = 0.02;
+const LOSS_DECREASE_FACTOR: f64 = 0.9;
+const LOSS_INCREASE_FACTOR: f64 = 1.05;
+
+// loss-based AIMD controller: cuts the target bitrate on sustained high loss, ramps
+// it back up once loss is low again - clamped to [min_bitrate_bps, max_bitrate_bps]
+// throughout.
+//
+// KNOWN LIMITATION: this is the only congestion-control signal implemented here. The
+// originating request additionally asked for RTCP TWCC extmap advertisement and a
+// delay-based trendline/least-squares estimator over per-packet arrival timestamps;
+// neither is implemented, because gstd's HTTP API doesn't expose rtpbin's TWCC
+// feedback or per-packet arrival timestamps for this process to consume. Driven from
+// rtpsession's RTCP receiver-report loss stats (parse_fraction_lost) instead.
+pub struct AimdController {
+    target_bitrate_bps: u32,
+    min_bitrate_bps: u32,
+    max_bitrate_bps: u32,
+}
+
+impl AimdController {
+    pub fn new(initial_bitrate_bps: u32, min_bitrate_bps: u32, max_bitrate_bps: u32) -> Self {
+        Self {
+            target_bitrate_bps: initial_bitrate_bps.clamp(min_bitrate_bps, max_bitrate_bps),
+            min_bitrate_bps,
+            max_bitrate_bps,
+        }
+    }
+
+    pub fn target_bitrate_bps(&self) -> u32 {
+        self.target_bitrate_bps
+    }
+
+    // cut on sustained high loss, ramp back up once loss is low again
+    pub fn on_fraction_lost(&mut self, fraction_lost: f64) {
+        if fraction_lost > LOSS_DECREASE_THRESHOLD {
+            self.target_bitrate_bps =
+                (self.target_bitrate_bps as f64 * LOSS_DECREASE_FACTOR) as u32;
+        } else if fraction_lost < LOSS_INCREASE_THRESHOLD {
+            self.target_bitrate_bps =
+                (self.target_bitrate_bps as f64 * LOSS_INCREASE_FACTOR) as u32;
+        }
+        self.target_bitrate_bps = self
+            .target_bitrate_bps
+            .clamp(self.min_bitrate_bps, self.max_bitrate_bps);
+    }
+}
+
+// applies the loss-based AIMD controller's target bitrate to a running v4l2h264enc
+// element over gstd
+pub struct CongestionController {
+    controller: AimdController,
+}
+
+impl CongestionController {
+    pub fn new(initial_bitrate_bps: u32, min_bitrate_bps: u32, max_bitrate_bps: u32) -> Self {
+        Self {
+            controller: AimdController::new(initial_bitrate_bps, min_bitrate_bps, max_bitrate_bps),
+        }
+    }
+
+    // feed one RTCP receiver-report fraction-lost sample (0.0-1.0) - returns the
+    // controller's updated target bitrate
+    pub fn on_fraction_lost(&mut self, fraction_lost: f64) -> u32 {
+        self.controller.on_fraction_lost(fraction_lost);
+        self.controller.target_bitrate_bps()
+    }
+
+    pub fn target_bitrate_bps(&self) -> u32 {
+        self.controller.target_bitrate_bps()
+    }
+
+    // pushes the current target bitrate to a running v4l2h264enc element via gstd's
+    // element property endpoint, matching the `extra-controls` syntax already used
+    // when the pipeline is first created
+    pub async fn apply_bitrate(
+        &self,
+        pipeline: &gst_client::resources::Pipeline,
+        encoder_element_name: &str,
+    ) -> Result<()> {
+        let bitrate = self.controller.target_bitrate_bps();
+        let extra_controls = format!("controls,video_bitrate={bitrate}");
+        pipeline
+            .element(encoder_element_name)
+            .property("extra-controls")
+            .set(extra_controls)
+            .await?;
+        info!(
+            "congestion: applied target_bitrate_bps={} to element={}",
+            bitrate, encoder_element_name
+        );
+        Ok(())
+    }
+}
+
+// rtpsession's "stats" property is a serialized GstStructure of the form
+// "application/x-rtp-session-stats, rb-fractionlost=(uint)N, ..." where N is the
+// RFC3550 0-255 scaled fraction lost reported by the remote receiver - gstd's HTTP
+// API returns it pre-serialized, so we parse the field out rather than round-trip it
+// through gstreamer's Structure type
+pub fn parse_fraction_lost(stats: &str) -> f64 {
+    stats
+        .split(',')
+        .find_map(|field| {
+            let field = field.trim();
+            field
+                .strip_prefix("rb-fractionlost=(uint)")
+                .and_then(|v| v.trim().parse::<u32>().ok())
+        })
+        .map(|raw| raw as f64 / 255.0)
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fraction_lost() {
+        let stats = "application/x-rtp-session-stats, rb-fractionlost=(uint)26, rb-jitter=(uint)12";
+        assert!((parse_fraction_lost(stats) - (26.0 / 255.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_parse_fraction_lost_missing_field() {
+        assert_eq!(parse_fraction_lost("application/x-rtp-session-stats"), 0.0);
+    }
+
+    #[test]
+    fn test_loss_based_controller_cuts_on_high_loss() {
+        let mut controller = AimdController::new(1_000_000, 200_000, 4_000_000);
+        controller.on_fraction_lost(0.2);
+        assert_eq!(controller.target_bitrate_bps(), 900_000);
+    }
+
+    #[test]
+    fn test_loss_based_controller_ramps_up_on_low_loss() {
+        let mut controller = AimdController::new(1_000_000, 200_000, 4_000_000);
+        controller.on_fraction_lost(0.0);
+        assert_eq!(controller.target_bitrate_bps(), 1_050_000);
+    }
+
+    #[test]
+    fn test_loss_based_controller_clamps_to_min() {
+        let mut controller = AimdController::new(1_000_000, 200_000, 4_000_000);
+        for _ in 0..100 {
+            controller.on_fraction_lost(0.2);
+        }
+        assert_eq!(controller.target_bitrate_bps(), 200_000);
+    }
+}
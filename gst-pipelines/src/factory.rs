@@ -1,22 +1,140 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use gst_client::reqwest;
 use gst_client::GstClient;
-use log::{error, info};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use tokio::sync::RwLock;
 
 use printnanny_settings::printnanny::PrintNannySettings;
-use printnanny_settings::printnanny_asyncapi_models::CameraSettings;
+use printnanny_settings::printnanny_asyncapi_models::{CameraSettings, CameraSource, WebrtcSignaller};
+
+use crate::codec::{probe_encoder_elements, VideoCodec};
+use crate::congestion::{parse_fraction_lost, CongestionController};
 
 use anyhow::Result;
 
+// how often start_congestion_control polls rtpsession stats and retargets the
+// encoder's bitrate
+const CONGESTION_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+// how often supervise() polls each pipeline's gstd bus for ERROR/EOS messages
+const SUPERVISOR_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+// backoff applied before restarting a faulted pipeline, doubling on each consecutive
+// restart failure up to SUPERVISOR_MAX_BACKOFF so a pipeline stuck in a crash loop
+// doesn't hammer gstd
+const SUPERVISOR_INITIAL_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+const SUPERVISOR_MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
+// steady-state pipelines start_pipelines() creates, in dependency order (a pipeline
+// always appears after whatever interpipesrc its consumers listen-to). supervise()
+// walks this same order so a restart always recreates a producer before its
+// consumers. "recording" is deliberately excluded - its lifecycle is driven by
+// explicit start/stop commands (see message_v2.rs's recording supervisor), and
+// auto-restarting it here would fight with stop_video_recording_pipeline's EOS-based
+// finalization.
+const PIPELINE_RESTART_ORDER: &[&str] = &[
+    "camera",
+    "snapshot",
+    "snapshot_nats",
+    "h264",
+    "hls",
+    "webrtc",
+    "rtp",
+    "tflite_inference",
+    "bounding_boxes",
+    "df",
+];
+
+// pipelines whose interpipesrc listens directly on `name`'s interpipesink -
+// recreating `name` without also recreating these would leave them listening to a
+// sink that no longer exists
+fn pipeline_dependents(name: &str) -> &'static [&'static str] {
+    match name {
+        "camera" => &["snapshot", "snapshot_nats", "h264", "tflite_inference"],
+        "h264" => &["hls", "webrtc", "rtp"],
+        "tflite_inference" => &["bounding_boxes", "df"],
+        _ => &[],
+    }
+}
+
+// `name` plus everything transitively downstream of it, sorted back into
+// PIPELINE_RESTART_ORDER so restart_pipeline recreates producers before consumers
+fn affected_pipelines(name: &str) -> Vec<&'static str> {
+    let mut affected = vec![name];
+    let mut frontier = vec![name];
+    while let Some(next) = frontier.pop() {
+        for dependent in pipeline_dependents(next) {
+            if !affected.contains(dependent) {
+                affected.push(dependent);
+                frontier.push(dependent);
+            }
+        }
+    }
+    affected.sort_by_key(|name| {
+        PIPELINE_RESTART_ORDER
+            .iter()
+            .position(|candidate| candidate == name)
+            .unwrap_or(usize::MAX)
+    });
+    affected
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineHealth {
+    Healthy,
+    Restarting,
+    Failed,
+}
+
+// supervise()'s last-known view of one pipeline - cloned out via status() so a NATS
+// capability or PrintNannyService's task-status sync can report stream health
+// upstream without holding the supervisor's lock
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineStatus {
+    pub name: String,
+    pub health: PipelineHealth,
+    pub restart_count: u32,
+    pub last_fault: Option<String>,
+}
+
+impl PipelineStatus {
+    fn healthy(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            health: PipelineHealth::Healthy,
+            restart_count: 0,
+            last_fault: None,
+        }
+    }
+}
+
 pub struct PrintNannyPipelineFactory {
     pub address: String,
     pub port: i32,
     pub uri: String,
+    // resolved once at construction via codec::probe_encoder_elements() so every
+    // make_*_pipeline call reuses the same hardware-vs-software decision instead of
+    // re-probing gst-inspect-1.0 per pipeline
+    encoder_elements: HashMap<VideoCodec, String>,
+    // shared with supervise()'s background task so status() can report current
+    // pipeline health without awaiting the supervisor loop itself
+    status: Arc<RwLock<HashMap<String, PipelineStatus>>>,
 }
 
 impl PrintNannyPipelineFactory {
     pub fn new(address: String, port: i32) -> Self {
         let uri = Self::uri(&address, port);
-        Self { address, port, uri }
+        let encoder_elements = probe_encoder_elements();
+        Self {
+            address,
+            port,
+            uri,
+            encoder_elements,
+            status: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
     fn uri(address: &str, port: i32) -> String {
         format!("http://{}:{}", address, port)
@@ -30,6 +148,12 @@ impl PrintNannyPipelineFactory {
         format!("{pipeline_name}_sink")
     }
 
+    // names the v4l2h264enc element so CongestionController can retarget its bitrate
+    // at runtime via gstd's element property endpoint
+    pub fn to_encoder_name(pipeline_name: &str) -> String {
+        format!("{pipeline_name}_enc")
+    }
+
     async fn make_pipeline(
         &self,
         pipeline_name: &str,
@@ -63,10 +187,28 @@ impl PrintNannyPipelineFactory {
         Ok(pipeline)
     }
 
+    // dispatches to the libcamera or PipeWire/portal source depending on how this
+    // camera is configured - both tails end in the same capsfilter/interpipesink so
+    // every downstream pipeline (h264, snapshot, inference, ...) is source-agnostic
     async fn make_camera_pipeline(
         &self,
         pipeline_name: &str,
         camera: &CameraSettings,
+    ) -> Result<gst_client::resources::Pipeline> {
+        match &camera.source {
+            CameraSource::PipeWire { .. } => {
+                self.make_pipewire_camera_pipeline(pipeline_name, camera).await
+            }
+            CameraSource::Libcamera { .. } => {
+                self.make_libcamera_pipeline(pipeline_name, camera).await
+            }
+        }
+    }
+
+    async fn make_libcamera_pipeline(
+        &self,
+        pipeline_name: &str,
+        camera: &CameraSettings,
     ) -> Result<gst_client::resources::Pipeline> {
         let interpipesink = Self::to_interpipesink_name(pipeline_name);
         let description = format!(
@@ -82,6 +224,27 @@ impl PrintNannyPipelineFactory {
         self.make_pipeline(pipeline_name, &description).await
     }
 
+    // the PipeWire source behind org.freedesktop.portal.Camera for pipewiresrc - NOT
+    // currently usable, and makes no portal request. open_pipewire_remote() obtains the PipeWire
+    // remote fd over *this process's* D-Bus connection, but pipelines run inside the
+    // separate gstd daemon (reached over HTTP via GstClient/self.uri). File
+    // descriptors are per-process, so embedding the raw fd number in a
+    // `pipewiresrc fd=...` pipeline description handed to gstd refers to nothing (or
+    // the wrong thing) in gstd's fd table. Actually wiring this up requires passing
+    // the fd to gstd via SCM_RIGHTS over a unix socket (or running the pipeline
+    // in-process instead of through gstd); neither exists yet, so this source is
+    // rejected rather than silently producing a broken pipeline.
+    async fn make_pipewire_camera_pipeline(
+        &self,
+        _pipeline_name: &str,
+        _camera: &CameraSettings,
+    ) -> Result<gst_client::resources::Pipeline> {
+        Err(anyhow::anyhow!(
+            "PipeWire camera source is not supported: gstd runs as a separate process \
+            and pipewiresrc fds cannot be transferred to it without SCM_RIGHTS support"
+        ))
+    }
+
     async fn make_jpeg_snapshot_pipeline(
         &self,
         pipeline_name: &str,
@@ -97,38 +260,75 @@ impl PrintNannyPipelineFactory {
         self.make_pipeline(pipeline_name, &description).await
     }
 
+    // publishes each encoded JPEG directly to a NATS subject via the nats_sink
+    // element (the same custom element make_df_pipeline already uses), so a frame
+    // never touches the SD card - an alternative to make_jpeg_snapshot_pipeline's
+    // disk-backed multifilesink, not a replacement for it
+    async fn make_jpeg_snapshot_nats_pipeline(
+        &self,
+        pipeline_name: &str,
+        listen_to: &str,
+        nats_server_uri: &str,
+        nats_subject: &str,
+    ) -> Result<gst_client::resources::Pipeline> {
+
+        let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
+        let listen_to = Self::to_interpipesink_name(listen_to);
+
+        let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false num-buffers=2 leaky-type=2 \
+            ! v4l2jpegenc ! nats_sink nats-address={nats_server_uri} nats-subject={nats_subject}");
+        self.make_pipeline(pipeline_name, &description).await
+    }
+
     async fn make_h264_pipeline(
         &self,
         pipeline_name: &str,
         listen_to: &str,
         framerate: &i32,
+        codec: VideoCodec,
     ) -> Result<gst_client::resources::Pipeline> {
 
         let listen_to = Self::to_interpipesink_name(listen_to);
         let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
         let interpipesink = Self::to_interpipesink_name(pipeline_name);
+        let encoder_name = Self::to_encoder_name(pipeline_name);
+        let encoder_element = &self.encoder_elements[&codec];
+        let encoder = codec.encoder_description(encoder_element, &encoder_name, *framerate);
+        let parse = Self::parser_element(codec);
+        let caps = codec.caps();
 
         let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false \
             ! v4l2convert \
-            ! v4l2h264enc min-force-key-unit-interval={framerate} extra-controls=controls,repeat_sequence_header=1 \
-            ! h264parse \
-            ! capsfilter caps=video/x-h264,level=(string)3,profile=(string)main \
+            ! {encoder} \
+            ! {parse} \
+            ! capsfilter caps={caps} \
             ! interpipesink name={interpipesink} sync=false");
         self.make_pipeline(pipeline_name, &description).await
     }
 
+    // the parse element that normalizes each codec's encoder output into
+    // discrete, payload-ready frames, mirroring h264parse's existing role
+    fn parser_element(codec: VideoCodec) -> &'static str {
+        match codec {
+            VideoCodec::H264 => "h264parse",
+            VideoCodec::Vp8 | VideoCodec::Vp9 => "identity",
+        }
+    }
+
     async fn make_rtp_pipeline(
         &self,
         pipeline_name: &str,
         listen_to: &str,
         port: i32,
+        codec: VideoCodec,
     ) -> Result<gst_client::resources::Pipeline> {
 
         let listen_to = Self::to_interpipesink_name(listen_to);
         let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
+        let payloader = codec.payloader_description();
 
         let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false \
-            ! rtph264pay config-interval=1 aggregate-mode=zero-latency pt=96 \
+            ! {payloader} \
             ! udpsink port={port}");
         self.make_pipeline(pipeline_name, &description).await
     }
@@ -150,6 +350,43 @@ impl PrintNannyPipelineFactory {
         self.make_pipeline(pipeline_name, &description).await
     }
 
+    // feeds the h264 interpipesink into webrtcsink for sub-second browser-native
+    // playback, skipping the relay/transcode hop that RTP and HLS both require.
+    // signaller selects whether webrtcsink talks WHIP directly to a plain HTTP
+    // endpoint or joins a Janus VideoRoom (offer/answer negotiation happens inside
+    // webrtcsink/the signaller plugin, not here)
+    async fn make_webrtc_pipeline(
+        &self,
+        pipeline_name: &str,
+        listen_to: &str,
+        signaller: &WebrtcSignaller,
+        stun_server: &str,
+        turn_server: &str,
+        codec: VideoCodec,
+    ) -> Result<gst_client::resources::Pipeline> {
+
+        let listen_to = Self::to_interpipesink_name(listen_to);
+        let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
+        let video_caps = codec.caps();
+
+        let signaller_props = match signaller {
+            WebrtcSignaller::Whip { whip_url } => {
+                format!("signaller::whip-endpoint={whip_url}")
+            }
+            WebrtcSignaller::Janus {
+                janus_endpoint,
+                room_id,
+                feed_id,
+            } => format!(
+                "signaller::signaller-type=janus-videoroom signaller::janus-endpoint={janus_endpoint} signaller::room-id={room_id} signaller::feed-id={feed_id}"
+            ),
+        };
+
+        let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false \
+            ! webrtcsink name={pipeline_name}_webrtc stun-server={stun_server} turn-server={turn_server} video-caps={video_caps} {signaller_props}");
+        self.make_pipeline(pipeline_name, &description).await
+    }
+
     async fn make_inference_pipeline(
         &self,
         pipeline_name: &str,
@@ -184,18 +421,32 @@ impl PrintNannyPipelineFactory {
         tensor_height: i32,
         tflite_label_file: &str,
         port: i32,
+        codec: VideoCodec,
     ) -> Result<gst_client::resources::Pipeline> {
 
         let listen_to = Self::to_interpipesink_name(listen_to);
         let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
+        let encoder_name = Self::to_encoder_name(pipeline_name);
+        let encoder_element = &self.encoder_elements[&codec];
+        // overlay encoder keeps the original output-io-mode/capture-io-mode mmap
+        // hint when running on the hardware encoder; the software fallback doesn't
+        // have an equivalent so it's dropped rather than guessed at
+        let encoder = if encoder_element.as_str() == "v4l2h264enc" {
+            format!("v4l2h264enc name={encoder_name} output-io-mode=mmap capture-io-mode=mmap extra-controls=controls,repeat_sequence_header=1")
+        } else {
+            codec.encoder_description(encoder_element, &encoder_name, 30)
+        };
+        let parse = Self::parser_element(codec);
+        let caps = codec.caps();
+        let payloader = codec.payloader_description();
 
         let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false \
             ! tensor_decoder mode=bounding_boxes option1=mobilenet-ssd-postprocess option2={tflite_label_file} option3=0:1:2:3,{nms_threshold} option4={video_width}:{video_height} option5={tensor_width}:{tensor_height} \
             ! videoconvert \
-            ! v4l2h264enc output-io-mode=mmap capture-io-mode=mmap extra-controls=controls,repeat_sequence_header=1 \
-            ! h264parse \
-            ! capsfilter caps=video/x-h264,level=(string)3,profile=(string)main \
-            ! rtph264pay config-interval=1 aggregate-mode=zero-latency pt=96 \
+            ! {encoder} \
+            ! {parse} \
+            ! capsfilter caps={caps} \
+            ! {payloader} \
             ! udpsink port={port}
             ");
         self.make_pipeline(pipeline_name, &description).await
@@ -207,6 +458,7 @@ impl PrintNannyPipelineFactory {
         listen_to: &str,
         nms_threshold: i32,
         nats_server_uri: &str,
+        nats_subject: &str,
     ) -> Result<gst_client::resources::Pipeline> {
         let nms_threshold = nms_threshold as f32 / 100_f32;
 
@@ -216,18 +468,249 @@ impl PrintNannyPipelineFactory {
         let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false \
             ! tensor_decoder mode=custom-code option1=printnanny_bb_dataframe_decoder \
             ! dataframe_agg filter-threshold={nms_threshold} output-type=json |
-            ! nats_sink nats-address={nats_server_uri}");
+            ! nats_sink nats-address={nats_server_uri} nats-subject={nats_subject}");
         self.make_pipeline(pipeline_name, &description).await
     }
 
+    // splitmuxsink's location pattern for a recording's numbered parts, written
+    // alongside mp4_file_name so the NATS recording supervisor can list them back out
+    // by prefix once the recording is stopped
+    fn recording_segment_location(mp4_file_name: &str) -> String {
+        format!("{mp4_file_name}.part-%05d.mp4")
+    }
+
+    // branches a segmented MP4 recording off the h264 pipeline's sink. Pads are only
+    // flushed/finalized once stop_video_recording_pipeline() sends EOS - until then
+    // splitmuxsink's current part has no moov atom and isn't a playable file yet.
+    pub async fn start_video_recording_pipeline(
+        &self,
+        mp4_file_name: &str,
+    ) -> Result<gst_client::resources::Pipeline> {
+        let pipeline_name = "recording";
+        let listen_to = Self::to_interpipesink_name("h264");
+        let interpipesrc = Self::to_interpipesrc_name(pipeline_name);
+        let location = Self::recording_segment_location(mp4_file_name);
+
+        let description = format!("interpipesrc name={interpipesrc} listen-to={listen_to} accept-events=false accept-eos-event=false is-live=true allow-renegotiation=false format=time \
+            ! h264parse \
+            ! splitmuxsink name={pipeline_name}_mux location={location} max-size-time=0 muxer=mp4mux");
+        let pipeline = self.make_pipeline(pipeline_name, &description).await?;
+        pipeline.play().await?;
+        Ok(pipeline)
+    }
+
+    // sends EOS through the recording pipeline so splitmuxsink flushes and finalizes
+    // its current part before the recording is considered stopped
+    pub async fn stop_video_recording_pipeline(&self) -> Result<()> {
+        let client = GstClient::build(&self.uri)?;
+        let pipeline = client.pipeline("recording");
+        pipeline.eos().await?;
+        Ok(())
+    }
+
+    // polls the RTP pipeline's rtpsession stats and feeds the reported fraction-lost
+    // into a CongestionController, applying the resulting target bitrate to the h264
+    // encoder on an interval.
+    //
+    // KNOWN LIMITATION: the congestion-control request this implements called for RTCP
+    // TWCC extmap advertisement plus a delay-based trendline/least-squares estimator
+    // built from per-packet send/arrival timestamps, with loss-based AIMD as a third,
+    // secondary signal. Only the AIMD controller is implemented. gstd doesn't expose
+    // rtpbin's "on-feedback-rtcp"/TWCC signal (or per-packet arrival timestamps) over
+    // its HTTP API, so neither the extmap advertisement nor the trendline estimator
+    // can be fed real data through this control plane - an earlier attempt shipped
+    // both as unreachable code before being removed. rtpsession0's RTCP receiver-report
+    // loss stats are the only live signal gstd's HTTP API actually surfaces, so
+    // loss-based AIMD is the only congestion control this can offer until gstd (or a
+    // replacement control plane with direct rtpbin access) exposes TWCC feedback.
+    pub async fn start_congestion_control(
+        &self,
+        rtp_pipeline_name: &str,
+        h264_pipeline_name: &str,
+        min_bitrate_bps: u32,
+        initial_bitrate_bps: u32,
+        max_bitrate_bps: u32,
+    ) -> Result<()> {
+        let uri = self.uri.clone();
+        let rtp_pipeline_name = rtp_pipeline_name.to_string();
+        let encoder = Self::to_encoder_name(h264_pipeline_name);
+
+        tokio::spawn(async move {
+            let client = match GstClient::build(&uri) {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("congestion: failed to build GstClient: {}", e);
+                    return;
+                }
+            };
+            let pipeline = client.pipeline(&rtp_pipeline_name);
+            let mut controller =
+                CongestionController::new(initial_bitrate_bps, min_bitrate_bps, max_bitrate_bps);
+            let mut interval = tokio::time::interval(CONGESTION_POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+                let stats = match pipeline
+                    .element("rtpsession0")
+                    .property("stats")
+                    .get::<String>()
+                    .await
+                {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        debug!("congestion: failed to read rtpsession stats: {}", e);
+                        continue;
+                    }
+                };
+
+                controller.on_fraction_lost(parse_fraction_lost(&stats));
+                if let Err(e) = controller.apply_bitrate(&pipeline, &encoder).await {
+                    error!("congestion: failed to apply target bitrate: {}", e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    // current supervisor-reported health of every pipeline supervise() has observed -
+    // empty until supervise() has completed at least one poll interval
+    pub async fn status(&self) -> Vec<PipelineStatus> {
+        self.status.read().await.values().cloned().collect()
+    }
+
+    async fn mark_healthy(&self, name: &str) {
+        let mut status = self.status.write().await;
+        let entry = status
+            .entry(name.to_string())
+            .or_insert_with(|| PipelineStatus::healthy(name));
+        entry.health = PipelineHealth::Healthy;
+        entry.last_fault = None;
+    }
+
+    async fn mark_restarting(&self, name: &str, fault: &str) {
+        let mut status = self.status.write().await;
+        let entry = status
+            .entry(name.to_string())
+            .or_insert_with(|| PipelineStatus::healthy(name));
+        entry.health = PipelineHealth::Restarting;
+        entry.restart_count += 1;
+        entry.last_fault = Some(fault.to_string());
+    }
+
+    async fn mark_failed(&self, name: &str, fault: &str) {
+        let mut status = self.status.write().await;
+        let entry = status
+            .entry(name.to_string())
+            .or_insert_with(|| PipelineStatus::healthy(name));
+        entry.health = PipelineHealth::Failed;
+        entry.last_fault = Some(fault.to_string());
+    }
+
+    // reads `name`'s gstd bus once and returns a human-readable fault description if
+    // an ERROR or unexpected EOS message is pending. A bus read request that fails
+    // outright (gstd restarted, pipeline missing, ...) is treated as a fault too, so
+    // the supervisor reacts to a dead daemon the same way it reacts to a dead
+    // pipeline.
+    async fn poll_pipeline_fault(client: &GstClient, name: &str) -> Option<String> {
+        let pipeline = client.pipeline(name);
+        match pipeline.bus().read().await {
+            Ok(message) if message.to_lowercase().contains("gst_message_error") => {
+                Some(format!("bus reported error: {}", message))
+            }
+            Ok(message) if message.to_lowercase().contains("gst_message_eos") => {
+                Some(format!("bus reported unexpected eos: {}", message))
+            }
+            Ok(_) => None,
+            Err(e) => Some(format!("failed to read bus: {}", e)),
+        }
+    }
+
+    // tears down `name` and everything downstream of it (affected_pipelines), then
+    // recreates them by re-running start_pipelines() - which already builds
+    // pipelines in dependency order and treats an existing pipeline as a no-op (see
+    // make_pipeline's CONFLICT handling), so anything not torn down here is left
+    // untouched
+    async fn restart_pipeline(&self, name: &str) -> Result<()> {
+        let client = GstClient::build(&self.uri)?;
+        for affected in affected_pipelines(name) {
+            let pipeline = client.pipeline(affected);
+            if let Err(e) = pipeline.stop().await {
+                debug!(
+                    "supervisor: pipeline={} stop before restart failed (may already be stopped): {}",
+                    affected, e
+                );
+            }
+            if let Err(e) = pipeline.delete().await {
+                debug!(
+                    "supervisor: pipeline={} delete before restart failed (may already be gone): {}",
+                    affected, e
+                );
+            }
+        }
+        self.start_pipelines().await
+    }
+
+    // periodically polls every pipeline in PIPELINE_RESTART_ORDER through GstClient,
+    // watching each one's gstd bus for ERROR/EOS messages. On fault, tears down and
+    // recreates the failed pipeline and everything downstream of it with exponential
+    // backoff between attempts, so a single flaky libcamerasrc or a gstd restart
+    // doesn't leave the stream dead until the whole daemon is restarted. Takes
+    // `Arc<Self>` (rather than `&self`) because the supervisor loop needs to outlive
+    // this call and call back into `restart_pipeline`/`start_pipelines`.
+    pub async fn supervise(self: Arc<Self>) -> Result<()> {
+        let client = GstClient::build(&self.uri)?;
+
+        tokio::spawn(async move {
+            let mut backoff: HashMap<&str, std::time::Duration> = HashMap::new();
+            let mut interval = tokio::time::interval(SUPERVISOR_POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+                for &name in PIPELINE_RESTART_ORDER {
+                    let fault = match Self::poll_pipeline_fault(&client, name).await {
+                        Some(fault) => fault,
+                        None => {
+                            self.mark_healthy(name).await;
+                            continue;
+                        }
+                    };
+
+                    let delay = backoff.get(name).copied().unwrap_or(SUPERVISOR_INITIAL_BACKOFF);
+                    warn!(
+                        "supervisor: pipeline={} faulted ({}), restarting in {:?}",
+                        name, fault, delay
+                    );
+                    self.mark_restarting(name, &fault).await;
+                    tokio::time::sleep(delay).await;
+
+                    match self.restart_pipeline(name).await {
+                        Ok(()) => {
+                            backoff.remove(name);
+                            self.mark_healthy(name).await;
+                            info!("supervisor: restarted pipeline={}", name);
+                        }
+                        Err(e) => {
+                            error!("supervisor: failed to restart pipeline={}: {}", name, e);
+                            backoff.insert(name, (delay * 2).min(SUPERVISOR_MAX_BACKOFF));
+                            self.mark_failed(name, &e.to_string()).await;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
     pub async fn start_pipelines(&self) -> Result<()> {
         let settings = PrintNannySettings::new()?;
         let snapshot_settings = *settings.video_stream.snapshot;
         let camera = *settings.video_stream.camera;
         let hls_settings = *settings.video_stream.hls;
         let rtp_settings = *settings.video_stream.rtp;
+        let webrtc_settings = *settings.video_stream.webrtc;
 
         let detection_settings = *settings.video_stream.detection;
+        let codec = VideoCodec::parse(&camera.video_codec);
 
         let camera_pipeline_name = "camera";
         let camera_pipeline = self
@@ -247,9 +730,27 @@ impl PrintNannyPipelineFactory {
             snapshot_pipeline.play().await?;
         }
 
+        if snapshot_settings.nats_enabled {
+            let snapshot_nats_pipeline_name = "snapshot_nats";
+            let snapshot_nats_pipeline = self
+                .make_jpeg_snapshot_nats_pipeline(
+                    snapshot_nats_pipeline_name,
+                    camera_pipeline_name,
+                    &snapshot_settings.nats_server_uri,
+                    &snapshot_settings.nats_subject,
+                )
+                .await?;
+            snapshot_nats_pipeline.play().await?;
+        }
+
         let h264_pipeline_name = "h264";
         let h264_pipeline = self
-            .make_h264_pipeline(h264_pipeline_name, camera_pipeline_name, &camera.framerate)
+            .make_h264_pipeline(
+                h264_pipeline_name,
+                camera_pipeline_name,
+                &camera.framerate,
+                codec,
+            )
             .await?;
         h264_pipeline.play().await?;
 
@@ -267,16 +768,43 @@ impl PrintNannyPipelineFactory {
             hls_pipeline.play().await?;
         }
 
+        if webrtc_settings.enabled {
+            let webrtc_pipeline_name = "webrtc";
+            let webrtc_pipeline = self
+                .make_webrtc_pipeline(
+                    webrtc_pipeline_name,
+                    h264_pipeline_name,
+                    &webrtc_settings.signaller,
+                    &webrtc_settings.stun_server,
+                    &webrtc_settings.turn_server,
+                    codec,
+                )
+                .await?;
+            webrtc_pipeline.play().await?;
+        }
+
         let rtp_pipeline_name = "rtp";
         let rtp_pipeline = self
             .make_rtp_pipeline(
                 rtp_pipeline_name,
                 h264_pipeline_name,
                 rtp_settings.video_udp_port,
+                codec,
             )
             .await?;
         rtp_pipeline.play().await?;
 
+        if rtp_settings.congestion_control_enabled {
+            self.start_congestion_control(
+                rtp_pipeline_name,
+                h264_pipeline_name,
+                rtp_settings.min_bitrate_bps,
+                rtp_settings.initial_bitrate_bps,
+                rtp_settings.max_bitrate_bps,
+            )
+            .await?;
+        }
+
         let inference_pipeline_name = "tflite_inference";
         let inference_pipeline = self
             .make_inference_pipeline(
@@ -301,6 +829,7 @@ impl PrintNannyPipelineFactory {
                 detection_settings.tensor_height,
                 &detection_settings.label_file,
                 rtp_settings.overlay_udp_port,
+                codec,
             )
             .await?;
         bb_pipeline.play().await?;
@@ -312,6 +841,7 @@ impl PrintNannyPipelineFactory {
                 inference_pipeline_name,
                 detection_settings.nms_threshold,
                 &detection_settings.nats_server_uri,
+                &detection_settings.nats_subject,
             )
             .await?;
 
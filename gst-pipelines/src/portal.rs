@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, RawFd};
+
+use anyhow::{anyhow, Result};
+use printnanny_dbus::zbus;
+use printnanny_dbus::zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+// minimal client for the xdg-desktop-portal Camera interface
+// (org.freedesktop.portal.Camera), to request access to a camera the portal is
+// brokering, e.g. in a sandboxed environment or when the camera is already opened by
+// another process over PipeWire. make_pipewire_camera_pipeline does not call it: it
+// rejects the PipeWire source because gstd cannot use this process's fds
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Camera",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait CameraPortal {
+    #[zbus(property)]
+    fn is_camera_present(&self) -> zbus::Result<bool>;
+
+    fn access_camera(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
+
+    fn open_pipe_wire_remote(
+        &self,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+}
+
+// AccessCamera doesn't return the grant result directly - it hands back a request
+// object path that emits exactly one Response signal once the user (or policy) has
+// decided whether to allow access
+#[zbus::proxy(interface = "org.freedesktop.portal.Request")]
+trait PortalRequest {
+    #[zbus(signal)]
+    fn response(&self, response: u32, results: HashMap<String, OwnedValue>) -> zbus::Result<()>;
+}
+
+// requests camera access through the desktop portal and returns the PipeWire remote
+// file descriptor pipewiresrc needs to connect to the portal-brokered node, together
+// with the PipeWire node id the portal returns in the AccessCamera results under
+// "camera_node".
+pub async fn open_pipewire_remote() -> Result<(RawFd, u32)> {
+    let connection = zbus::Connection::session().await?;
+    let portal = CameraPortalProxy::new(&connection).await?;
+
+    if !portal.is_camera_present().await? {
+        return Err(anyhow!(
+            "xdg-desktop-portal reports no camera is present to share"
+        ));
+    }
+
+    let handle = portal.access_camera(HashMap::new()).await?;
+    let request = PortalRequestProxy::builder(&connection)
+        .path(handle)?
+        .build()
+        .await?;
+
+    let mut responses = request.receive_response().await?;
+    let signal = responses.next().await.ok_or_else(|| {
+        anyhow!("xdg-desktop-portal closed the AccessCamera request without a response")
+    })?;
+    let args = signal.args()?;
+    if args.response != 0 {
+        return Err(anyhow!(
+            "xdg-desktop-portal denied camera access (response code {})",
+            args.response
+        ));
+    }
+
+    let node_id = args
+        .results
+        .get("camera_node")
+        .and_then(|v| u32::try_from(v.clone()).ok())
+        .ok_or_else(|| {
+            anyhow!("xdg-desktop-portal's AccessCamera response had no camera_node id")
+        })?;
+
+    let owned_fd = portal.open_pipe_wire_remote(HashMap::new()).await?;
+    let fd = owned_fd.as_raw_fd();
+    // leak the OwnedFd so the returned RawFd stays open for the caller; the fd number
+    // is only meaningful inside this process
+    std::mem::forget(owned_fd);
+    Ok((fd, node_id))
+}
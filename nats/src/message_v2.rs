@@ -1,22 +1,30 @@
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::process::Command;
 use std::time::SystemTime;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::prelude::*;
+use futures::StreamExt;
+use lazy_static::lazy_static;
 use log::{error, info, warn};
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::PrometheusBuilder;
 use printnanny_settings::cam::CameraVideoSource;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
+use tokio::io::AsyncBufReadExt;
 
 use printnanny_dbus::printnanny_asyncapi_models;
 use printnanny_dbus::printnanny_asyncapi_models::{
     CameraRecordingLoadReply, CameraRecordingStarted, CameraRecordingStopped, CamerasLoadReply,
     CrashReportOsLogsReply, CrashReportOsLogsRequest, DeviceInfoLoadReply,
     PrintNannyCloudAuthReply, PrintNannyCloudAuthRequest, PrintNannyCloudSyncReply, SettingsApp,
-    SettingsFile, SettingsFileApplyReply, SettingsFileApplyRequest, SettingsFileLoadReply,
+    SettingsFile, SettingsFileApplyBatchReply, SettingsFileApplyBatchRequest,
+    SettingsFileApplyReply, SettingsFileApplyRequest, SettingsFileLoadReply,
     SettingsFileRevertReply, SettingsFileRevertRequest, SystemdManagerDisableUnitsReply,
     SystemdManagerEnableUnitsReply, SystemdManagerGetUnitFileStateReply,
     SystemdManagerGetUnitReply, SystemdManagerGetUnitRequest, SystemdManagerRestartUnitReply,
@@ -24,14 +32,15 @@ use printnanny_dbus::printnanny_asyncapi_models::{
     SystemdManagerStopUnitReply, SystemdManagerStopUnitRequest, SystemdManagerUnitFilesRequest,
     SystemdUnitChange, SystemdUnitChangeState, SystemdUnitFileState, VideoStreamSettings,
 };
-use printnanny_dbus::systemd1::models::PRINTNANNY_RECORDING_SERVICE_TEMPLATE;
-
 use printnanny_dbus::zbus;
 use printnanny_dbus::zbus_systemd;
 
+use notify::Watcher;
 use printnanny_settings::git2;
 use printnanny_settings::printnanny::PrintNannySettings;
-use printnanny_settings::vcs::VersionControlledSettings;
+use printnanny_settings::vcs::{
+    GitFastForwardOutcome, GitFileDiff, VersionControlledSettings, VersionControlledSettingsError,
+};
 
 use printnanny_services::printnanny_api::ApiService;
 
@@ -71,6 +80,32 @@ pub enum NatsRequest {
     #[serde(rename = "pi.{pi_id}.command.cloud.sync")]
     PrintNannyCloudSyncRequest,
 
+    // pi.{pi_id}.command.camera.recording.sync.progress
+    // subscribes a controller to progress updates published while a recording uploads
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.progress")]
+    CameraRecordingSyncProgressRequest,
+
+    // pi.{pi_id}.command.camera.recording.sync.enqueue
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.enqueue")]
+    CloudSyncEnqueueRequest(CloudSyncEnqueueRequest),
+
+    // pi.{pi_id}.command.camera.recording.sync.queue
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.queue")]
+    CloudSyncQueueStateRequest,
+
+    // pi.{pi_id}.command.camera.recording.sync.cancel
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.cancel")]
+    CloudSyncCancelRequest(CloudSyncCancelRequest),
+
+    // pi.{pi_id}.command.job.status
+    #[serde(rename = "pi.{pi_id}.command.job.status")]
+    JobStatusRequest(JobStatusRequest),
+
+    // pi.{pi_id}.command.job.progress
+    // subscribes a controller to progress updates checkpointed by the job manager
+    #[serde(rename = "pi.{pi_id}.command.job.progress")]
+    JobProgressRequest,
+
     // pi.{pi_id}.crash_reports.os
     #[serde(rename = "pi.{pi_id}.crash_reports.os")]
     CrashReportOsLogsRequest(CrashReportOsLogsRequest),
@@ -86,8 +121,24 @@ pub enum NatsRequest {
     SettingsFileLoadRequest,
     #[serde(rename = "pi.{pi_id}.settings.file.apply")]
     SettingsFileApplyRequest(SettingsFileApplyRequest),
+    #[serde(rename = "pi.{pi_id}.settings.file.apply.batch")]
+    SettingsFileApplyBatchRequest(SettingsFileApplyBatchRequest),
     #[serde(rename = "pi.{pi_id}.settings.file.revert")]
     SettingsFileRevertRequest(SettingsFileRevertRequest),
+    #[serde(rename = "pi.{pi_id}.settings.file.diff")]
+    SettingsFileDiffRequest(SettingsFileDiffRequest),
+
+    #[serde(rename = "pi.{pi_id}.settings.remote.sync")]
+    SettingsRemoteSyncRequest(SettingsRemoteSyncRequest),
+    #[serde(rename = "pi.{pi_id}.settings.remote.push")]
+    SettingsRemotePushRequest(SettingsRemotePushRequest),
+    #[serde(rename = "pi.{pi_id}.settings.remote.pull")]
+    SettingsRemotePullRequest(SettingsRemotePullRequest),
+
+    #[serde(rename = "pi.{pi_id}.settings.watcher.start")]
+    SettingsWatcherStartRequest(SettingsWatcherStartRequest),
+    #[serde(rename = "pi.{pi_id}.settings.watcher.stop")]
+    SettingsWatcherStopRequest(SettingsWatcherStopRequest),
 
     #[serde(rename = "pi.{pi_id}.settings.camera.apply")]
     CameraSettingsFileApplyRequest(VideoStreamSettings),
@@ -112,6 +163,28 @@ pub enum NatsRequest {
     SystemdManagerStartUnitRequest(SystemdManagerStartUnitRequest),
     #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit")]
     SystemdManagerStopUnitRequest(SystemdManagerStopUnitRequest),
+    #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs")]
+    SystemdManagerStreamUnitLogsRequest(SystemdManagerStreamUnitLogsRequest),
+    #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits")]
+    SystemdManagerApplyUnitsRequest(SystemdManagerApplyUnitsRequest),
+
+    // pi.{pi_id}.command.recording.supervisor.start
+    // starts the detection-driven recording lifecycle: watches the df pipeline's
+    // dataframe output and starts/stops a recording based on detection confidence,
+    // mirroring SystemdManagerStreamUnitLogsRequest's enqueue-and-return-subjects shape
+    #[serde(rename = "pi.{pi_id}.command.recording.supervisor.start")]
+    RecordingSupervisorStartRequest,
+
+    // pi.{pi_id}.capabilities
+    // answers with this daemon's protocol version and the subject_patterns it
+    // implements, so clients can feature-gate against what's actually installed
+    #[serde(rename = "pi.{pi_id}.capabilities")]
+    CapabilitiesRequest,
+
+    // constructed by deserialize_payload_inner when a subject_pattern isn't
+    // recognized by this build, so handle() can reply with structured capability
+    // info instead of bubbling up a bare deserialize error
+    UnsupportedCapabilityRequest(String),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -136,6 +209,30 @@ pub enum NatsReply {
     #[serde(rename = "pi.{pi_id}.command.cloud.sync")]
     PrintNannyCloudSyncReply(PrintNannyCloudSyncReply),
 
+    // pi.{pi_id}.command.camera.recording.sync.progress
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.progress")]
+    CameraRecordingSyncProgressReply(CameraRecordingSyncProgress),
+
+    // pi.{pi_id}.command.camera.recording.sync.enqueue
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.enqueue")]
+    CloudSyncEnqueueReply(CloudSyncEnqueueReply),
+
+    // pi.{pi_id}.command.camera.recording.sync.queue
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.queue")]
+    CloudSyncQueueStateReply(CloudSyncQueueStateReply),
+
+    // pi.{pi_id}.command.camera.recording.sync.cancel
+    #[serde(rename = "pi.{pi_id}.command.camera.recording.sync.cancel")]
+    CloudSyncCancelReply(CloudSyncCancelReply),
+
+    // pi.{pi_id}.command.job.status
+    #[serde(rename = "pi.{pi_id}.command.job.status")]
+    JobStatusReply(JobState),
+
+    // pi.{pi_id}.command.job.progress
+    #[serde(rename = "pi.{pi_id}.command.job.progress")]
+    JobProgressReply(JobProgress),
+
     // pi.{pi_id}.crash_reports.os
     #[serde(rename = "pi.{pi_id}.crash_reports.os")]
     CrashReportOsLogsReply(CrashReportOsLogsReply),
@@ -151,11 +248,27 @@ pub enum NatsReply {
     SettingsFileLoadReply(SettingsFileLoadReply),
     #[serde(rename = "pi.{pi_id}.settings.printnanny.apply")]
     SettingsFileApplyReply(SettingsFileApplyReply),
+    #[serde(rename = "pi.{pi_id}.settings.file.apply.batch")]
+    SettingsFileApplyBatchReply(SettingsFileApplyBatchReply),
     #[serde(rename = "pi.{pi_id}.settings.printnanny.revert")]
     SettingsFileRevertReply(SettingsFileRevertReply),
+    #[serde(rename = "pi.{pi_id}.settings.file.diff")]
+    SettingsFileDiffReply(SettingsFileDiffReply),
+
+    #[serde(rename = "pi.{pi_id}.settings.remote.sync")]
+    SettingsRemoteSyncReply(SettingsRemoteSyncReply),
+    #[serde(rename = "pi.{pi_id}.settings.remote.push")]
+    SettingsRemotePushReply(SettingsRemotePushReply),
+    #[serde(rename = "pi.{pi_id}.settings.remote.pull")]
+    SettingsRemotePullReply(SettingsRemotePullReply),
+
+    #[serde(rename = "pi.{pi_id}.settings.watcher.start")]
+    SettingsWatcherStartReply(SettingsWatcherStartReply),
+    #[serde(rename = "pi.{pi_id}.settings.watcher.stop")]
+    SettingsWatcherStopReply(SettingsWatcherStopReply),
 
     #[serde(rename = "pi.{pi_id}.settings.camera.apply")]
-    CameraSettingsFileApplyReply(VideoStreamSettings),
+    CameraSettingsFileApplyReply(CameraSettingsApplyResult),
     #[serde(rename = "pi.{pi_id}.settings.camera.load")]
     CameraSettingsFileLoadReply(VideoStreamSettings),
 
@@ -177,265 +290,2062 @@ pub enum NatsReply {
     SystemdManagerStartUnitReply(SystemdManagerStartUnitReply),
     #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit")]
     SystemdManagerStopUnitReply(SystemdManagerStopUnitReply),
-}
+    #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs")]
+    SystemdManagerStreamUnitLogsReply(SystemdManagerStreamUnitLogsReply),
+    #[serde(rename = "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits")]
+    SystemdManagerApplyUnitsReply(SystemdManagerApplyUnitsReply),
 
-impl NatsRequest {
-    pub fn handle_camera_recording_load() -> Result<NatsReply> {
-        let recordings: Vec<printnanny_asyncapi_models::VideoRecording> =
-            printnanny_edge_db::video_recording::VideoRecording::get_all()?
-                .into_iter()
-                .map(|v| (v).into())
-                .collect();
-        let current = printnanny_edge_db::video_recording::VideoRecording::get_current()?
-            .map(|v| Box::new(v.into()));
-        Ok(NatsReply::CameraRecordingLoadReply(
-            CameraRecordingLoadReply {
-                recordings,
-                current,
-            },
-        ))
-    }
+    // pi.{pi_id}.command.recording.supervisor.start
+    #[serde(rename = "pi.{pi_id}.command.recording.supervisor.start")]
+    RecordingSupervisorStartReply(RecordingSupervisorStartReply),
 
-    pub async fn handle_camera_recording_start() -> Result<NatsReply> {
-        let recording = printnanny_edge_db::video_recording::VideoRecording::start_new()?;
-        let factory = PrintNannyPipelineFactory::default();
-        factory
-            .start_video_recording_pipeline(&recording.mp4_file_name)
-            .await?;
-        let now = Utc::now();
-        let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
-            recording_status: Some("inprogress"),
-            recording_start: Some(&now),
-            deleted: None,
-            gcode_file_name: None,
-            recording_end: None,
-            mp4_upload_url: None,
-            mp4_download_url: None,
-            cloud_sync_percent: None,
-            cloud_sync_status: None,
-            cloud_sync_start: None,
-            cloud_sync_end: None,
-        };
-        printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
-        let recording =
-            printnanny_edge_db::video_recording::VideoRecording::get_by_id(&recording.id)?;
+    // pi.{pi_id}.capabilities
+    #[serde(rename = "pi.{pi_id}.capabilities")]
+    CapabilitiesReply(CapabilitiesReply),
 
-        Ok(NatsReply::CameraRecordingStartReply(
-            CameraRecordingStarted {
-                recording: Box::new(recording.into()),
-            },
-        ))
-    }
+    #[serde(rename = "pi.{pi_id}.unsupported_capability")]
+    UnsupportedCapabilityReply(UnsupportedCapabilityReply),
+}
 
-    pub async fn handle_camera_recording_stop() -> Result<NatsReply> {
-        let recording = printnanny_edge_db::video_recording::VideoRecording::get_current()?;
-        let factory = PrintNannyPipelineFactory::default();
+// bumped whenever a breaking change lands in NatsRequest/NatsReply - clients compare
+// this against their own expectations after a CapabilitiesRequest, and `capabilities`
+// tells them exactly which subject_patterns this daemon build accepts
+pub const NATS_PROTOCOL_VERSION: u32 = 1;
 
-        // send EOS signal to gstreamer
-        factory.stop_video_recording_pipeline().await?;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CapabilitiesReply {
+    pub protocol_version: u32,
+    pub capabilities: Vec<String>,
+}
 
-        // start cloud sync service
-        let settings = PrintNannySettings::new()?;
-        if settings.video_stream.recording.cloud_sync {
-            match &recording {
-                Some(recording) => {
-                    let connection = zbus::Connection::system().await?;
-                    let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
-                    let unit_name = format!(
-                        "{PRINTNANNY_RECORDING_SERVICE_TEMPLATE}{}.service",
-                        recording.id
-                    );
-                    info!("Attempting to start {}", &unit_name);
-                    // ref: https://www.freedesktop.org/wiki/Software/systemd/dbus/
-                    // StartUnit() enqeues a start job, and possibly depending jobs. Takes the unit to activate, plus a mode string.
-                    // The mode needs to be one of replace, fail, isolate, ignore-dependencies, ignore-requirements.
-                    // If "replace" the call will start the unit and its dependencies, possibly replacing already queued jobs that conflict with this.
-                    // If "fail" the call will start the unit and its dependencies, but will fail if this would change an already queued job.
-                    // If "isolate" the call will start the unit in question and terminate all units that aren't dependencies of it.
-                    // If "ignore-dependencies" it will start a unit but ignore all its dependencies.
-                    // If "ignore-requirements" it will start a unit but only ignore the requirement dependencies.
-                    // It is not recommended to make use of the latter two options. Returns the newly created job object.
-                    let job = proxy.start_unit(unit_name.to_string(), "fail".into()).await; // "fail"
-                    match job {
-                        Ok(job) => {
-                            info!(
-                                "Success, submitted StartUnit job={} for unit={}",
-                                job.to_string(),
-                                &unit_name
-                            );
-                        }
-                        Err(e) => {
-                            error!(
-                                "Error submitting StartUnit job for {} error={}",
-                                &unit_name, e
-                            );
-                        }
-                    }
-                }
-                None => {
-                    warn!("handle_camera_recording_stop called, but no active recording was found. You may need to manually run `printnanny cloud sync-video-recordings` to backup recording to PrintNanny Cloud.");
-                }
-            }
-        }
+// returned instead of a bare error when a client sends a subject_pattern this daemon
+// build doesn't implement (e.g. an older daemon talking to a newer web UI)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnsupportedCapabilityReply {
+    pub subject_pattern: String,
+    pub protocol_version: u32,
+    pub capabilities: Vec<String>,
+}
 
-        let recording = match recording {
-            Some(recording) => {
-                let now = Utc::now();
-                let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
-                    recording_status: Some("done"),
-                    recording_end: Some(&now),
-                    deleted: None,
-                    recording_start: None,
-                    gcode_file_name: None,
-                    mp4_upload_url: None,
-                    mp4_download_url: None,
-                    cloud_sync_percent: None,
-                    cloud_sync_status: None,
-                    cloud_sync_start: None,
-                    cloud_sync_end: None,
-                };
-                printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
-                let recording =
-                    printnanny_edge_db::video_recording::VideoRecording::get_by_id(&recording.id)?;
-                Some(recording)
-            }
-            None => None,
-        };
-        Ok(NatsReply::CameraRecordingStopReply(
-            CameraRecordingStopped {
-                recording: recording.map(|v| Box::new(v.into())),
-            },
-        ))
-    }
+// acks RecordingSupervisorStartRequest with the concrete (pi_id-resolved) subjects
+// the caller can watch: dataframe_subject is the df pipeline's raw detection output,
+// finished_subject is where RecordingFinished events get published
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingSupervisorStartReply {
+    pub dataframe_subject: String,
+    pub finished_subject: String,
+}
 
-    pub async fn handle_cloud_sync() -> Result<NatsReply> {
-        let start = chrono::offset::Utc::now().to_rfc3339();
+// published on RecordingSupervisorStartReply::finished_subject once a supervised
+// recording's pads are flushed/EOS'd and its segments are all on disk
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingFinished {
+    pub recording_id: String,
+    pub mp4_file_name: String,
+    pub segment_file_names: Vec<String>,
+}
 
-        let api = ApiService::new()?;
-        // sync cloud models to edge db
-        api.sync().await?;
-        // set optional pipelines to correct state
-        let gst_pipelines = PrintNannyPipelineFactory::default();
-        gst_pipelines.sync_optional_pipelines().await?;
-        let end = chrono::offset::Utc::now().to_rfc3339();
+// one detection emitted by dataframe_agg on the df pipeline's NATS output
+#[derive(Clone, Debug, Default, Deserialize)]
+struct DataframeDetection {
+    #[serde(default)]
+    confidence: f64,
+}
 
-        Ok(NatsReply::PrintNannyCloudSyncReply(
-            PrintNannyCloudSyncReply { start, end },
-        ))
+#[derive(Clone, Debug, Default, Deserialize)]
+struct DataframeRecord {
+    #[serde(default)]
+    detections: Vec<DataframeDetection>,
+}
+
+impl DataframeRecord {
+    // the recording supervisor only cares about the strongest detection in the frame
+    fn max_confidence(&self) -> f64 {
+        self.detections
+            .iter()
+            .map(|d| d.confidence)
+            .fold(0.0, f64::max)
     }
+}
 
-    // message messages sent to: "pi.{pi_id}.device_info.load"
-    pub async fn handle_device_info_load() -> Result<NatsReply> {
-        let settings = PrintNannySettings::new()?;
-        let issue = fs::read_to_string(settings.paths.issue_txt).await?;
-        let os_release = fs::read_to_string(settings.paths.os_release).await?;
+// published periodically on pi.{pi_id}.command.camera.recording.sync.progress while
+// a recording's mp4 uploads to PrintNanny Cloud
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraRecordingSyncProgress {
+    pub recording_id: String,
+    pub percent: f32,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+    pub bytes_per_sec: f64,
+}
 
-        let ifaddrs = tokio::task::spawn_blocking(|| match nix::ifaddrs::getifaddrs() {
-            Ok(result) => result
-                .map(
-                    |v| printnanny_settings::printnanny_asyncapi_models::NetworkInterfaceAddress {
-                        interface_name: v.interface_name,
-                        flags: v.flags.bits(),
-                        address: v.address.map(|v| v.to_string()),
-                        netmask: v.netmask.map(|v| v.to_string()),
-                        destination: v.destination.map(|v| v.to_string()),
-                        broadcast: v.broadcast.map(|v| v.to_string()),
-                    },
-                )
-                .collect(),
-            Err(e) => {
-                error!("Error loading ifaddrs {}", e.to_string());
-                vec![]
-            }
-        })
-        .await?;
+// no bytes sent for this long flips cloud_sync_status to "stalled"
+const CLOUD_SYNC_STALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const CLOUD_SYNC_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
-        // let ifaddrs = ifaddrs
-        //     .map(
-        //         |v| printnanny_settings::printnanny_asyncapi_models::NetworkInterfaceAddress {
-        //             interface_name: v.interface_name,
-        //             flags: v.flags.bits(),
-        //             address: v.address.map(|v| v.to_string()),
-        //             netmask: v.netmask.map(|v| v.to_string()),
-        //             destination: v.destination.map(|v| v.to_string()),
-        //             broadcast: v.broadcast.map(|v| v.to_string()),
-        //         },
-        //     )
-        //     .collect();
+// request/reply pair for pi.{pi_id}.command.camera.recording.sync.enqueue
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncEnqueueRequest {
+    pub recording_id: String,
+}
 
-        Ok(NatsReply::DeviceInfoLoadReply(DeviceInfoLoadReply {
-            issue,
-            os_release,
-            printnanny_cli_version: "".into(), // TODO
-            ifaddrs,
-        }))
-    }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncEnqueueReply {
+    pub recording_id: String,
+    pub queue_depth: usize,
+    // id of the checkpointed JobState tracking this upload - pass to
+    // pi.{pi_id}.command.job.status to poll it or watch job.progress for updates
+    pub job_id: String,
+}
 
-    // handle messages sent to: "pi.{pi_id}.settings.printnanny.cloud.auth"
-    pub async fn handle_printnanny_cloud_auth(
-        request: &PrintNannyCloudAuthRequest,
-    ) -> Result<NatsReply> {
-        let api_service = ApiService::new()?;
-        let result = api_service
-            .connect_cloud_account(request.api_url.clone(), request.api_token.clone())
-            .await;
+// request/reply pair for pi.{pi_id}.command.camera.recording.sync.cancel
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncCancelRequest {
+    pub recording_id: String,
+}
 
-        let result = match result {
-            Ok(_) => {
-                info!(
-                    "Successfully connected PrintNanny Cloud account: {}",
-                    request.email
-                );
-                NatsReply::PrintNannyCloudAuthReply(PrintNannyCloudAuthReply {
-                    status_code: 200,
-                    msg: format!("Success! Connected account: {}", request.email),
-                })
-            }
-            Err(e) => {
-                error!("Failed to connect PrintNanny Cloud account, error: {}", e);
-                NatsReply::PrintNannyCloudAuthReply(PrintNannyCloudAuthReply {
-                    status_code: 403,
-                    msg: format!("Error connecting account: {}", e),
-                })
-            }
-        };
-        Ok(result)
-    }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncCancelReply {
+    pub recording_id: String,
+    pub cancelled: bool,
+}
 
-    pub async fn handle_crash_report(request: &CrashReportOsLogsRequest) -> Result<NatsReply> {
-        let api_service = ApiService::new()?;
-        let result = api_service.crash_report_update(&request.id).await?;
-        Ok(NatsReply::CrashReportOsLogsReply(CrashReportOsLogsReply {
-            id: result.id,
-            updated_dt: result.updated_dt,
-        }))
-    }
+// one entry of the reply to pi.{pi_id}.command.camera.recording.sync.queue
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncJobState {
+    pub recording_id: String,
+    pub attempt: u32,
+}
 
-    pub fn handle_cameras_load() -> Result<NatsReply> {
-        let cameras: Vec<printnanny_asyncapi_models::Camera> =
-            CameraVideoSource::from_libcamera_list()?
-                .iter()
-                .map(|v| v.into())
-                .collect();
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloudSyncQueueStateReply {
+    pub depth: usize,
+    pub jobs: Vec<CloudSyncJobState>,
+}
 
-        Ok(NatsReply::CameraLoadReply(
-            printnanny_asyncapi_models::cameras_load_reply::CamerasLoadReply { cameras },
-        ))
-    }
+// what a job is doing - distinct from SyncJob below, which only tracks an in-memory
+// upload attempt for the life of the process. JobState is checkpointed to state_dir
+// so a job that was Queued/Running when the daemon restarted can be found and resumed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JobKind {
+    CloudSync { recording_id: String },
+    CameraRecordingStart,
+    CameraRecordingStop,
+}
 
-    pub async fn handle_printnanny_settings_revert(
-        request: &SettingsFileRevertRequest,
-    ) -> Result<NatsReply> {
-        let settings = PrintNannySettings::new()?;
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+}
 
-        // revert commit
-        let oid = git2::Oid::from_str(&request.git_commit)?;
-        settings.git_revert_hooks(Some(oid)).await?;
-        let files = vec![settings.to_payload(SettingsApp::Printnanny).await?];
-        Self::build_settings_revert_reply(request, &settings, files)
-    }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobState {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub step: String,
+    pub items_done: u64,
+    pub items_total: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub error: Option<String>,
+}
 
-    async fn handle_octoprint_settings_revert(
+// request/reply pair for pi.{pi_id}.settings.file.diff - a unified diff between two
+// commits in the settings repo, restricted to the file managed by `app`, so a
+// controller can show a review screen before applying or reverting
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsFileDiffRequest {
+    pub app: Box<SettingsApp>,
+    pub from_commit: String,
+    pub to_commit: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsFileDiffReply {
+    pub app: Box<SettingsApp>,
+    pub from_commit: String,
+    pub to_commit: String,
+    pub files: Vec<GitFileDiff>,
+}
+
+// request/reply pair for pi.{pi_id}.settings.watcher.start - starts the filesystem
+// watcher that auto-commits out-of-band edits to managed settings files. Starting an
+// already-running watcher is a no-op that just reports its current configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsWatcherStartRequest {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsWatcherStartReply {
+    pub watching: Vec<String>,
+    pub debounce_ms: u64,
+}
+
+// request/reply pair for pi.{pi_id}.settings.watcher.stop
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsWatcherStopRequest {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsWatcherStopReply {
+    pub stopped: bool,
+}
+
+// published on pi.{pi_id}.settings.file.changed whenever the watcher auto-commits a
+// debounced batch of out-of-band edits to a managed settings file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsFileChangedNotification {
+    pub file_name: String,
+    pub git_commit_msg: String,
+    pub git_head_commit: String,
+}
+
+// request/reply pair for pi.{pi_id}.settings.remote.sync - registers or repoints a
+// named git remote for the settings repo, used for off-device backup/restore
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsRemoteSyncRequest {
+    pub remote_name: String,
+    pub remote_url: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsRemoteSyncReply {
+    pub remote_name: String,
+    pub remote_url: String,
+}
+
+// request/reply pair for pi.{pi_id}.settings.remote.push
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsRemotePushRequest {
+    pub remote_name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsRemotePushReply {
+    pub remote_name: String,
+    pub git_head_commit: String,
+}
+
+// request/reply pair for pi.{pi_id}.settings.remote.pull - a pull either fast-forwards
+// cleanly or reports the diverging commit oids so the caller can choose a merge or
+// revert rather than losing local commits to a silent overwrite
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsRemotePullRequest {
+    pub remote_name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SettingsRemotePullReply {
+    UpToDate { git_head_commit: String },
+    FastForwarded { git_head_commit: String },
+    // local and remote diverged but were reconciled with a three-way merge commit
+    Merged { git_head_commit: String },
+    // local and remote diverged and the merge left real conflicts - local edits were
+    // not overwritten, `files` names the conflicted paths and `diff` is the pending
+    // working-tree patch so the caller can resolve before retrying
+    Conflict { files: Vec<String>, diff: String },
+}
+
+// request/reply pair for pi.{pi_id}.command.job.status
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobStatusRequest {
+    pub job_id: String,
+}
+
+// published periodically on pi.{pi_id}.command.job.progress as a job's JobState is
+// checkpointed, so a controller can show a progress bar without polling job.status
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub step: String,
+    pub items_done: u64,
+    pub items_total: Option<u64>,
+}
+
+// caps concurrent uploads so a burst of finished recordings doesn't saturate the
+// pi's uplink or open one reqwest connection per recording at once
+const CLOUD_SYNC_MAX_CONCURRENT_UPLOADS: usize = 2;
+// exponential backoff between retry attempts: base * 2^(attempt - 1), capped at max
+const CLOUD_SYNC_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+const CLOUD_SYNC_RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(300);
+const CLOUD_SYNC_MAX_ATTEMPTS: u32 = 8;
+
+// a pending or in-flight cloud sync upload; replayed from the edge db on startup so
+// uploads interrupted by a reboot resume instead of being silently dropped
+#[derive(Clone, Debug)]
+struct SyncJob {
+    pi_id: String,
+    recording_id: String,
+    upload_url: String,
+    attempt: u32,
+    // id of the checkpointed JobState tracking this upload, so status/progress
+    // queries can follow it independent of the in-memory SYNC_QUEUE below
+    job_id: String,
+}
+
+lazy_static! {
+    // jobs that are queued or currently uploading, used to answer queue-depth/state queries
+    static ref SYNC_QUEUE: std::sync::Arc<tokio::sync::Mutex<VecDeque<SyncJob>>> =
+        std::sync::Arc::new(tokio::sync::Mutex::new(VecDeque::new()));
+    // bounds how many uploads run concurrently regardless of how many jobs are queued
+    static ref SYNC_QUEUE_SEMAPHORE: std::sync::Arc<tokio::sync::Semaphore> =
+        std::sync::Arc::new(tokio::sync::Semaphore::new(CLOUD_SYNC_MAX_CONCURRENT_UPLOADS));
+    // recording_ids requested for cancellation; consumed (and cleared) by the next retry check
+    static ref SYNC_QUEUE_CANCELLED: std::sync::Arc<tokio::sync::Mutex<std::collections::HashSet<String>>> =
+        std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashSet::new()));
+}
+
+// installed at most once per process, the first time any request is handled
+static METRICS_EXPORTER_INIT: std::sync::Once = std::sync::Once::new();
+
+// default port the prometheus exporter listens on when settings.nats.metrics_port is unset
+const DEFAULT_METRICS_PORT: u16 = 9927;
+
+fn ensure_metrics_exporter_installed() {
+    METRICS_EXPORTER_INIT.call_once(|| {
+        let port = PrintNannySettings::new()
+            .map(|settings| settings.nats.metrics_port)
+            .unwrap_or(DEFAULT_METRICS_PORT);
+        let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
+        match PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()
+        {
+            Ok(()) => info!("Installed Prometheus metrics exporter on {}", addr),
+            Err(e) => error!("Failed to install Prometheus metrics exporter: {}", e),
+        }
+    });
+}
+
+// gated behind settings so operators can silence the per-request completion log line
+// (e.g. on noisy subjects like settings.file.load) without a rebuild
+fn request_logging_enabled() -> bool {
+    PrintNannySettings::new()
+        .map(|settings| settings.nats.request_logging)
+        .unwrap_or(false)
+}
+
+// default how long a SystemdManager*UnitRequest waits for its JobRemoved signal
+// before giving up, used when settings.nats.systemd_job_timeout_secs is unset
+const DEFAULT_SYSTEMD_JOB_TIMEOUT_SECS: u64 = 30;
+
+fn systemd_job_timeout() -> std::time::Duration {
+    let secs = PrintNannySettings::new()
+        .map(|settings| settings.nats.systemd_job_timeout_secs)
+        .unwrap_or(DEFAULT_SYSTEMD_JOB_TIMEOUT_SECS);
+    std::time::Duration::from_secs(secs)
+}
+
+// request/reply pair for pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs -
+// opens a journald cursor for `unit_name` and hands back the per-follow subjects a
+// caller should subscribe to. Flow control and liveness are borrowed from
+// Bottlerocket's exec client: the subscriber advertises how many frames it can
+// buffer by publishing SystemdUnitLogCapacity on capacity_subject, and either side
+// publishing SystemdUnitLogHeartbeat on heartbeat_subject bumps a shared last_seen
+// so a stalled consumer or dead connection tears down the follow instead of
+// leaking a journalctl process forever
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdManagerStreamUnitLogsRequest {
+    pub unit_name: String,
+    pub follow: bool,
+    // resume after this journald cursor instead of starting from the beginning
+    pub since: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdManagerStreamUnitLogsReply {
+    pub unit_name: String,
+    pub log_subject: String,
+    pub capacity_subject: String,
+    pub heartbeat_subject: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SystemdUnitOp {
+    Enable,
+    Disable,
+}
+
+// request/reply pair for pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits -
+// applies a batch of enable/disable operations as a single transaction. Each unit's
+// prior enable state is recorded before anything runs, so a failure partway through
+// the batch can reverse the operations already applied instead of leaving the system
+// half-changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdManagerApplyUnitsRequest {
+    pub operations: Vec<(String, SystemdUnitOp)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdManagerApplyUnitsReply {
+    pub changes: Vec<SystemdUnitChange>,
+}
+
+// describes what happened to one already-applied unit when a later unit in the same
+// batch failed and the batch had to roll back
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdUnitRollback {
+    pub unit: String,
+    pub restored: bool,
+    pub error: Option<String>,
+}
+
+// one journal entry published on a SystemdManagerStreamUnitLogsReply.log_subject
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdUnitLogFrame {
+    pub unit_name: String,
+    // journald __CURSOR for this entry - pass back as `since` to resume after it
+    pub cursor: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// published by a subscriber on capacity_subject to advertise how many more frames
+// it can buffer before the publish loop should pause
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdUnitLogCapacity {
+    pub available: i64,
+}
+
+// ping/pong exchanged on heartbeat_subject; either side publishing one counts as a
+// liveness signal and bumps the follow task's last_seen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SystemdUnitLogHeartbeat {
+    pub unit_name: String,
+}
+
+// how often the follow task pings the heartbeat subject, and how long it will wait
+// without hearing back before tearing the journalctl follow down
+const UNIT_LOG_HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const UNIT_LOG_HEARTBEAT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+// how often the follow loop rechecks advertised capacity while paused waiting for room
+const UNIT_LOG_CAPACITY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+async fn connect_nats_client() -> Result<async_nats::Client> {
+    let settings = PrintNannySettings::new()?;
+    Ok(async_nats::connect(&settings.nats.uri).await?)
+}
+
+lazy_static! {
+    // cached system bus connection + Manager proxy, built once and cloned on every
+    // handler call - zbus::Connection is cheap to clone (it's Arc-backed under the
+    // hood), so this avoids re-handshaking with the system bus on every NATS request
+    static ref SYSTEMD_MANAGER: std::sync::Arc<tokio::sync::Mutex<Option<(zbus::Connection, zbus_systemd::systemd1::ManagerProxy<'static>)>>> =
+        std::sync::Arc::new(tokio::sync::Mutex::new(None));
+}
+
+async fn get_systemd_manager(
+) -> Result<(zbus::Connection, zbus_systemd::systemd1::ManagerProxy<'static>)> {
+    let mut cached = SYSTEMD_MANAGER.lock().await;
+    if let Some((connection, proxy)) = cached.as_ref() {
+        return Ok((connection.clone(), proxy.clone()));
+    }
+    let connection = zbus::Connection::system().await?;
+    let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+    *cached = Some((connection.clone(), proxy.clone()));
+    Ok((connection, proxy))
+}
+
+// state/jobs, under state_dir - holds one MessagePack-encoded JobState file per job
+fn jobs_dir() -> Result<std::path::PathBuf> {
+    let settings = PrintNannySettings::new()?;
+    Ok(settings.paths.state_dir.join("jobs"))
+}
+
+fn job_state_path(job_id: &str) -> Result<std::path::PathBuf> {
+    Ok(jobs_dir()?.join(format!("{}.mp", job_id)))
+}
+
+// MessagePack keeps these writes cheap since a job's state is checkpointed on every step
+async fn save_job_state(job: &JobState) -> Result<()> {
+    let dir = jobs_dir()?;
+    fs::create_dir_all(&dir).await?;
+    let bytes = rmp_serde::to_vec(job)?;
+    fs::write(job_state_path(&job.id)?, bytes).await?;
+    Ok(())
+}
+
+async fn load_job_state(job_id: &str) -> Result<JobState> {
+    let bytes = fs::read(job_state_path(job_id)?).await?;
+    Ok(rmp_serde::from_slice(&bytes)?)
+}
+
+// scans jobs_dir for every checkpointed job, e.g. so the manager can find jobs left
+// Queued/Running by an unclean shutdown and resume them
+async fn list_job_states() -> Result<Vec<JobState>> {
+    let dir = jobs_dir()?;
+    let mut entries = match fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut result = vec![];
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("mp") {
+            continue;
+        }
+        match fs::read(&path).await {
+            Ok(bytes) => match rmp_serde::from_slice::<JobState>(&bytes) {
+                Ok(job) => result.push(job),
+                Err(e) => warn!("Failed to deserialize job state {:?}: {}", path, e),
+            },
+            Err(e) => warn!("Failed to read job state {:?}: {}", path, e),
+        }
+    }
+    Ok(result)
+}
+
+// creates and checkpoints a new job in the Queued state; returns the generated id
+// immediately so the caller (a NATS reply) doesn't have to block on the job itself
+async fn create_job(kind: JobKind) -> Result<JobState> {
+    let now = Utc::now();
+    let job = JobState {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        status: JobStatus::Queued,
+        step: "queued".to_string(),
+        items_done: 0,
+        items_total: None,
+        started_at: now,
+        updated_at: now,
+        error: None,
+    };
+    save_job_state(&job).await?;
+    Ok(job)
+}
+
+// loads a job, applies `f`, bumps updated_at, and checkpoints the result
+async fn update_job_state(job_id: &str, f: impl FnOnce(&mut JobState)) -> Result<JobState> {
+    let mut job = load_job_state(job_id).await?;
+    f(&mut job);
+    job.updated_at = Utc::now();
+    save_job_state(&job).await?;
+    Ok(job)
+}
+
+// scans jobs_dir on startup and resumes anything left Queued/Running by an unclean
+// shutdown. CloudSync jobs re-enter the in-memory sync queue from their last
+// checkpoint; a camera recording job can't be resumed (the gstreamer pipeline and any
+// in-progress mp4 are gone), so it's marked Failed with an explanatory error instead.
+pub async fn resume_unfinished_jobs() -> Result<()> {
+    for job in list_job_states().await? {
+        if !matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+            continue;
+        }
+        match &job.kind {
+            JobKind::CloudSync { recording_id } => {
+                let recording =
+                    printnanny_edge_db::video_recording::VideoRecording::get_by_id(recording_id);
+                match recording.ok().and_then(|r| r.mp4_upload_url.clone()) {
+                    Some(upload_url) => {
+                        let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+                        info!("Resuming checkpointed cloud sync job {}", job.id);
+                        enqueue_sync_job_with_id(job.id.clone(), pi_id, recording_id.clone(), upload_url)
+                            .await;
+                    }
+                    None => {
+                        update_job_state(&job.id, |j| {
+                            j.status = JobStatus::Failed;
+                            j.error = Some("recording or mp4_upload_url no longer available".into());
+                        })
+                        .await?;
+                    }
+                }
+            }
+            JobKind::CameraRecordingStart | JobKind::CameraRecordingStop => {
+                warn!(
+                    "Job {} ({:?}) was interrupted by a restart and cannot be resumed",
+                    job.id, job.kind
+                );
+                update_job_state(&job.id, |j| {
+                    j.status = JobStatus::Failed;
+                    j.error = Some("interrupted by a daemon restart".into());
+                })
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// pushes a job onto the queue and spawns its drain task; the semaphore (not the
+// number of spawned tasks) is what actually bounds concurrent uploads
+async fn enqueue_sync_job(pi_id: String, recording_id: String, upload_url: String) -> (usize, String) {
+    let job_id = match create_job(JobKind::CloudSync {
+        recording_id: recording_id.clone(),
+    })
+    .await
+    {
+        Ok(job) => job.id,
+        Err(e) => {
+            error!("Failed to checkpoint cloud sync job state: {}", e);
+            uuid::Uuid::new_v4().to_string()
+        }
+    };
+    let depth = enqueue_sync_job_with_id(job_id.clone(), pi_id, recording_id, upload_url).await;
+    (depth, job_id)
+}
+
+// shared by enqueue_sync_job (new job) and resume_unfinished_jobs (an existing
+// checkpointed job id being re-entered into the in-memory queue)
+async fn enqueue_sync_job_with_id(
+    job_id: String,
+    pi_id: String,
+    recording_id: String,
+    upload_url: String,
+) -> usize {
+    let job = SyncJob {
+        pi_id,
+        recording_id,
+        upload_url,
+        attempt: 0,
+        job_id,
+    };
+    let depth = {
+        let mut queue = SYNC_QUEUE.lock().await;
+        queue.push_back(job.clone());
+        queue.len()
+    };
+    tokio::spawn(drain_sync_job(job));
+    depth
+}
+
+async fn cancel_sync_job(recording_id: &str) -> bool {
+    let queued = {
+        let queue = SYNC_QUEUE.lock().await;
+        queue.iter().any(|j| j.recording_id == recording_id)
+    };
+    if queued {
+        SYNC_QUEUE_CANCELLED
+            .lock()
+            .await
+            .insert(recording_id.to_string());
+    }
+    queued
+}
+
+async fn sync_queue_state() -> CloudSyncQueueStateReply {
+    let queue = SYNC_QUEUE.lock().await;
+    CloudSyncQueueStateReply {
+        depth: queue.len(),
+        jobs: queue
+            .iter()
+            .map(|j| CloudSyncJobState {
+                recording_id: j.recording_id.clone(),
+                attempt: j.attempt,
+            })
+            .collect(),
+    }
+}
+
+// best-effort publish of a job's current checkpoint on pi.{pi_id}.command.job.progress;
+// failures are logged and swallowed since progress is advisory - job.status remains
+// the source of truth and can always be polled directly
+async fn publish_job_progress(nats_client: &async_nats::Client, pi_id: &str, job_id: &str) {
+    let job = match load_job_state(job_id).await {
+        Ok(job) => job,
+        Err(e) => {
+            warn!("Failed to load job {} to publish progress: {}", job_id, e);
+            return;
+        }
+    };
+    let progress = JobProgress {
+        job_id: job.id,
+        status: job.status,
+        step: job.step,
+        items_done: job.items_done,
+        items_total: job.items_total,
+    };
+    let subject = format!("pi.{pi_id}.command.job.progress");
+    match serde_json::to_vec(&progress) {
+        Ok(payload) => {
+            if let Err(e) = nats_client.publish(subject, payload.into()).await {
+                error!("Failed to publish job progress for {}: {}", job_id, e);
+            }
+        }
+        Err(e) => error!("Failed to serialize job progress for {}: {}", job_id, e),
+    }
+}
+
+// drains a single job: acquires a concurrency permit, then retries
+// sync_video_recording_upload with exponential backoff until it succeeds, the job
+// is cancelled, or CLOUD_SYNC_MAX_ATTEMPTS is exhausted
+async fn drain_sync_job(mut job: SyncJob) {
+    let _permit = match SYNC_QUEUE_SEMAPHORE.clone().acquire_owned().await {
+        Ok(permit) => permit,
+        Err(e) => {
+            error!("Cloud sync queue semaphore closed unexpectedly: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        if SYNC_QUEUE_CANCELLED
+            .lock()
+            .await
+            .remove(&job.recording_id)
+        {
+            info!("Cloud sync job for recording {} was cancelled", job.recording_id);
+            if let Err(e) = update_job_state(&job.job_id, |j| {
+                j.status = JobStatus::Failed;
+                j.error = Some("cancelled".into());
+            })
+            .await
+            {
+                warn!("Failed to checkpoint cancelled job {}: {}", job.job_id, e);
+            }
+            break;
+        }
+
+        let recording =
+            match printnanny_edge_db::video_recording::VideoRecording::get_by_id(&job.recording_id)
+            {
+                Ok(recording) => recording,
+                Err(e) => {
+                    error!(
+                        "Failed to load recording {} for queued cloud sync: {}",
+                        job.recording_id, e
+                    );
+                    if let Err(e) = update_job_state(&job.job_id, |j| {
+                        j.status = JobStatus::Failed;
+                        j.error = Some(e.to_string());
+                    })
+                    .await
+                    {
+                        warn!("Failed to checkpoint failed job {}: {}", job.job_id, e);
+                    }
+                    break;
+                }
+            };
+
+        let nats_client = match connect_nats_client().await {
+            Ok(client) => client,
+            Err(e) => {
+                error!(
+                    "Failed to connect to NATS to sync recording {}: {}",
+                    job.recording_id, e
+                );
+                break;
+            }
+        };
+
+        if let Err(e) = update_job_state(&job.job_id, |j| {
+            j.status = JobStatus::Running;
+            j.step = format!("uploading (attempt {})", job.attempt + 1);
+        })
+        .await
+        {
+            warn!("Failed to checkpoint running job {}: {}", job.job_id, e);
+        }
+        publish_job_progress(&nats_client, &job.pi_id, &job.job_id).await;
+
+        match sync_video_recording_upload(&nats_client, &job.pi_id, recording, &job.upload_url)
+            .await
+        {
+            Ok(()) => {
+                info!(
+                    "Cloud sync for recording {} finished after {} attempt(s)",
+                    job.recording_id,
+                    job.attempt + 1
+                );
+                if let Err(e) = update_job_state(&job.job_id, |j| {
+                    j.status = JobStatus::Completed;
+                    j.step = "done".into();
+                })
+                .await
+                {
+                    warn!("Failed to checkpoint completed job {}: {}", job.job_id, e);
+                }
+                publish_job_progress(&nats_client, &job.pi_id, &job.job_id).await;
+                break;
+            }
+            Err(e) => {
+                job.attempt += 1;
+                if job.attempt >= CLOUD_SYNC_MAX_ATTEMPTS {
+                    error!(
+                        "Giving up on cloud sync for recording {} after {} attempts: {}",
+                        job.recording_id, job.attempt, e
+                    );
+                    if let Err(save_err) = update_job_state(&job.job_id, |j| {
+                        j.status = JobStatus::Failed;
+                        j.error = Some(e.to_string());
+                    })
+                    .await
+                    {
+                        warn!(
+                            "Failed to checkpoint failed job {}: {}",
+                            job.job_id, save_err
+                        );
+                    }
+                    publish_job_progress(&nats_client, &job.pi_id, &job.job_id).await;
+                    break;
+                }
+                let delay = CLOUD_SYNC_RETRY_BASE_DELAY
+                    .saturating_mul(1u32 << (job.attempt - 1).min(16))
+                    .min(CLOUD_SYNC_RETRY_MAX_DELAY);
+                warn!(
+                    "Cloud sync attempt {} failed for recording {}, retrying in {:?}: {}",
+                    job.attempt, job.recording_id, delay, e
+                );
+                {
+                    let mut queue = SYNC_QUEUE.lock().await;
+                    if let Some(queued) = queue
+                        .iter_mut()
+                        .find(|j| j.recording_id == job.recording_id)
+                    {
+                        queued.attempt = job.attempt;
+                    }
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+
+    SYNC_QUEUE
+        .lock()
+        .await
+        .retain(|j| j.recording_id != job.recording_id);
+}
+
+// replays jobs left `inprogress`, `stalled` or `pending` by an unclean shutdown; call once on startup
+// before subscribing to NATS so an interrupted upload isn't silently dropped
+pub async fn replay_unfinished_cloud_sync_jobs() -> Result<()> {
+    let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+    let recordings = printnanny_edge_db::video_recording::VideoRecording::get_all()?;
+    for recording in recordings {
+        let resumable = matches!(
+            recording.cloud_sync_status.as_deref(),
+            Some("inprogress") | Some("stalled") | Some("pending")
+        );
+        if resumable {
+            if let Some(upload_url) = recording.mp4_upload_url.clone() {
+                info!(
+                    "Replaying unfinished cloud sync job for recording {}",
+                    recording.id
+                );
+                enqueue_sync_job(pi_id.clone(), recording.id.clone(), upload_url).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+// wraps zstd-compressed bytes in a chunked stream, incrementing `sent` as each
+// chunk is yielded to reqwest so a concurrent task can publish progress
+fn counting_upload_body(
+    compressed: Vec<u8>,
+    sent: std::sync::Arc<std::sync::atomic::AtomicU64>,
+) -> reqwest::Body {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let chunks: Vec<Bytes> = compressed
+        .chunks(CHUNK_SIZE)
+        .map(Bytes::copy_from_slice)
+        .collect();
+    let stream = futures::stream::iter(chunks).map(move |chunk| {
+        sent.fetch_add(chunk.len() as u64, std::sync::atomic::Ordering::Relaxed);
+        Ok::<Bytes, std::io::Error>(chunk)
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+// temporary bucket credentials exchanged for the pi's PrintNanny Cloud bearer token;
+// short-lived, so S3_CLIENT_CACHE re-fetches a fresh set once these are close to expiry
+#[derive(Debug, Clone, Deserialize)]
+struct S3TemporaryCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expires_at: DateTime<Utc>,
+    bucket: String,
+    region: String,
+}
+
+// exchanges the pi's PrintNanny Cloud token for temporary S3 credentials scoped to
+// this pi's recordings prefix, the same way ApiService already authenticates other
+// requests with the cached bearer_access_token
+async fn fetch_s3_temporary_credentials() -> Result<S3TemporaryCredentials> {
+    let api = ApiService::new()?;
+    let url = format!("{}/cloud_sync/credentials", api.reqwest.base_path);
+    let mut req = reqwest::Client::new().get(&url);
+    if let Some(token) = &api.reqwest.bearer_access_token {
+        req = req.bearer_auth(token);
+    }
+    let creds = req
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<S3TemporaryCredentials>()
+        .await?;
+    Ok(creds)
+}
+
+lazy_static! {
+    // cached (expiry, client, bucket) so most uploads reuse one aws_sdk_s3::Client
+    // instead of re-authenticating and rebuilding it on every part
+    static ref S3_CLIENT_CACHE: std::sync::Arc<tokio::sync::Mutex<Option<(DateTime<Utc>, aws_sdk_s3::Client, String)>>> =
+        std::sync::Arc::new(tokio::sync::Mutex::new(None));
+}
+
+async fn get_s3_client() -> Result<(aws_sdk_s3::Client, String)> {
+    {
+        let cache = S3_CLIENT_CACHE.lock().await;
+        if let Some((expires_at, client, bucket)) = cache.as_ref() {
+            if *expires_at > Utc::now() + chrono::Duration::seconds(60) {
+                return Ok((client.clone(), bucket.clone()));
+            }
+        }
+    }
+
+    let creds = fetch_s3_temporary_credentials().await?;
+    let credentials = aws_credential_types::Credentials::new(
+        creds.access_key_id.clone(),
+        creds.secret_access_key.clone(),
+        Some(creds.session_token.clone()),
+        Some(creds.expires_at.into()),
+        "printnanny-cloud",
+    );
+    let config = aws_sdk_s3::config::Builder::new()
+        .region(aws_sdk_s3::config::Region::new(creds.region.clone()))
+        .credentials_provider(credentials)
+        .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+        .build();
+    let client = aws_sdk_s3::Client::from_conf(config);
+
+    let mut cache = S3_CLIENT_CACHE.lock().await;
+    *cache = Some((creds.expires_at, client.clone(), creds.bucket.clone()));
+    Ok((client, creds.bucket))
+}
+
+// minimum multipart part size accepted by S3 is 5MiB; 8MiB keeps part count reasonable
+// for a typical multi-hundred-MB timelapse without wasting too much on a retried part
+const S3_MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+// uploads `raw` directly to S3 via the multipart API, resuming an in-progress upload
+// for this recording's key (if one exists) by skipping parts S3 already has, so a
+// network drop only costs the in-flight part rather than the whole recording
+async fn multipart_upload_recording(
+    recording: &printnanny_edge_db::video_recording::VideoRecording,
+    raw: Vec<u8>,
+    sent: std::sync::Arc<std::sync::atomic::AtomicU64>,
+) -> Result<()> {
+    let (client, bucket) = get_s3_client().await?;
+    let key = format!("recordings/{}.mp4", recording.id);
+
+    let existing_upload_id = client
+        .list_multipart_uploads()
+        .bucket(&bucket)
+        .prefix(&key)
+        .send()
+        .await?
+        .uploads()
+        .iter()
+        .find(|u| u.key() == Some(key.as_str()))
+        .and_then(|u| u.upload_id())
+        .map(|id| id.to_string());
+
+    let upload_id = match existing_upload_id {
+        Some(id) => {
+            info!("Resuming multipart upload {} for {}", id, key);
+            id
+        }
+        None => {
+            let created = client
+                .create_multipart_upload()
+                .bucket(&bucket)
+                .key(&key)
+                .send()
+                .await?;
+            created
+                .upload_id()
+                .ok_or_else(|| anyhow!("S3 did not return an upload_id for {}", key))?
+                .to_string()
+        }
+    };
+
+    let already_uploaded: std::collections::HashMap<i32, String> = client
+        .list_parts()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .send()
+        .await?
+        .parts()
+        .iter()
+        .filter_map(|p| Some((p.part_number()?, p.e_tag()?.to_string())))
+        .collect();
+
+    let chunks: Vec<&[u8]> = raw.chunks(S3_MULTIPART_PART_SIZE).collect();
+    let mut completed_parts = Vec::with_capacity(chunks.len());
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let part_number = (idx + 1) as i32;
+        let e_tag = match already_uploaded.get(&part_number) {
+            Some(e_tag) => {
+                info!(
+                    "Part {} of {} already uploaded for {}, skipping",
+                    part_number,
+                    chunks.len(),
+                    key
+                );
+                e_tag.clone()
+            }
+            None => {
+                let resp = client
+                    .upload_part()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .upload_id(&upload_id)
+                    .part_number(part_number)
+                    .body(aws_sdk_s3::primitives::ByteStream::from(chunk.to_vec()))
+                    .send()
+                    .await?;
+                resp.e_tag()
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "S3 did not return an ETag for part {} of {}",
+                            part_number,
+                            key
+                        )
+                    })?
+                    .to_string()
+            }
+        };
+        sent.fetch_add(chunk.len() as u64, std::sync::atomic::Ordering::Relaxed);
+        completed_parts.push(
+            aws_sdk_s3::types::CompletedPart::builder()
+                .part_number(part_number)
+                .e_tag(e_tag)
+                .build(),
+        );
+    }
+
+    client
+        .complete_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .multipart_upload(
+            aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                .set_parts(Some(completed_parts))
+                .build(),
+        )
+        .send()
+        .await?;
+
+    info!(
+        "Completed multipart upload for recording {} -> s3://{}/{}",
+        recording.id, bucket, key
+    );
+    Ok(())
+}
+
+// uploads `recording`'s mp4 - uncompressed via native S3 multipart when
+// cloud_sync_s3_native is set, otherwise zstd-compressed to `upload_url` - publishing
+// CameraRecordingSyncProgress on the recording's progress subject every tick and
+// flipping cloud_sync_status to "stalled" if no bytes move for CLOUD_SYNC_STALL_TIMEOUT
+async fn sync_video_recording_upload(
+    nats_client: &async_nats::Client,
+    pi_id: &str,
+    recording: printnanny_edge_db::video_recording::VideoRecording,
+    upload_url: &str,
+) -> Result<()> {
+    let now = Utc::now();
+    let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
+        cloud_sync_status: Some("inprogress"),
+        cloud_sync_start: Some(&now),
+        cloud_sync_percent: Some(&0),
+        deleted: None,
+        recording_start: None,
+        recording_end: None,
+        gcode_file_name: None,
+        mp4_upload_url: Some(upload_url),
+        mp4_download_url: None,
+        cloud_sync_end: None,
+        probe_failed: None,
+        duration_seconds: None,
+        container: None,
+        video_codec: None,
+        audio_codec: None,
+        width: None,
+        height: None,
+        framerate: None,
+        bitrate: None,
+        thumbnail_jpeg_file_name: None,
+        blurhash: None,
+    };
+    printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
+
+    let raw = tokio::fs::read(&recording.mp4_file_name).await?;
+    let total_bytes = raw.len() as u64;
+    let compressed = zstd::stream::encode_all(raw.as_slice(), 0)?;
+
+    let sent = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let subject = format!("pi.{pi_id}.command.camera.recording.sync.progress");
+
+    let progress_sent = sent.clone();
+    let progress_nats = nats_client.clone();
+    let progress_recording_id = recording.id.clone();
+    let progress_handle = tokio::spawn(async move {
+        let mut last_bytes = 0u64;
+        let mut stalled_for = std::time::Duration::ZERO;
+        loop {
+            tokio::time::sleep(CLOUD_SYNC_PROGRESS_INTERVAL).await;
+            let current = progress_sent.load(std::sync::atomic::Ordering::Relaxed);
+
+            if current == last_bytes {
+                stalled_for += CLOUD_SYNC_PROGRESS_INTERVAL;
+            } else {
+                stalled_for = std::time::Duration::ZERO;
+            }
+
+            let bytes_per_sec =
+                (current - last_bytes) as f64 / CLOUD_SYNC_PROGRESS_INTERVAL.as_secs_f64();
+            let percent = (current as f32 / total_bytes.max(1) as f32) * 100.0;
+
+            let progress = CameraRecordingSyncProgress {
+                recording_id: progress_recording_id.clone(),
+                percent,
+                bytes_sent: current,
+                total_bytes,
+                bytes_per_sec,
+            };
+            if let Ok(payload) = serde_json::to_vec(&progress) {
+                if let Err(e) = progress_nats.publish(subject.clone(), payload.into()).await {
+                    error!("Failed to publish sync progress for {}: {}", progress_recording_id, e);
+                }
+            }
+
+            if stalled_for >= CLOUD_SYNC_STALL_TIMEOUT {
+                warn!(
+                    "Upload stalled for recording {} - no progress for {:?}",
+                    progress_recording_id, stalled_for
+                );
+                let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
+                    cloud_sync_status: Some("stalled"),
+                    cloud_sync_percent: None,
+                    cloud_sync_start: None,
+                    cloud_sync_end: None,
+                    deleted: None,
+                    recording_start: None,
+                    recording_end: None,
+                    gcode_file_name: None,
+                    mp4_upload_url: None,
+                    mp4_download_url: None,
+                    probe_failed: None,
+                    duration_seconds: None,
+                    container: None,
+                    video_codec: None,
+                    audio_codec: None,
+                    width: None,
+                    height: None,
+                    framerate: None,
+                    bitrate: None,
+                    thumbnail_jpeg_file_name: None,
+                    blurhash: None,
+                };
+                if let Err(e) = printnanny_edge_db::video_recording::VideoRecording::update(
+                    &progress_recording_id,
+                    update,
+                ) {
+                    error!("Failed to mark recording {} stalled: {}", progress_recording_id, e);
+                }
+            }
+
+            if current >= total_bytes {
+                break;
+            }
+        }
+    });
+
+    // native S3 multipart upload skips the PrintNanny Cloud API as a relay and resumes
+    // partially-uploaded recordings by re-uploading only missing parts; presigned-URL
+    // PUT is kept as a fallback for pis whose settings haven't opted into it yet
+    let settings = PrintNannySettings::new()?;
+    let result: Result<()> = if settings.video_stream.recording.cloud_sync_s3_native {
+        multipart_upload_recording(&recording, raw, sent.clone()).await
+    } else {
+        let client = reqwest::Client::new();
+        let body = counting_upload_body(compressed, sent.clone());
+        client
+            .put(upload_url)
+            .header("Content-Encoding", "zstd")
+            .body(body)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow!(e))
+    };
+
+    progress_handle.abort();
+
+    let now = Utc::now();
+    let update = match &result {
+        Ok(_) => printnanny_edge_db::video_recording::UpdateVideoRecording {
+            cloud_sync_status: Some("done"),
+            cloud_sync_percent: Some(&100),
+            cloud_sync_end: Some(&now),
+            cloud_sync_start: None,
+            deleted: None,
+            recording_start: None,
+            recording_end: None,
+            gcode_file_name: None,
+            mp4_upload_url: None,
+            mp4_download_url: None,
+            probe_failed: None,
+            duration_seconds: None,
+            container: None,
+            video_codec: None,
+            audio_codec: None,
+            width: None,
+            height: None,
+            framerate: None,
+            bitrate: None,
+            thumbnail_jpeg_file_name: None,
+            blurhash: None,
+        },
+        Err(e) => {
+            error!("Upload failed for recording {}: {}", recording.id, e);
+            printnanny_edge_db::video_recording::UpdateVideoRecording {
+                cloud_sync_status: Some("failed"),
+                cloud_sync_percent: None,
+                cloud_sync_end: Some(&now),
+                cloud_sync_start: None,
+                deleted: None,
+                recording_start: None,
+                recording_end: None,
+                gcode_file_name: None,
+                mp4_upload_url: None,
+                mp4_download_url: None,
+                probe_failed: None,
+                duration_seconds: None,
+                container: None,
+                video_codec: None,
+                audio_codec: None,
+                width: None,
+                height: None,
+                framerate: None,
+                bitrate: None,
+                thumbnail_jpeg_file_name: None,
+                blurhash: None,
+            }
+        }
+    };
+    printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
+    result
+}
+
+// result of shelling out to ffprobe against a finished recording's mp4_file_name
+// every field is optional because a truncated/zero-frame recording may yield an
+// empty (but still zero-exit-code) streams array
+#[derive(Debug, Clone, Default)]
+struct VideoProbeResult {
+    probe_failed: bool,
+    duration_seconds: Option<f64>,
+    container: Option<String>,
+    video_codec: Option<String>,
+    audio_codec: Option<String>,
+    width: Option<i32>,
+    height: Option<i32>,
+    framerate: Option<f64>,
+    bitrate: Option<i64>,
+}
+
+impl VideoProbeResult {
+    fn failed() -> Self {
+        Self {
+            probe_failed: true,
+            ..Self::default()
+        }
+    }
+}
+
+// shells out to ffprobe the same way OctoPrintHelper shells out to pip/python -
+// run the command, check the exit status, and parse stdout only on success.
+// An interrupted or zero-frame recording produces a missing/empty streams array
+// rather than a non-zero exit code, so that case is treated as a probe failure too.
+fn probe_video_recording(mp4_file_name: &str) -> VideoProbeResult {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+            mp4_file_name,
+        ])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            warn!(
+                "ffprobe exited with non-zero status for {} stderr={}",
+                mp4_file_name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return VideoProbeResult::failed();
+        }
+        Err(e) => {
+            warn!("Failed to spawn ffprobe for {}: {}", mp4_file_name, e);
+            return VideoProbeResult::failed();
+        }
+    };
+
+    let parsed: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            warn!("Failed to parse ffprobe output for {}: {}", mp4_file_name, e);
+            return VideoProbeResult::failed();
+        }
+    };
+
+    let format = parsed.get("format");
+    let streams = parsed.get("streams").and_then(|s| s.as_array());
+
+    if streams.map(|s| s.is_empty()).unwrap_or(true) {
+        warn!(
+            "ffprobe returned no streams for {} - recording may be interrupted or zero-frame",
+            mp4_file_name
+        );
+        return VideoProbeResult::failed();
+    }
+    let streams = streams.expect("checked non-empty above");
+
+    let video_stream = streams
+        .iter()
+        .find(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some("video"));
+    let audio_stream = streams
+        .iter()
+        .find(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some("audio"));
+
+    VideoProbeResult {
+        probe_failed: false,
+        duration_seconds: format
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.parse::<f64>().ok()),
+        container: format
+            .and_then(|f| f.get("format_name"))
+            .and_then(|f| f.as_str())
+            .map(|s| s.to_string()),
+        video_codec: video_stream
+            .and_then(|s| s.get("codec_name"))
+            .and_then(|c| c.as_str())
+            .map(|s| s.to_string()),
+        audio_codec: audio_stream
+            .and_then(|s| s.get("codec_name"))
+            .and_then(|c| c.as_str())
+            .map(|s| s.to_string()),
+        width: video_stream
+            .and_then(|s| s.get("width"))
+            .and_then(|w| w.as_i64())
+            .map(|w| w as i32),
+        height: video_stream
+            .and_then(|s| s.get("height"))
+            .and_then(|h| h.as_i64())
+            .map(|h| h as i32),
+        framerate: video_stream
+            .and_then(|s| s.get("avg_frame_rate"))
+            .and_then(|f| f.as_str())
+            .and_then(|f| {
+                let mut parts = f.split('/');
+                let num: f64 = parts.next()?.parse().ok()?;
+                let den: f64 = parts.next()?.parse().ok()?;
+                if den == 0.0 {
+                    None
+                } else {
+                    Some(num / den)
+                }
+            }),
+        bitrate: format
+            .and_then(|f| f.get("bit_rate"))
+            .and_then(|b| b.as_str())
+            .and_then(|b| b.parse::<i64>().ok()),
+    }
+}
+
+// result of probing a camera device in the exact mode a CameraSettings apply
+// requests - supported_streams is the streams ffprobe actually found negotiating
+// that device_name/width/height/framerate combination, empty when the device
+// doesn't exist or doesn't support the requested mode
+#[derive(Debug, Clone, Default)]
+struct CameraProbeResult {
+    supported_streams: Vec<serde_json::Value>,
+}
+
+// the applied VideoStreamSettings plus the device capabilities probe_camera_device
+// found while validating the requested camera/width/height/framerate, so the UI can
+// populate valid choices instead of re-deriving them from the settings alone
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CameraSettingsApplyResult {
+    pub video_stream: VideoStreamSettings,
+    pub supported_streams: Vec<serde_json::Value>,
+}
+
+// shells out to ffprobe to confirm `device_name` exists and can actually negotiate
+// width/height/framerate, the same way probe_video_recording validates a finished
+// recording's mp4 - ffprobe reports an unsupported mode or a missing device as a
+// missing/empty streams array rather than a distinct error, so that case is handled
+// as "no usable streams" here too instead of indexing into a field that isn't there
+fn probe_camera_device(device_name: &str, width: i32, height: i32, framerate: i32) -> Result<CameraProbeResult> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-f",
+            "v4l2",
+            "-video_size",
+            &format!("{}x{}", width, height),
+            "-framerate",
+            &framerate.to_string(),
+            "-print_format",
+            "json",
+            "-show_streams",
+            device_name,
+        ])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            return Err(anyhow!(
+                "Camera device {} does not support {}x{}@{}fps: {}",
+                device_name,
+                width,
+                height,
+                framerate,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Err(e) => {
+            return Err(anyhow!(
+                "Failed to spawn ffprobe for camera device {}: {}",
+                device_name,
+                e
+            ));
+        }
+    };
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+        anyhow!(
+            "Failed to parse ffprobe output for camera device {}: {}",
+            device_name,
+            e
+        )
+    })?;
+
+    let streams = parsed.get("streams").and_then(|s| s.as_array());
+    if streams.map(|s| s.is_empty()).unwrap_or(true) {
+        return Err(anyhow!(
+            "Camera device {} reported no usable streams for {}x{}@{}fps - it may not exist or may not support that mode",
+            device_name, width, height, framerate
+        ));
+    }
+
+    Ok(CameraProbeResult {
+        supported_streams: streams.expect("checked non-empty above").clone(),
+    })
+}
+
+// number of BlurHash DCT-like components along each axis; 4x3 gives a pleasant
+// placeholder for 16:9 video without the string growing much past 30 bytes
+const BLURHASH_X_COMPONENTS: u32 = 4;
+const BLURHASH_Y_COMPONENTS: u32 = 3;
+
+// extracts a representative JPEG frame a second into the recording, the same way
+// probe_video_recording shells out to ffprobe, so a controller can show a poster
+// before the mp4 finishes uploading
+fn generate_thumbnail_jpeg(mp4_file_name: &str) -> Option<String> {
+    let jpeg_file_name = format!("{mp4_file_name}.thumb.jpg");
+    let output = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-ss",
+            "00:00:01",
+            "-i",
+            mp4_file_name,
+            "-frames:v",
+            "1",
+            "-q:v",
+            "4",
+            &jpeg_file_name,
+        ])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => Some(jpeg_file_name),
+        Ok(output) => {
+            warn!(
+                "ffmpeg exited with non-zero status generating thumbnail for {} stderr={}",
+                mp4_file_name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            None
+        }
+        Err(e) => {
+            warn!(
+                "Failed to spawn ffmpeg to generate thumbnail for {}: {}",
+                mp4_file_name, e
+            );
+            None
+        }
+    }
+}
+
+// decodes the thumbnail and encodes it as a BlurHash placeholder string
+fn generate_blurhash(jpeg_file_name: &str) -> Option<String> {
+    let img = match image::open(jpeg_file_name) {
+        Ok(img) => img.to_rgb8(),
+        Err(e) => {
+            warn!(
+                "Failed to decode thumbnail {} for blurhash: {}",
+                jpeg_file_name, e
+            );
+            return None;
+        }
+    };
+    Some(encode_blurhash(
+        &img,
+        BLURHASH_X_COMPONENTS,
+        BLURHASH_Y_COMPONENTS,
+    ))
+}
+
+const BLURHASH_ALPHABET: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn blurhash_encode_base83(mut value: u32, length: usize) -> String {
+    let mut result = vec![0u8; length];
+    for slot in result.iter_mut().rev() {
+        *slot = BLURHASH_ALPHABET[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(result).expect("BLURHASH_ALPHABET is ASCII")
+}
+
+fn blurhash_srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn blurhash_linear_to_srgb(value: f64) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn blurhash_sign_pow(value: f64, exp: f64) -> f64 {
+    value.abs().powf(exp).copysign(value)
+}
+
+// encodes `img` as a BlurHash string per the reference algorithm at
+// https://github.com/woltapp/blurhash: the (0,0) component is the DC/average color,
+// every other component is an AC term quantized against the largest AC magnitude
+fn encode_blurhash(img: &image::RgbImage, x_components: u32, y_components: u32) -> String {
+    let (width, height) = img.dimensions();
+    let mut factors = Vec::with_capacity((x_components * y_components) as usize);
+
+    for j in 0..y_components {
+        for i in 0..x_components {
+            let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for (px, py, pixel) in img.enumerate_pixels() {
+                let basis = (std::f64::consts::PI * i as f64 * px as f64 / width as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * py as f64 / height as f64).cos();
+                r += basis * blurhash_srgb_to_linear(pixel[0]);
+                g += basis * blurhash_srgb_to_linear(pixel[1]);
+                b += basis * blurhash_srgb_to_linear(pixel[2]);
+            }
+            let scale = normalization / (width * height) as f64;
+            factors.push((r * scale, g * scale, b * scale));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let size_flag = (x_components - 1) + (y_components - 1) * 9;
+    let mut result = blurhash_encode_base83(size_flag, 1);
+
+    let max_ac = ac
+        .iter()
+        .flat_map(|(r, g, b)| [r.abs(), g.abs(), b.abs()])
+        .fold(0.0_f64, f64::max);
+
+    let quantized_max_ac = if !ac.is_empty() {
+        let quantized = ((max_ac * 166.0 - 0.5).floor() as i64).clamp(0, 82) as u32;
+        result.push_str(&blurhash_encode_base83(quantized, 1));
+        (quantized as f64 + 1.0) / 166.0
+    } else {
+        result.push_str(&blurhash_encode_base83(0, 1));
+        1.0
+    };
+
+    let dc_value = (blurhash_linear_to_srgb(dc.0) as u32) << 16
+        | (blurhash_linear_to_srgb(dc.1) as u32) << 8
+        | blurhash_linear_to_srgb(dc.2) as u32;
+    result.push_str(&blurhash_encode_base83(dc_value, 4));
+
+    for (r, g, b) in ac {
+        let quantize = |value: f64| -> u32 {
+            ((blurhash_sign_pow(value / quantized_max_ac, 0.5) * 9.0 + 9.5).floor() as i64)
+                .clamp(0, 18) as u32
+        };
+        let ac_value = quantize(*r) * 19 * 19 + quantize(*g) * 19 + quantize(*b);
+        result.push_str(&blurhash_encode_base83(ac_value, 2));
+    }
+
+    result
+}
+
+impl NatsRequest {
+    pub fn handle_camera_recording_load() -> Result<NatsReply> {
+        let recordings: Vec<printnanny_asyncapi_models::VideoRecording> =
+            printnanny_edge_db::video_recording::VideoRecording::get_all()?
+                .into_iter()
+                .map(|v| (v).into())
+                .collect();
+        let current = printnanny_edge_db::video_recording::VideoRecording::get_current()?
+            .map(|v| Box::new(v.into()));
+        Ok(NatsReply::CameraRecordingLoadReply(
+            CameraRecordingLoadReply {
+                recordings,
+                current,
+            },
+        ))
+    }
+
+    pub async fn handle_camera_recording_start() -> Result<NatsReply> {
+        // checkpointed so "is a recording already starting" survives a daemon restart -
+        // if interrupted, resume_unfinished_jobs marks it Failed rather than leaving it
+        // silently Running forever
+        let job = create_job(JobKind::CameraRecordingStart).await?;
+        update_job_state(&job.id, |j| {
+            j.status = JobStatus::Running;
+            j.step = "starting pipeline".into();
+        })
+        .await?;
+
+        let recording = printnanny_edge_db::video_recording::VideoRecording::start_new()?;
+        let factory = PrintNannyPipelineFactory::default();
+        factory
+            .start_video_recording_pipeline(&recording.mp4_file_name)
+            .await?;
+        let now = Utc::now();
+        let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
+            recording_status: Some("inprogress"),
+            recording_start: Some(&now),
+            deleted: None,
+            gcode_file_name: None,
+            recording_end: None,
+            mp4_upload_url: None,
+            mp4_download_url: None,
+            cloud_sync_percent: None,
+            cloud_sync_status: None,
+            cloud_sync_start: None,
+            cloud_sync_end: None,
+            probe_failed: None,
+            duration_seconds: None,
+            container: None,
+            video_codec: None,
+            audio_codec: None,
+            width: None,
+            height: None,
+            framerate: None,
+            bitrate: None,
+            thumbnail_jpeg_file_name: None,
+            blurhash: None,
+        };
+        printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
+        let recording =
+            printnanny_edge_db::video_recording::VideoRecording::get_by_id(&recording.id)?;
+
+        update_job_state(&job.id, |j| {
+            j.status = JobStatus::Completed;
+            j.step = "recording".into();
+        })
+        .await?;
+
+        Ok(NatsReply::CameraRecordingStartReply(
+            CameraRecordingStarted {
+                recording: Box::new(recording.into()),
+            },
+        ))
+    }
+
+    pub async fn handle_camera_recording_stop() -> Result<NatsReply> {
+        let stop_job = create_job(JobKind::CameraRecordingStop).await?;
+        update_job_state(&stop_job.id, |j| {
+            j.status = JobStatus::Running;
+            j.step = "stopping pipeline".into();
+        })
+        .await?;
+
+        let recording = printnanny_edge_db::video_recording::VideoRecording::get_current()?;
+        let factory = PrintNannyPipelineFactory::default();
+
+        // send EOS signal to gstreamer
+        factory.stop_video_recording_pipeline().await?;
+
+        // enqueue cloud sync onto the in-process job queue instead of starting a
+        // one-shot printnanny-recording-*.service unit over D-Bus - the queue gives us
+        // bounded concurrency, retry/backoff, and a depth we can report over NATS
+        let settings = PrintNannySettings::new()?;
+        if settings.video_stream.recording.cloud_sync {
+            match &recording {
+                Some(recording) => match &recording.mp4_upload_url {
+                    Some(upload_url) => {
+                        let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+                        let (depth, job_id) = enqueue_sync_job(
+                            pi_id,
+                            recording.id.clone(),
+                            upload_url.clone(),
+                        )
+                        .await;
+                        info!(
+                            "Enqueued cloud sync job {} for recording {} - queue depth={}",
+                            job_id, recording.id, depth
+                        );
+                    }
+                    None => {
+                        warn!(
+                            "Recording {} has no mp4_upload_url set - skipping cloud sync enqueue",
+                            recording.id
+                        );
+                    }
+                },
+                None => {
+                    warn!("handle_camera_recording_stop called, but no active recording was found. You may need to manually run `printnanny cloud sync-video-recordings` to backup recording to PrintNanny Cloud.");
+                }
+            }
+        }
+
+        let recording = match recording {
+            Some(recording) => {
+                let now = Utc::now();
+                let probe = probe_video_recording(&recording.mp4_file_name);
+                if probe.probe_failed {
+                    warn!(
+                        "ffprobe could not read {} - marking recording {} as probe_failed",
+                        &recording.mp4_file_name, &recording.id
+                    );
+                }
+
+                // a thumbnail/blurhash placeholder only makes sense for a recording
+                // ffprobe could actually read
+                let (thumbnail_jpeg_file_name, blurhash) = if probe.probe_failed {
+                    (None, None)
+                } else {
+                    match generate_thumbnail_jpeg(&recording.mp4_file_name) {
+                        Some(jpeg_file_name) => {
+                            let blurhash = generate_blurhash(&jpeg_file_name);
+                            (Some(jpeg_file_name), blurhash)
+                        }
+                        None => (None, None),
+                    }
+                };
+
+                let update = printnanny_edge_db::video_recording::UpdateVideoRecording {
+                    recording_status: Some("done"),
+                    recording_end: Some(&now),
+                    deleted: None,
+                    recording_start: None,
+                    gcode_file_name: None,
+                    mp4_upload_url: None,
+                    mp4_download_url: None,
+                    cloud_sync_percent: None,
+                    cloud_sync_status: None,
+                    cloud_sync_start: None,
+                    cloud_sync_end: None,
+                    probe_failed: Some(probe.probe_failed),
+                    duration_seconds: probe.duration_seconds.as_ref(),
+                    container: probe.container.as_deref(),
+                    video_codec: probe.video_codec.as_deref(),
+                    audio_codec: probe.audio_codec.as_deref(),
+                    width: probe.width.as_ref(),
+                    height: probe.height.as_ref(),
+                    framerate: probe.framerate.as_ref(),
+                    bitrate: probe.bitrate.as_ref(),
+                    thumbnail_jpeg_file_name: thumbnail_jpeg_file_name.as_deref(),
+                    blurhash: blurhash.as_deref(),
+                };
+                printnanny_edge_db::video_recording::VideoRecording::update(&recording.id, update)?;
+                let recording =
+                    printnanny_edge_db::video_recording::VideoRecording::get_by_id(&recording.id)?;
+                Some(recording)
+            }
+            None => None,
+        };
+
+        update_job_state(&stop_job.id, |j| {
+            j.status = JobStatus::Completed;
+            j.step = "stopped".into();
+        })
+        .await?;
+
+        Ok(NatsReply::CameraRecordingStopReply(
+            CameraRecordingStopped {
+                recording: recording.map(|v| Box::new(v.into())),
+            },
+        ))
+    }
+
+    pub async fn handle_cloud_sync() -> Result<NatsReply> {
+        let start = chrono::offset::Utc::now().to_rfc3339();
+
+        let api = ApiService::new()?;
+        // sync cloud models to edge db
+        api.sync().await?;
+        // set optional pipelines to correct state
+        let gst_pipelines = PrintNannyPipelineFactory::default();
+        gst_pipelines.sync_optional_pipelines().await?;
+        let end = chrono::offset::Utc::now().to_rfc3339();
+
+        Ok(NatsReply::PrintNannyCloudSyncReply(
+            PrintNannyCloudSyncReply { start, end },
+        ))
+    }
+
+    // pi.{pi_id}.command.camera.recording.sync.progress is publish-only: progress updates
+    // are emitted by sync_video_recording_upload as it runs, so a direct request for this
+    // subject has nothing to reply with - controllers should subscribe instead.
+    pub fn handle_camera_recording_sync_progress() -> Result<NatsReply> {
+        Err(anyhow!(
+            "pi.{{pi_id}}.command.camera.recording.sync.progress is publish-only - subscribe to receive CameraRecordingSyncProgress updates"
+        ))
+    }
+
+    // message sent to "pi.{pi_id}.command.camera.recording.sync.enqueue" - pushes a
+    // recording onto the cloud sync queue, e.g. to retry a recording that was never
+    // enqueued automatically (cloud_sync was toggled on after the recording stopped)
+    pub async fn handle_cloud_sync_enqueue(request: &CloudSyncEnqueueRequest) -> Result<NatsReply> {
+        let recording = printnanny_edge_db::video_recording::VideoRecording::get_by_id(
+            &request.recording_id,
+        )?;
+        let upload_url = recording.mp4_upload_url.clone().ok_or_else(|| {
+            anyhow!(
+                "Recording {} has no mp4_upload_url set, cannot enqueue cloud sync",
+                request.recording_id
+            )
+        })?;
+        let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+        let (queue_depth, job_id) =
+            enqueue_sync_job(pi_id, request.recording_id.clone(), upload_url).await;
+        Ok(NatsReply::CloudSyncEnqueueReply(CloudSyncEnqueueReply {
+            recording_id: request.recording_id.clone(),
+            queue_depth,
+            job_id,
+        }))
+    }
+
+    // message sent to "pi.{pi_id}.command.camera.recording.sync.queue" - reports how
+    // many cloud sync jobs are queued/in-flight and how many attempts each has made
+    pub async fn handle_cloud_sync_queue_state() -> Result<NatsReply> {
+        Ok(NatsReply::CloudSyncQueueStateReply(sync_queue_state().await))
+    }
+
+    // message sent to "pi.{pi_id}.command.camera.recording.sync.cancel" - marks a
+    // queued job cancelled; a job already mid-upload finishes its current attempt
+    // before noticing the cancellation on its next retry check
+    pub async fn handle_cloud_sync_cancel(request: &CloudSyncCancelRequest) -> Result<NatsReply> {
+        let cancelled = cancel_sync_job(&request.recording_id).await;
+        Ok(NatsReply::CloudSyncCancelReply(CloudSyncCancelReply {
+            recording_id: request.recording_id.clone(),
+            cancelled,
+        }))
+    }
+
+    // message sent to "pi.{pi_id}.command.recording.supervisor.start" - acks
+    // immediately with the subjects to watch and spawns the detection-driven
+    // recording lifecycle in the background, mirroring
+    // handle_stream_unit_logs_request's enqueue-and-return shape
+    pub async fn handle_recording_supervisor_start() -> Result<NatsReply> {
+        let detection_settings = *PrintNannySettings::new()?.video_stream.detection;
+        let dataframe_subject = detection_settings.nats_subject.clone();
+        let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+        let finished_subject = format!("pi.{pi_id}.event.recording.finished");
+
+        tokio::spawn(recording_supervisor_loop(
+            dataframe_subject.clone(),
+            finished_subject.clone(),
+        ));
+
+        Ok(NatsReply::RecordingSupervisorStartReply(
+            RecordingSupervisorStartReply {
+                dataframe_subject,
+                finished_subject,
+            },
+        ))
+    }
+
+    // message sent to "pi.{pi_id}.command.job.status" - looks up a job's checkpointed
+    // state by id, e.g. the job_id returned by CloudSyncEnqueueReply
+    pub async fn handle_job_status(request: &JobStatusRequest) -> Result<NatsReply> {
+        let job = load_job_state(&request.job_id).await?;
+        Ok(NatsReply::JobStatusReply(job))
+    }
+
+    // pi.{pi_id}.command.job.progress is publish-only: job checkpoints publish
+    // JobProgress as they update, so a direct request for this subject has nothing to
+    // reply with - controllers should subscribe instead.
+    pub fn handle_job_progress() -> Result<NatsReply> {
+        Err(anyhow!(
+            "pi.{{pi_id}}.command.job.progress is publish-only - subscribe to receive JobProgress updates"
+        ))
+    }
+
+    // message messages sent to: "pi.{pi_id}.device_info.load"
+    pub async fn handle_device_info_load() -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        let issue = fs::read_to_string(settings.paths.issue_txt).await?;
+        let os_release = fs::read_to_string(settings.paths.os_release).await?;
+
+        let ifaddrs = tokio::task::spawn_blocking(|| match nix::ifaddrs::getifaddrs() {
+            Ok(result) => result
+                .map(
+                    |v| printnanny_settings::printnanny_asyncapi_models::NetworkInterfaceAddress {
+                        interface_name: v.interface_name,
+                        flags: v.flags.bits(),
+                        address: v.address.map(|v| v.to_string()),
+                        netmask: v.netmask.map(|v| v.to_string()),
+                        destination: v.destination.map(|v| v.to_string()),
+                        broadcast: v.broadcast.map(|v| v.to_string()),
+                    },
+                )
+                .collect(),
+            Err(e) => {
+                error!("Error loading ifaddrs {}", e.to_string());
+                vec![]
+            }
+        })
+        .await?;
+
+        // let ifaddrs = ifaddrs
+        //     .map(
+        //         |v| printnanny_settings::printnanny_asyncapi_models::NetworkInterfaceAddress {
+        //             interface_name: v.interface_name,
+        //             flags: v.flags.bits(),
+        //             address: v.address.map(|v| v.to_string()),
+        //             netmask: v.netmask.map(|v| v.to_string()),
+        //             destination: v.destination.map(|v| v.to_string()),
+        //             broadcast: v.broadcast.map(|v| v.to_string()),
+        //         },
+        //     )
+        //     .collect();
+
+        Ok(NatsReply::DeviceInfoLoadReply(DeviceInfoLoadReply {
+            issue,
+            os_release,
+            printnanny_cli_version: "".into(), // TODO
+            ifaddrs,
+        }))
+    }
+
+    // handle messages sent to: "pi.{pi_id}.capabilities"
+    pub async fn handle_capabilities() -> Result<NatsReply> {
+        Ok(NatsReply::CapabilitiesReply(CapabilitiesReply {
+            protocol_version: NATS_PROTOCOL_VERSION,
+            capabilities: Self::supported_capabilities()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }))
+    }
+
+    // constructed from deserialize_payload_inner's fallback arm - answers with the
+    // same capability set as handle_capabilities() so a client can tell whether it
+    // mis-typed the subject or is simply newer than the daemon it talked to
+    pub async fn handle_unsupported_capability(subject_pattern: &str) -> Result<NatsReply> {
+        Ok(NatsReply::UnsupportedCapabilityReply(
+            UnsupportedCapabilityReply {
+                subject_pattern: subject_pattern.to_string(),
+                protocol_version: NATS_PROTOCOL_VERSION,
+                capabilities: Self::supported_capabilities()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            },
+        ))
+    }
+
+    // handle messages sent to: "pi.{pi_id}.settings.printnanny.cloud.auth"
+    pub async fn handle_printnanny_cloud_auth(
+        request: &PrintNannyCloudAuthRequest,
+    ) -> Result<NatsReply> {
+        let api_service = ApiService::new()?;
+        let result = api_service
+            .connect_cloud_account(request.api_url.clone(), request.api_token.clone())
+            .await;
+
+        let result = match result {
+            Ok(_) => {
+                info!(
+                    "Successfully connected PrintNanny Cloud account: {}",
+                    request.email
+                );
+                NatsReply::PrintNannyCloudAuthReply(PrintNannyCloudAuthReply {
+                    status_code: 200,
+                    msg: format!("Success! Connected account: {}", request.email),
+                })
+            }
+            Err(e) => {
+                error!("Failed to connect PrintNanny Cloud account, error: {}", e);
+                NatsReply::PrintNannyCloudAuthReply(PrintNannyCloudAuthReply {
+                    status_code: 403,
+                    msg: format!("Error connecting account: {}", e),
+                })
+            }
+        };
+        Ok(result)
+    }
+
+    pub async fn handle_crash_report(request: &CrashReportOsLogsRequest) -> Result<NatsReply> {
+        let api_service = ApiService::new()?;
+        let result = api_service.crash_report_update(&request.id).await?;
+        Ok(NatsReply::CrashReportOsLogsReply(CrashReportOsLogsReply {
+            id: result.id,
+            updated_dt: result.updated_dt,
+        }))
+    }
+
+    pub fn handle_cameras_load() -> Result<NatsReply> {
+        let cameras: Vec<printnanny_asyncapi_models::Camera> =
+            CameraVideoSource::from_libcamera_list()?
+                .iter()
+                .map(|v| v.into())
+                .collect();
+
+        Ok(NatsReply::CameraLoadReply(
+            printnanny_asyncapi_models::cameras_load_reply::CamerasLoadReply { cameras },
+        ))
+    }
+
+    pub async fn handle_printnanny_settings_revert(
+        request: &SettingsFileRevertRequest,
+    ) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+
+        // revert commit
+        let oid = git2::Oid::from_str(&request.git_commit)?;
+        settings.git_revert_hooks(Some(oid)).await?;
+        let files = vec![settings.to_payload(SettingsApp::Printnanny).await?];
+        Self::build_settings_revert_reply(request, &settings, files)
+    }
+
+    async fn handle_octoprint_settings_revert(
         request: &SettingsFileRevertRequest,
     ) -> Result<NatsReply> {
         let settings = PrintNannySettings::new()?;
@@ -626,6 +2536,83 @@ impl NatsRequest {
         }
     }
 
+    // writes a single file onto the already-checked-out working tree, without staging
+    // or committing it - the caller is responsible for committing (or rolling
+    // back) once every file in the batch has applied cleanly
+    async fn apply_settings_file_uncommitted(
+        settings: &PrintNannySettings,
+        file: &SettingsFile,
+    ) -> Result<SettingsFile> {
+        match *file.app {
+            SettingsApp::Printnanny => {
+                settings.pre_save().await?;
+                settings.write_settings(&file.content)?;
+                settings.post_save().await?;
+                Ok(settings.to_payload(SettingsApp::Printnanny).await?)
+            }
+            SettingsApp::Octoprint => {
+                settings.octoprint.pre_save().await?;
+                settings.octoprint.write_settings(&file.content)?;
+                settings.octoprint.post_save().await?;
+                Ok(settings.octoprint.to_payload(SettingsApp::Octoprint).await?)
+            }
+            SettingsApp::Moonraker => {
+                settings.moonraker.pre_save().await?;
+                settings.moonraker.write_settings(&file.content)?;
+                settings.moonraker.post_save().await?;
+                Ok(settings.moonraker.to_payload(SettingsApp::Moonraker).await?)
+            }
+            SettingsApp::Klipper => {
+                settings.klipper.pre_save().await?;
+                settings.klipper.write_settings(&file.content)?;
+                settings.klipper.post_save().await?;
+                Ok(settings.klipper.to_payload(SettingsApp::Klipper).await?)
+            }
+        }
+    }
+
+    // applies every file in the batch and commits them as a single git revision, so a UI
+    // changing several apps at once gets an all-or-nothing transaction instead of leaving
+    // some apps applied and others not if one fails partway through
+    pub async fn handle_settings_apply_batch(
+        request: &SettingsFileApplyBatchRequest,
+    ) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        let rollback_oid = git2::Oid::from_str(&request.git_head_commit)?;
+        // bail out before writing anything if the client's head doesn't resolve to a
+        // commit in this repo - there'd be nothing to roll back to
+        settings.get_git_repo()?.find_commit(rollback_oid)?;
+
+        let mut applied = Vec::with_capacity(request.files.len());
+        for file in &request.files {
+            match Self::apply_settings_file_uncommitted(&settings, file).await {
+                Ok(file) => applied.push(file),
+                Err(e) => {
+                    warn!(
+                        "Batch settings apply failed, resetting working tree back to {}: {}",
+                        request.git_head_commit, e
+                    );
+                    settings.git_reset_hard(rollback_oid)?;
+                    return Err(e);
+                }
+            }
+        }
+
+        settings.git_add_all()?;
+        settings.git_commit(Some(request.git_commit_msg.clone()))?;
+
+        let git_head_commit = settings.get_git_head_commit()?.oid;
+        let git_history: Vec<printnanny_asyncapi_models::GitCommit> =
+            settings.get_rev_list()?.iter().map(|r| r.into()).collect();
+        Ok(NatsReply::SettingsFileApplyBatchReply(
+            SettingsFileApplyBatchReply {
+                files: applied,
+                git_head_commit,
+                git_history,
+            },
+        ))
+    }
+
     pub async fn handle_camera_settings_load() -> Result<NatsReply> {
         let settings = PrintNannySettings::new()?;
         Ok(NatsReply::CameraSettingsFileLoadReply(
@@ -635,6 +2622,21 @@ impl NatsRequest {
 
     pub async fn handle_camera_settings_apply(request: &VideoStreamSettings) -> Result<NatsReply> {
         info!("Received request: {:#?}", request);
+
+        // reject an apply before it's ever written to disk if the requested device/mode
+        // doesn't actually exist - otherwise this only surfaces later when the pipeline
+        // fails to start, with a much less actionable error
+        let probe = probe_camera_device(
+            &request.camera.device_name,
+            request.camera.width,
+            request.camera.height,
+            request.camera.framerate,
+        )?;
+        info!(
+            "Probed camera device {}: {:?}",
+            request.camera.device_name, probe.supported_streams
+        );
+
         let mut settings = PrintNannySettings::new()?;
 
         settings.video_stream = request.clone().into();
@@ -643,7 +2645,10 @@ impl NatsRequest {
         let commit_msg = format!("Updated PrintNannySettings.camera @ {ts:?}");
         settings.save_and_commit(&content, Some(commit_msg)).await?;
         Ok(NatsReply::CameraSettingsFileApplyReply(
-            settings.video_stream.into(),
+            CameraSettingsApplyResult {
+                video_stream: settings.video_stream.into(),
+                supported_streams: probe.supported_streams,
+            },
         ))
     }
 
@@ -656,11 +2661,125 @@ impl NatsRequest {
         }
     }
 
+    // message sent to "pi.{pi_id}.settings.file.diff" - a unified diff of the file
+    // managed by `request.app` between two commits, restricted to that file's path so
+    // an unrelated change elsewhere in the settings repo doesn't show up in the review
+    pub async fn handle_settings_file_diff(request: &SettingsFileDiffRequest) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        let from_oid = git2::Oid::from_str(&request.from_commit)?;
+        let to_oid = git2::Oid::from_str(&request.to_commit)?;
+
+        let pathspec = match *request.app {
+            SettingsApp::Printnanny => settings.get_settings_file(),
+            SettingsApp::Octoprint => settings.octoprint.get_settings_file(),
+            SettingsApp::Moonraker => settings.moonraker.get_settings_file(),
+            SettingsApp::Klipper => settings.klipper.get_settings_file(),
+        }
+        .display()
+        .to_string();
+        let files = settings.git_diff_commits(from_oid, to_oid, Some(&pathspec))?;
+
+        Ok(NatsReply::SettingsFileDiffReply(SettingsFileDiffReply {
+            app: request.app.clone(),
+            from_commit: request.from_commit.clone(),
+            to_commit: request.to_commit.clone(),
+            files,
+        }))
+    }
+
+    pub async fn handle_settings_remote_sync(
+        request: &SettingsRemoteSyncRequest,
+    ) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        settings.git_remote_add(&request.remote_name, &request.remote_url)?;
+        Ok(NatsReply::SettingsRemoteSyncReply(SettingsRemoteSyncReply {
+            remote_name: request.remote_name.clone(),
+            remote_url: request.remote_url.clone(),
+        }))
+    }
+
+    pub async fn handle_settings_remote_push(
+        request: &SettingsRemotePushRequest,
+    ) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        settings.git_push(&request.remote_name)?;
+        let git_head_commit = settings.get_git_head_commit()?.oid;
+        Ok(NatsReply::SettingsRemotePushReply(SettingsRemotePushReply {
+            remote_name: request.remote_name.clone(),
+            git_head_commit,
+        }))
+    }
+
+    pub async fn handle_settings_remote_pull(
+        request: &SettingsRemotePullRequest,
+    ) -> Result<NatsReply> {
+        let settings = PrintNannySettings::new()?;
+        settings.git_fetch(&request.remote_name)?;
+        let reply = match settings.git_fast_forward(&request.remote_name)? {
+            GitFastForwardOutcome::UpToDate => SettingsRemotePullReply::UpToDate {
+                git_head_commit: settings.get_git_head_commit()?.oid,
+            },
+            GitFastForwardOutcome::FastForwarded(oid) => SettingsRemotePullReply::FastForwarded {
+                git_head_commit: oid.to_string(),
+            },
+            // diverged - fall back to a three-way merge instead of reporting the
+            // raw oids and leaving local edits unreconciled
+            GitFastForwardOutcome::Diverged { .. } => {
+                match settings.git_merge_remote().await {
+                    Ok(oid) => SettingsRemotePullReply::Merged {
+                        git_head_commit: oid.to_string(),
+                    },
+                    Err(VersionControlledSettingsError::MergeConflict { files, diff }) => {
+                        SettingsRemotePullReply::Conflict { files, diff }
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        };
+        Ok(NatsReply::SettingsRemotePullReply(reply))
+    }
+
+    pub async fn handle_settings_watcher_start(
+        _request: &SettingsWatcherStartRequest,
+    ) -> Result<NatsReply> {
+        let mut running = SETTINGS_WATCHER.lock().await;
+        if let Some(handle) = running.as_ref() {
+            return Ok(NatsReply::SettingsWatcherStartReply(SettingsWatcherStartReply {
+                watching: handle.watching.clone(),
+                debounce_ms: handle.debounce_ms,
+            }));
+        }
+
+        let settings = PrintNannySettings::new()?;
+        let handle = start_settings_watcher(settings).await?;
+        let reply = SettingsWatcherStartReply {
+            watching: handle.watching.clone(),
+            debounce_ms: handle.debounce_ms,
+        };
+        *running = Some(handle);
+        Ok(NatsReply::SettingsWatcherStartReply(reply))
+    }
+
+    pub async fn handle_settings_watcher_stop(
+        _request: &SettingsWatcherStopRequest,
+    ) -> Result<NatsReply> {
+        let mut running = SETTINGS_WATCHER.lock().await;
+        let stopped = match running.take() {
+            Some(handle) => {
+                handle.stop().await;
+                true
+            }
+            None => false,
+        };
+        Ok(NatsReply::SettingsWatcherStopReply(SettingsWatcherStopReply {
+            stopped,
+        }))
+    }
+
     pub async fn handle_disable_units_request(
         request: &SystemdManagerUnitFilesRequest,
     ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+        let (_connection, proxy) = get_systemd_manager().await?;
         let changes = proxy
             .disable_unit_files(request.files.clone(), false)
             .await?;
@@ -668,22 +2787,25 @@ impl NatsRequest {
             .iter()
             .map(
                 |(change_type, file, destination)| match change_type.as_str() {
-                    "symlink" => SystemdUnitChange {
+                    // both arms collapse to the same variant as handle_enable_units_request -
+                    // tracked there, not re-litigated here
+                    "symlink" => Ok(SystemdUnitChange {
                         change: Box::new(SystemdUnitChangeState::Symlink),
                         file: file.to_string(),
                         destination: destination.to_string(),
-                    },
-                    "unlink" => SystemdUnitChange {
+                    }),
+                    "unlink" => Ok(SystemdUnitChange {
                         change: Box::new(SystemdUnitChangeState::Symlink),
                         file: file.to_string(),
                         destination: destination.to_string(),
-                    },
-                    _ => {
-                        unimplemented!("No implementation for systemd change type {}", change_type)
-                    }
+                    }),
+                    _ => Err(anyhow!(
+                        "No implementation for systemd change type {}",
+                        change_type
+                    )),
                 },
             )
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
         info!(
             "Disabled units: {:?} - changes: {:?}",
             request.files, changes
@@ -701,9 +2823,7 @@ impl NatsRequest {
     pub async fn handle_enable_units_request(
         request: &SystemdManagerUnitFilesRequest,
     ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-
-        let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+        let (_connection, proxy) = get_systemd_manager().await?;
         let (_enablement_info, changes) = proxy
             .enable_unit_files(request.files.clone(), false, false)
             .await?;
@@ -712,22 +2832,23 @@ impl NatsRequest {
             .iter()
             .map(
                 |(change_type, file, destination)| match change_type.as_str() {
-                    "symlink" => SystemdUnitChange {
+                    "symlink" => Ok(SystemdUnitChange {
                         change: Box::new(SystemdUnitChangeState::Symlink),
                         file: file.to_string(),
                         destination: destination.to_string(),
-                    },
-                    "unlink" => SystemdUnitChange {
+                    }),
+                    "unlink" => Ok(SystemdUnitChange {
                         change: Box::new(SystemdUnitChangeState::Symlink),
                         file: file.to_string(),
                         destination: destination.to_string(),
-                    },
-                    _ => {
-                        unimplemented!("No implementation for systemd change type {}", change_type)
-                    }
+                    }),
+                    _ => Err(anyhow!(
+                        "No implementation for systemd change type {}",
+                        change_type
+                    )),
                 },
             )
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
         info!(
             "Enabled units: {:?} - changes: {:?}",
             request.files, changes
@@ -742,11 +2863,120 @@ impl NatsRequest {
         ))
     }
 
+    // applies a batch of enable/disable operations as a single transaction - records
+    // each unit's enable state before touching anything, and if any operation in the
+    // batch fails, reverses everything already applied back to its recorded state
+    // before returning an error naming the failing unit and the rollback outcome
+    pub async fn handle_apply_units_request(
+        request: &SystemdManagerApplyUnitsRequest,
+    ) -> Result<NatsReply> {
+        let (_connection, proxy) = get_systemd_manager().await?;
+
+        let mut prior_enabled = Vec::with_capacity(request.operations.len());
+        for (unit, _op) in &request.operations {
+            let state = proxy.get_unit_file_state(unit.clone()).await?;
+            prior_enabled.push(matches!(
+                state.as_str(),
+                "enabled" | "enabled-runtime" | "linked" | "linked-runtime"
+            ));
+        }
+
+        let mut changes = Vec::with_capacity(request.operations.len());
+        for (applied, (unit, op)) in request.operations.iter().enumerate() {
+            match Self::apply_unit_op(&proxy, unit, op).await {
+                Ok(mut unit_changes) => changes.append(&mut unit_changes),
+                Err(e) => {
+                    let rollback = Self::rollback_applied_units(
+                        &proxy,
+                        &request.operations[..applied],
+                        &prior_enabled[..applied],
+                    )
+                    .await;
+                    return Err(anyhow!(
+                        "Failed to apply systemd unit op for {}: {} - rollback: {:?}",
+                        unit,
+                        e,
+                        rollback
+                    ));
+                }
+            }
+        }
+
+        proxy.reload().await?;
+
+        Ok(NatsReply::SystemdManagerApplyUnitsReply(
+            SystemdManagerApplyUnitsReply { changes },
+        ))
+    }
+
+    // applies a single enable/disable operation, mapping the raw D-Bus change tuples
+    // to SystemdUnitChange the same way handle_{enable,disable}_units_request do
+    async fn apply_unit_op(
+        proxy: &zbus_systemd::systemd1::ManagerProxy<'_>,
+        unit: &str,
+        op: &SystemdUnitOp,
+    ) -> Result<Vec<SystemdUnitChange>> {
+        let raw_changes = match op {
+            SystemdUnitOp::Enable => {
+                let (_enablement_info, changes) = proxy
+                    .enable_unit_files(vec![unit.to_string()], false, false)
+                    .await?;
+                changes
+            }
+            SystemdUnitOp::Disable => {
+                proxy
+                    .disable_unit_files(vec![unit.to_string()], false)
+                    .await?
+            }
+        };
+        raw_changes
+            .iter()
+            .map(
+                |(change_type, file, destination)| match change_type.as_str() {
+                    "symlink" | "unlink" => Ok(SystemdUnitChange {
+                        change: Box::new(SystemdUnitChangeState::Symlink),
+                        file: file.to_string(),
+                        destination: destination.to_string(),
+                    }),
+                    _ => Err(anyhow!(
+                        "No implementation for systemd change type {}",
+                        change_type
+                    )),
+                },
+            )
+            .collect()
+    }
+
+    // reverses the units in `applied` (in reverse order), restoring each one's
+    // pre-batch enable state - best-effort, so one unit failing to restore doesn't
+    // stop the others from being attempted
+    async fn rollback_applied_units(
+        proxy: &zbus_systemd::systemd1::ManagerProxy<'_>,
+        applied: &[(String, SystemdUnitOp)],
+        prior_enabled: &[bool],
+    ) -> Vec<SystemdUnitRollback> {
+        let mut outcomes = Vec::with_capacity(applied.len());
+        for ((unit, _op), was_enabled) in applied.iter().zip(prior_enabled).rev() {
+            let restore_op = if *was_enabled {
+                SystemdUnitOp::Enable
+            } else {
+                SystemdUnitOp::Disable
+            };
+            let result = Self::apply_unit_op(proxy, unit, &restore_op).await;
+            outcomes.push(SystemdUnitRollback {
+                unit: unit.clone(),
+                restored: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+        let _ = proxy.reload().await;
+        outcomes
+    }
+
     async fn get_systemd_unit(
         unit_name: String,
     ) -> Result<printnanny_asyncapi_models::SystemdUnit> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = printnanny_dbus::zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+        let (_connection, proxy) = get_systemd_manager().await?;
         let unit_path = proxy.load_unit(unit_name.clone()).await?; // load_unit is similar to get_unit, but will first attempt to load unit file
         let unit =
             printnanny_dbus::systemd1::models::SystemdUnit::from_owned_object_path(unit_path)
@@ -764,106 +2994,861 @@ impl NatsRequest {
         ))
     }
 
-    async fn handle_get_unit_file_state_request(
-        request: &SystemdManagerGetUnitRequest,
-    ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = printnanny_dbus::zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+    async fn handle_get_unit_file_state_request(
+        request: &SystemdManagerGetUnitRequest,
+    ) -> Result<NatsReply> {
+        let (_connection, proxy) = get_systemd_manager().await?;
+
+        let unit_file_state = proxy.get_unit_file_state(request.unit_name.clone()).await?;
+
+        let unit_file_state = match unit_file_state.as_str() {
+            "enabled" => SystemdUnitFileState::Enabled,
+            "enabled-runtime" => SystemdUnitFileState::EnabledMinusRuntime,
+            "linked" => SystemdUnitFileState::Linked,
+            "linked-runtime" => SystemdUnitFileState::LinkedMinusRuntime,
+            "masked" => SystemdUnitFileState::Masked,
+            "masked-runtime" => SystemdUnitFileState::MaskedMinusRuntime,
+            "static" => SystemdUnitFileState::Static,
+            "disabled" => SystemdUnitFileState::Disabled,
+            "invalid" => SystemdUnitFileState::Invalid,
+            other => return Err(anyhow!("Unrecognized systemd unit file state: {}", other)),
+        };
+
+        Ok(NatsReply::SystemdManagerGetUnitFileStateReply(
+            SystemdManagerGetUnitFileStateReply {
+                unit_file_state: Box::new(unit_file_state),
+                request: Box::new(request.clone()),
+            },
+        ))
+    }
+
+    // TODO
+    // Job type reload is not applicable for unit octoprint.service.
+    // async fn handle_reload_unit_request(
+    //     &self,
+    //     request: &SystemdManagerReloadUnitRequest,
+    // ) -> Result<NatsReply> {
+    //     let connection = zbus::Connection::system().await?;
+    //     let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
+    //     let job = proxy
+    //         .reload_unit(request.unit_name.clone(), "replace".into())
+    //         .await?;
+    //     let unit = self.get_systemd_unit(request.unit_name.clone()).await?;
+
+    //     Ok(NatsReply::SystemdManagerReloadUnitReply(
+    //         SystemdManagerReloadUnitReply {
+    //             job: job.to_string(),
+    //             unit: Box::new(unit),
+    //         },
+    //     ))
+    // }
+
+    // subscribes to the Manager's JobRemoved signal *before* dispatching `dispatch`, then
+    // awaits the one matching the job path `dispatch` returns, so the caller can tell a
+    // unit actually came up (or why it didn't) instead of just that D-Bus accepted the job
+    async fn dispatch_and_await_job<F, Fut>(
+        proxy: &zbus_systemd::systemd1::ManagerProxy<'_>,
+        dispatch: F,
+    ) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = zbus::Result<zbus::zvariant::OwnedObjectPath>>,
+    {
+        let mut job_removed = proxy.receive_job_removed().await?;
+        let job = dispatch().await?;
+
+        let result = tokio::time::timeout(systemd_job_timeout(), async {
+            while let Some(signal) = job_removed.next().await {
+                let args = signal.args()?;
+                if args.job == job {
+                    return Ok(args.result.to_string());
+                }
+            }
+            Err(anyhow!(
+                "JobRemoved signal stream ended before job {} completed",
+                job
+            ))
+        })
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for systemd job {} to finish", job))??;
+
+        if result != "done" {
+            return Err(anyhow!(
+                "systemd job {} finished with result \"{}\"",
+                job,
+                result
+            ));
+        }
+        Ok(result)
+    }
+
+    async fn handle_restart_unit_request(
+        request: &SystemdManagerRestartUnitRequest,
+    ) -> Result<NatsReply> {
+        let (_connection, proxy) = get_systemd_manager().await?;
+        let unit_name = request.unit_name.clone();
+        let job = Self::dispatch_and_await_job(&proxy, || {
+            proxy.restart_unit(unit_name.clone(), "replace".into())
+        })
+        .await?;
+        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
+
+        Ok(NatsReply::SystemdManagerRestartUnitReply(
+            SystemdManagerRestartUnitReply {
+                job,
+                unit: Box::new(unit),
+            },
+        ))
+    }
+
+    async fn handle_start_unit_request(
+        request: &SystemdManagerStartUnitRequest,
+    ) -> Result<NatsReply> {
+        let (_connection, proxy) = get_systemd_manager().await?;
+        let unit_name = request.unit_name.clone();
+        let job = Self::dispatch_and_await_job(&proxy, || {
+            proxy.start_unit(unit_name.clone(), "replace".into())
+        })
+        .await?;
+        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
+        Ok(NatsReply::SystemdManagerStartUnitReply(
+            SystemdManagerStartUnitReply {
+                job,
+                unit: Box::new(unit),
+            },
+        ))
+    }
+
+    async fn handle_stop_unit_request(
+        request: &SystemdManagerStopUnitRequest,
+    ) -> Result<NatsReply> {
+        let (_connection, proxy) = get_systemd_manager().await?;
+        let unit_name = request.unit_name.clone();
+        let job = Self::dispatch_and_await_job(&proxy, || {
+            proxy.stop_unit(unit_name.clone(), "replace".into())
+        })
+        .await?;
+        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
+        Ok(NatsReply::SystemdManagerStopUnitReply(
+            SystemdManagerStopUnitReply {
+                job,
+                unit: Box::new(unit),
+            },
+        ))
+    }
+
+    // message sent to "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs" -
+    // acks immediately with the per-follow subjects and spawns the journalctl follow
+    // in the background, mirroring handle_cloud_sync_enqueue's enqueue-and-return shape
+    pub async fn handle_stream_unit_logs_request(
+        request: &SystemdManagerStreamUnitLogsRequest,
+    ) -> Result<NatsReply> {
+        let pi_id = printnanny_edge_db::cloud::Pi::get()?.id.to_string();
+        let base = format!(
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs.{}",
+            request.unit_name
+        );
+        let log_subject = format!("{base}.log");
+        let capacity_subject = format!("{base}.capacity");
+        let heartbeat_subject = format!("{base}.heartbeat");
+
+        tokio::spawn(stream_unit_logs(
+            request.clone(),
+            log_subject.clone(),
+            capacity_subject.clone(),
+            heartbeat_subject.clone(),
+        ));
+
+        Ok(NatsReply::SystemdManagerStreamUnitLogsReply(
+            SystemdManagerStreamUnitLogsReply {
+                unit_name: request.unit_name.clone(),
+                log_subject,
+                capacity_subject,
+                heartbeat_subject,
+            },
+        ))
+    }
+}
+
+// watches dataframe_subject for the df pipeline's per-frame detection confidence and
+// drives the recording lifecycle off it: starts a recording on the first
+// above-threshold detection, stops it once no above-threshold detection has been seen
+// for idle_timeout, then publishes RecordingFinished on finished_subject. Errors are
+// logged and swallowed since this is a detached background task with no caller left
+// to return to.
+async fn recording_supervisor_loop(dataframe_subject: String, finished_subject: String) {
+    if let Err(e) = recording_supervisor_inner(&dataframe_subject, &finished_subject).await {
+        error!("Recording supervisor exited with error: {}", e);
+    }
+}
+
+async fn recording_supervisor_inner(dataframe_subject: &str, finished_subject: &str) -> Result<()> {
+    let recording_settings = *PrintNannySettings::new()?.video_stream.recording;
+    let confidence_threshold = recording_settings.confidence_threshold as f64 / 100.0;
+    let idle_timeout =
+        std::time::Duration::from_secs(recording_settings.idle_timeout_seconds.max(1) as u64);
+
+    let nats_client = connect_nats_client().await?;
+    let mut dataframes = nats_client.subscribe(dataframe_subject.to_string()).await?;
+    let mut idle_check = tokio::time::interval(idle_timeout);
+    idle_check.reset();
+
+    // tracked here rather than read back from VideoRecording::get_current() each tick,
+    // since handle_camera_recording_stop() already clears the "current" row as part of
+    // finishing it
+    let mut recording_active = false;
+    let mut last_detected_at = tokio::time::Instant::now();
+
+    loop {
+        tokio::select! {
+            message = dataframes.next() => {
+                let message = match message {
+                    Some(message) => message,
+                    // subscription closed out from under us - nothing left to supervise
+                    None => break,
+                };
+                let record = match serde_json::from_slice::<DataframeRecord>(&message.payload) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        warn!("Recording supervisor could not parse dataframe payload: {}", e);
+                        continue;
+                    }
+                };
+                if record.max_confidence() >= confidence_threshold {
+                    last_detected_at = tokio::time::Instant::now();
+                    idle_check.reset();
+                    if !recording_active {
+                        match NatsRequest::handle_camera_recording_start().await {
+                            Ok(_) => recording_active = true,
+                            Err(e) => error!("Recording supervisor failed to start recording: {}", e),
+                        }
+                    }
+                }
+            }
+            _ = idle_check.tick() => {
+                if recording_active && last_detected_at.elapsed() >= idle_timeout {
+                    recording_active = false;
+                    if let Err(e) =
+                        finish_supervised_recording(finished_subject, recording_settings.auto_process).await
+                    {
+                        error!("Recording supervisor failed to finish recording: {}", e);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// stops the active recording pipeline (handle_camera_recording_stop sends EOS so every
+// recording pad is flushed before the row is marked done), then publishes
+// RecordingFinished so an auto_process step can mux/timelapse/upload the segments
+async fn finish_supervised_recording(finished_subject: &str, auto_process: bool) -> Result<()> {
+    let reply = NatsRequest::handle_camera_recording_stop().await?;
+    let recording = match reply {
+        NatsReply::CameraRecordingStopReply(stopped) => stopped.recording.ok_or_else(|| {
+            anyhow!("handle_camera_recording_stop reported no active recording to finish")
+        })?,
+        other => {
+            return Err(anyhow!(
+                "handle_camera_recording_stop returned an unexpected reply variant: {:?}",
+                other
+            ))
+        }
+    };
+
+    let event = RecordingFinished {
+        recording_id: recording.id.clone(),
+        mp4_file_name: recording.mp4_file_name.clone(),
+        segment_file_names: list_recording_segments(&recording.mp4_file_name),
+    };
+
+    let nats_client = connect_nats_client().await?;
+    let payload = serde_json::to_vec(&event)?;
+    nats_client
+        .publish(finished_subject.to_string(), payload.into())
+        .await?;
+
+    if auto_process {
+        tokio::spawn(mux_recording_segments(event));
+    }
+    Ok(())
+}
+
+// splitmuxsink writes numbered parts alongside mp4_file_name as
+// "<mp4_file_name>.part-00000.mp4", "<mp4_file_name>.part-00001.mp4", ... - list them
+// back out in order so RecordingFinished carries exactly what's on disk
+fn list_recording_segments(mp4_file_name: &str) -> Vec<String> {
+    let path = std::path::Path::new(mp4_file_name);
+    let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or(mp4_file_name);
+    let prefix = format!("{file_name}.part-");
+
+    let mut segments: Vec<String> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.starts_with(&prefix))
+            .map(|name| dir.join(name).display().to_string())
+            .collect(),
+        Err(e) => {
+            warn!(
+                "Recording supervisor could not list segments in {}: {}",
+                dir.display(),
+                e
+            );
+            Vec::new()
+        }
+    };
+    segments.sort();
+    segments
+}
+
+// auto_process's default post-processing step: concatenates the recording's segments
+// into its final mp4_file_name via ffmpeg's concat demuxer, the same shelling-out
+// convention generate_thumbnail_jpeg already uses for this recording pipeline
+async fn mux_recording_segments(event: RecordingFinished) {
+    if event.segment_file_names.is_empty() {
+        warn!(
+            "Recording {} finished with no segments to mux into {}",
+            event.recording_id, event.mp4_file_name
+        );
+        return;
+    }
+
+    let concat_list = format!("{}.concat.txt", event.mp4_file_name);
+    let contents = event
+        .segment_file_names
+        .iter()
+        .map(|segment| format!("file '{segment}'\n"))
+        .collect::<String>();
+    if let Err(e) = fs::write(&concat_list, contents).await {
+        error!(
+            "Failed to write ffmpeg concat list for recording {}: {}",
+            event.recording_id, e
+        );
+        return;
+    }
+
+    let output = tokio::process::Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-f",
+            "concat",
+            "-safe",
+            "0",
+            "-i",
+            &concat_list,
+            "-c",
+            "copy",
+            &event.mp4_file_name,
+        ])
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => {
+            info!(
+                "Muxed {} segments into {} for recording {}",
+                event.segment_file_names.len(),
+                event.mp4_file_name,
+                event.recording_id
+            );
+        }
+        Ok(output) => error!(
+            "ffmpeg exited with non-zero status muxing recording {}: {}",
+            event.recording_id,
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(e) => error!(
+            "Failed to spawn ffmpeg to mux recording {}: {}",
+            event.recording_id, e
+        ),
+    }
+}
+
+// follows `request.unit_name`'s journal via journalctl, publishing SystemdUnitLogFrame
+// on log_subject until the subscriber stops heartbeating or the journal hits EOF
+// (--follow never does, unless journalctl itself exits). Errors are logged and
+// swallowed since this is a detached background task with no caller left to return to.
+async fn stream_unit_logs(
+    request: SystemdManagerStreamUnitLogsRequest,
+    log_subject: String,
+    capacity_subject: String,
+    heartbeat_subject: String,
+) {
+    if let Err(e) =
+        stream_unit_logs_inner(&request, &log_subject, &capacity_subject, &heartbeat_subject).await
+    {
+        error!(
+            "Journal stream for unit {} ended with error: {}",
+            request.unit_name, e
+        );
+    }
+}
+
+async fn stream_unit_logs_inner(
+    request: &SystemdManagerStreamUnitLogsRequest,
+    log_subject: &str,
+    capacity_subject: &str,
+    heartbeat_subject: &str,
+) -> Result<()> {
+    let nats_client = connect_nats_client().await?;
+
+    // how many more frames the subscriber has told us it can buffer
+    let capacity = std::sync::Arc::new(std::sync::atomic::AtomicI64::new(0));
+    let last_seen = std::sync::Arc::new(tokio::sync::Mutex::new(tokio::time::Instant::now()));
+
+    let capacity_task = {
+        let capacity = capacity.clone();
+        let mut sub = nats_client.subscribe(capacity_subject.to_string()).await?;
+        tokio::spawn(async move {
+            while let Some(message) = sub.next().await {
+                if let Ok(update) =
+                    serde_json::from_slice::<SystemdUnitLogCapacity>(&message.payload)
+                {
+                    capacity.store(update.available, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        })
+    };
+
+    // a heartbeat arriving from the subscriber is a liveness signal on its own, same
+    // as the ping this task publishes below
+    let heartbeat_sub_task = {
+        let last_seen = last_seen.clone();
+        let mut sub = nats_client.subscribe(heartbeat_subject.to_string()).await?;
+        tokio::spawn(async move {
+            while sub.next().await.is_some() {
+                *last_seen.lock().await = tokio::time::Instant::now();
+            }
+        })
+    };
+
+    let heartbeat_ping_task = {
+        let nats_client = nats_client.clone();
+        let heartbeat_subject = heartbeat_subject.to_string();
+        let unit_name = request.unit_name.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(UNIT_LOG_HEARTBEAT_INTERVAL).await;
+                let heartbeat = SystemdUnitLogHeartbeat {
+                    unit_name: unit_name.clone(),
+                };
+                match serde_json::to_vec(&heartbeat) {
+                    Ok(payload) => {
+                        if let Err(e) = nats_client
+                            .publish(heartbeat_subject.clone(), payload.into())
+                            .await
+                        {
+                            error!("Failed to publish journal heartbeat for {}: {}", unit_name, e);
+                        }
+                    }
+                    Err(e) => error!("Failed to serialize journal heartbeat for {}: {}", unit_name, e),
+                }
+            }
+        })
+    };
+
+    let mut args = vec![
+        "-o".to_string(),
+        "json".to_string(),
+        "-u".to_string(),
+        request.unit_name.clone(),
+    ];
+    if request.follow {
+        args.push("--follow".to_string());
+    }
+    if let Some(since) = &request.since {
+        args.push("--after-cursor".to_string());
+        args.push(since.clone());
+    }
+
+    let mut child = tokio::process::Command::new("journalctl")
+        .args(&args)
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("journalctl spawned without a stdout pipe"))?;
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+
+    let result: Result<()> = async {
+        loop {
+            if last_seen.lock().await.elapsed() >= UNIT_LOG_HEARTBEAT_TIMEOUT {
+                warn!(
+                    "No heartbeat for unit {} journal stream in {:?} - tearing down follow",
+                    request.unit_name, UNIT_LOG_HEARTBEAT_TIMEOUT
+                );
+                return Ok(());
+            }
+
+            // don't outrun what the subscriber has advertised it can buffer
+            if capacity.load(std::sync::atomic::Ordering::Relaxed) <= 0 {
+                tokio::time::sleep(UNIT_LOG_CAPACITY_POLL_INTERVAL).await;
+                continue;
+            }
+
+            let line = match lines.next_line().await? {
+                Some(line) => line,
+                None => return Ok(()),
+            };
+            let entry: serde_json::Value = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!(
+                        "Failed to parse journal entry for unit {}: {}",
+                        request.unit_name, e
+                    );
+                    continue;
+                }
+            };
+            let cursor = entry
+                .get("__CURSOR")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let message = entry
+                .get("MESSAGE")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let timestamp = entry
+                .get("__REALTIME_TIMESTAMP")
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<i64>().ok())
+                .and_then(|micros| Utc.timestamp_micros(micros).single())
+                .unwrap_or_else(Utc::now);
+
+            let frame = SystemdUnitLogFrame {
+                unit_name: request.unit_name.clone(),
+                cursor,
+                message,
+                timestamp,
+            };
+            match serde_json::to_vec(&frame) {
+                Ok(payload) => {
+                    nats_client
+                        .publish(log_subject.to_string(), payload.into())
+                        .await?;
+                    capacity.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                }
+                Err(e) => error!(
+                    "Failed to serialize journal frame for unit {}: {}",
+                    request.unit_name, e
+                ),
+            }
+        }
+    }
+    .await;
+
+    heartbeat_ping_task.abort();
+    capacity_task.abort();
+    heartbeat_sub_task.abort();
+    let _ = child.kill().await;
 
-        let unit_file_state = proxy.get_unit_file_state(request.unit_name.clone()).await?;
+    result
+}
 
-        let unit_file_state = match unit_file_state.as_str() {
-            "enabled" => SystemdUnitFileState::Enabled,
-            "enabled-runtime" => SystemdUnitFileState::EnabledMinusRuntime,
-            "linked" => SystemdUnitFileState::Linked,
-            "linked-runtime" => SystemdUnitFileState::LinkedMinusRuntime,
-            "masked" => SystemdUnitFileState::Masked,
-            "masked-runtime" => SystemdUnitFileState::MaskedMinusRuntime,
-            "static" => SystemdUnitFileState::Static,
-            "disabled" => SystemdUnitFileState::Disabled,
-            "invalid" => SystemdUnitFileState::Invalid,
-            _ => unimplemented!(),
-        };
+lazy_static! {
+    // the currently running filesystem watcher, if one has been started - only one
+    // watcher runs per daemon process, mirroring how SYSTEMD_MANAGER caches a single
+    // shared connection rather than letting every NATS request spin up its own
+    static ref SETTINGS_WATCHER: tokio::sync::Mutex<Option<SettingsWatcherHandle>> =
+        tokio::sync::Mutex::new(None);
+}
 
-        Ok(NatsReply::SystemdManagerGetUnitFileStateReply(
-            SystemdManagerGetUnitFileStateReply {
-                unit_file_state: Box::new(unit_file_state),
-                request: Box::new(request.clone()),
-            },
-        ))
+// handle to the background watcher task started by start_settings_watcher - dropping
+// this without calling stop() leaks the task, so callers should always route through
+// handle_settings_watcher_stop (or hold it in SETTINGS_WATCHER for daemon lifetime)
+struct SettingsWatcherHandle {
+    watching: Vec<String>,
+    debounce_ms: u64,
+    stop_tx: tokio::sync::oneshot::Sender<()>,
+    join_handle: tokio::task::JoinHandle<()>,
+}
+
+impl SettingsWatcherHandle {
+    async fn stop(self) {
+        let _ = self.stop_tx.send(());
+        if let Err(e) = self.join_handle.await {
+            warn!("Settings file watcher task panicked while stopping: {}", e);
+        }
     }
+}
 
-    // TODO
-    // Job type reload is not applicable for unit octoprint.service.
-    // async fn handle_reload_unit_request(
-    //     &self,
-    //     request: &SystemdManagerReloadUnitRequest,
-    // ) -> Result<NatsReply> {
-    //     let connection = zbus::Connection::system().await?;
-    //     let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
-    //     let job = proxy
-    //         .reload_unit(request.unit_name.clone(), "replace".into())
-    //         .await?;
-    //     let unit = self.get_systemd_unit(request.unit_name.clone()).await?;
+// resolves the configured watch_paths against settings_dir, or falls back to watching
+// every file currently managed by VersionControlledSettings if the list is empty
+fn settings_watch_paths(settings: &PrintNannySettings) -> Vec<std::path::PathBuf> {
+    if settings.watcher.watch_paths.is_empty() {
+        vec![
+            settings.get_settings_file(),
+            settings.octoprint.get_settings_file(),
+            settings.moonraker.get_settings_file(),
+            settings.klipper.get_settings_file(),
+        ]
+    } else {
+        settings
+            .watcher
+            .watch_paths
+            .iter()
+            .map(|p| settings.paths.settings_dir.join(p))
+            .collect()
+    }
+}
 
-    //     Ok(NatsReply::SystemdManagerReloadUnitReply(
-    //         SystemdManagerReloadUnitReply {
-    //             job: job.to_string(),
-    //             unit: Box::new(unit),
-    //         },
-    //     ))
-    // }
+// starts a notify recommended watcher on each managed settings file, debounces modify
+// events (same file-watch pattern as git-next), and on a debounced change auto-commits
+// the new content to the settings repo and publishes a SettingsFileChangedNotification
+async fn start_settings_watcher(settings: PrintNannySettings) -> Result<SettingsWatcherHandle> {
+    let watch_paths = settings_watch_paths(&settings);
+    let debounce_ms = settings.watcher.debounce_ms;
+    let debounce = std::time::Duration::from_millis(debounce_ms);
+
+    let (event_tx, event_rx) = std::sync::mpsc::channel();
+    let mut fs_watcher: notify::RecommendedWatcher = notify::recommended_watcher(event_tx)?;
+    for path in &watch_paths {
+        fs_watcher.watch(path, notify::RecursiveMode::NonRecursive)?;
+    }
 
-    async fn handle_restart_unit_request(
-        request: &SystemdManagerRestartUnitRequest,
-    ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
-        let job = proxy
-            .restart_unit(request.unit_name.clone(), "replace".into())
-            .await?;
-        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+    let (changed_tx, mut changed_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // blocking thread: owns the notify watcher + debounce bookkeeping and does the
+    // (synchronous, git2-backed) auto-commit, handing the result to the async
+    // publisher task below rather than publishing to NATS itself
+    let debounce_handle = tokio::task::spawn_blocking(move || {
+        // keep the watcher alive for the life of this task - dropping it tears down
+        // the underlying inotify/kqueue/ReadDirectoryChangesW handle
+        let _fs_watcher = fs_watcher;
+        let mut stop_rx = stop_rx;
+        let mut pending: std::collections::HashMap<std::path::PathBuf, std::time::Instant> =
+            std::collections::HashMap::new();
+
+        loop {
+            if stop_rx.try_recv().is_ok() {
+                return;
+            }
+            match event_rx.recv_timeout(std::time::Duration::from_millis(200)) {
+                Ok(Ok(event)) => {
+                    if event.kind.is_modify() {
+                        for path in event.paths {
+                            pending.insert(path, std::time::Instant::now());
+                        }
+                    }
+                }
+                Ok(Err(e)) => warn!("Settings file watcher error: {}", e),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            }
 
-        Ok(NatsReply::SystemdManagerRestartUnitReply(
-            SystemdManagerRestartUnitReply {
-                job: job.to_string(),
-                unit: Box::new(unit),
-            },
-        ))
-    }
+            let ready: Vec<std::path::PathBuf> = pending
+                .iter()
+                .filter(|(_, seen)| seen.elapsed() >= debounce)
+                .map(|(path, _)| path.clone())
+                .collect();
 
-    async fn handle_start_unit_request(
-        request: &SystemdManagerStartUnitRequest,
-    ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
-        let job = proxy
-            .start_unit(request.unit_name.clone(), "replace".into())
-            .await?;
-        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
-        Ok(NatsReply::SystemdManagerStartUnitReply(
-            SystemdManagerStartUnitReply {
-                job: job.to_string(),
-                unit: Box::new(unit),
-            },
-        ))
-    }
+            for path in ready {
+                pending.remove(&path);
+                let file_name = path.display().to_string();
+                let git_commit_msg = format!("Auto-committed out-of-band edit to {file_name}");
+                let result: Result<String, VersionControlledSettingsError> = (|| {
+                    settings.git_add_all()?;
+                    let oid = settings.git_commit(Some(git_commit_msg.clone()))?;
+                    Ok(oid.to_string())
+                })();
+                match result {
+                    Ok(git_head_commit) => {
+                        let _ = changed_tx.send(SettingsFileChangedNotification {
+                            file_name,
+                            git_commit_msg,
+                            git_head_commit,
+                        });
+                    }
+                    Err(e) => error!(
+                        "Failed to auto-commit out-of-band edit to {}: {}",
+                        file_name, e
+                    ),
+                }
+            }
+        }
+    });
 
-    async fn handle_stop_unit_request(
-        request: &SystemdManagerStopUnitRequest,
-    ) -> Result<NatsReply> {
-        let connection = zbus::Connection::system().await?;
-        let proxy = zbus_systemd::systemd1::ManagerProxy::new(&connection).await?;
-        let job = proxy
-            .stop_unit(request.unit_name.clone(), "replace".into())
-            .await?;
-        let unit = Self::get_systemd_unit(request.unit_name.clone()).await?;
-        Ok(NatsReply::SystemdManagerStopUnitReply(
-            SystemdManagerStopUnitReply {
-                job: job.to_string(),
-                unit: Box::new(unit),
-            },
-        ))
+    // async task: publishes each auto-committed change to NATS as it arrives
+    let publish_handle = tokio::spawn(async move {
+        let nats_client = match connect_nats_client().await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Settings watcher could not connect to NATS: {}", e);
+                return;
+            }
+        };
+        let pi_id = match printnanny_edge_db::cloud::Pi::get() {
+            Ok(pi) => pi.id.to_string(),
+            Err(e) => {
+                error!("Settings watcher could not resolve pi id: {}", e);
+                return;
+            }
+        };
+        let subject = format!("pi.{pi_id}.settings.file.changed");
+        while let Some(notification) = changed_rx.recv().await {
+            match serde_json::to_vec(&notification) {
+                Ok(payload) => {
+                    if let Err(e) = nats_client.publish(subject.clone(), payload.into()).await {
+                        error!("Failed to publish settings.file.changed notification: {}", e);
+                    }
+                }
+                Err(e) => error!("Failed to serialize settings.file.changed notification: {}", e),
+            }
+        }
+    });
+
+    let join_handle = tokio::spawn(async move {
+        let _ = debounce_handle.await;
+        publish_handle.abort();
+    });
+
+    Ok(SettingsWatcherHandle {
+        watching: watch_paths.iter().map(|p| p.display().to_string()).collect(),
+        debounce_ms,
+        stop_tx,
+        join_handle,
+    })
+}
+
+// split out of NatsRequest::deserialize_payload so the trait method can wrap it with
+// a deserialize-failure counter without duplicating the match arms
+fn deserialize_payload_inner(subject_pattern: &str, payload: &Bytes) -> Result<NatsRequest> {
+    match subject_pattern {
+        "pi.{pi_id}.command.camera.recording.start" => {
+            Ok(NatsRequest::CameraRecordingStartRequest)
+        }
+        "pi.{pi_id}.command.camera.recording.stop" => {
+            Ok(NatsRequest::CameraRecordingStopRequest)
+        }
+        "pi.{pi_id}.command.camera.recording.load" => {
+            Ok(NatsRequest::CameraRecordingLoadRequest)
+        }
+        "pi.{pi_id}.command.cloud.sync" => Ok(NatsRequest::PrintNannyCloudSyncRequest),
+        "pi.{pi_id}.command.camera.recording.sync.progress" => {
+            Ok(NatsRequest::CameraRecordingSyncProgressRequest)
+        }
+        "pi.{pi_id}.command.camera.recording.sync.enqueue" => {
+            Ok(NatsRequest::CloudSyncEnqueueRequest(
+                serde_json::from_slice::<CloudSyncEnqueueRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.command.camera.recording.sync.queue" => {
+            Ok(NatsRequest::CloudSyncQueueStateRequest)
+        }
+        "pi.{pi_id}.command.camera.recording.sync.cancel" => {
+            Ok(NatsRequest::CloudSyncCancelRequest(
+                serde_json::from_slice::<CloudSyncCancelRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.command.job.status" => Ok(NatsRequest::JobStatusRequest(
+            serde_json::from_slice::<JobStatusRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.command.job.progress" => Ok(NatsRequest::JobProgressRequest),
+        "pi.{pi_id}.crash_reports.os" => Ok(NatsRequest::CrashReportOsLogsRequest(
+            serde_json::from_slice::<CrashReportOsLogsRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.cameras.load" => Ok(NatsRequest::CameraLoadRequest),
+        "pi.{pi_id}.device_info.load" => Ok(NatsRequest::DeviceInfoLoadRequest),
+        "pi.{pi_id}.settings.printnanny.cloud.auth" => {
+            Ok(NatsRequest::PrintNannyCloudAuthRequest(
+                serde_json::from_slice::<PrintNannyCloudAuthRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.settings.file.load" => Ok(NatsRequest::SettingsFileLoadRequest),
+        "pi.{pi_id}.settings.file.apply" => Ok(NatsRequest::SettingsFileApplyRequest(
+            serde_json::from_slice::<SettingsFileApplyRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.file.apply.batch" => Ok(NatsRequest::SettingsFileApplyBatchRequest(
+            serde_json::from_slice::<SettingsFileApplyBatchRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.file.revert" => Ok(NatsRequest::SettingsFileRevertRequest(
+            serde_json::from_slice::<SettingsFileRevertRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.file.diff" => Ok(NatsRequest::SettingsFileDiffRequest(
+            serde_json::from_slice::<SettingsFileDiffRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.remote.sync" => Ok(NatsRequest::SettingsRemoteSyncRequest(
+            serde_json::from_slice::<SettingsRemoteSyncRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.remote.push" => Ok(NatsRequest::SettingsRemotePushRequest(
+            serde_json::from_slice::<SettingsRemotePushRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.remote.pull" => Ok(NatsRequest::SettingsRemotePullRequest(
+            serde_json::from_slice::<SettingsRemotePullRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.watcher.start" => Ok(NatsRequest::SettingsWatcherStartRequest(
+            serde_json::from_slice::<SettingsWatcherStartRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.watcher.stop" => Ok(NatsRequest::SettingsWatcherStopRequest(
+            serde_json::from_slice::<SettingsWatcherStopRequest>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.camera.apply" => Ok(NatsRequest::CameraSettingsFileApplyRequest(
+            serde_json::from_slice::<VideoStreamSettings>(payload.as_ref())?,
+        )),
+        "pi.{pi_id}.settings.camera.load" => Ok(NatsRequest::CameraSettingsFileLoadRequest),
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.DisableUnit" => {
+            Ok(NatsRequest::SystemdManagerDisableUnitsRequest(
+                serde_json::from_slice::<SystemdManagerUnitFilesRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.EnableUnit" => {
+            Ok(NatsRequest::SystemdManagerEnableUnitsRequest(
+                serde_json::from_slice::<SystemdManagerUnitFilesRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnit" => {
+            Ok(NatsRequest::SystemdManagerGetUnitRequest(
+                serde_json::from_slice::<SystemdManagerGetUnitRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnitFileState" => {
+            Ok(NatsRequest::SystemdManagerGetUnitFileStateRequest(
+                serde_json::from_slice::<SystemdManagerGetUnitRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.RestartUnit" => {
+            Ok(NatsRequest::SystemdManagerRestartUnitRequest(
+                serde_json::from_slice::<SystemdManagerRestartUnitRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StartUnit" => {
+            Ok(NatsRequest::SystemdManagerStartUnitRequest(
+                serde_json::from_slice::<SystemdManagerStartUnitRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit" => {
+            Ok(NatsRequest::SystemdManagerStopUnitRequest(
+                serde_json::from_slice::<SystemdManagerStopUnitRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs" => {
+            Ok(NatsRequest::SystemdManagerStreamUnitLogsRequest(
+                serde_json::from_slice::<SystemdManagerStreamUnitLogsRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits" => {
+            Ok(NatsRequest::SystemdManagerApplyUnitsRequest(
+                serde_json::from_slice::<SystemdManagerApplyUnitsRequest>(payload.as_ref())?,
+            ))
+        }
+        "pi.{pi_id}.command.recording.supervisor.start" => {
+            Ok(NatsRequest::RecordingSupervisorStartRequest)
+        }
+        "pi.{pi_id}.capabilities" => Ok(NatsRequest::CapabilitiesRequest),
+        _ => Ok(NatsRequest::UnsupportedCapabilityRequest(
+            subject_pattern.to_string(),
+        )),
     }
 }
 
@@ -873,82 +3858,173 @@ impl NatsRequestHandler for NatsRequest {
     type Reply = NatsReply;
 
     fn deserialize_payload(subject_pattern: &str, payload: &Bytes) -> Result<Self::Request> {
-        match subject_pattern {
-            "pi.{pi_id}.command.camera.recording.start" => {
-                Ok(NatsRequest::CameraRecordingStartRequest)
-            }
-            "pi.{pi_id}.command.camera.recording.stop" => {
-                Ok(NatsRequest::CameraRecordingStopRequest)
-            }
-            "pi.{pi_id}.command.camera.recording.load" => {
-                Ok(NatsRequest::CameraRecordingLoadRequest)
-            }
-            "pi.{pi_id}.command.cloud.sync" => Ok(NatsRequest::PrintNannyCloudSyncRequest),
-            "pi.{pi_id}.crash_reports.os" => Ok(NatsRequest::CrashReportOsLogsRequest(
-                serde_json::from_slice::<CrashReportOsLogsRequest>(payload.as_ref())?,
-            )),
-            "pi.{pi_id}.cameras.load" => Ok(NatsRequest::CameraLoadRequest),
-            "pi.{pi_id}.device_info.load" => Ok(NatsRequest::DeviceInfoLoadRequest),
-            "pi.{pi_id}.settings.printnanny.cloud.auth" => {
-                Ok(NatsRequest::PrintNannyCloudAuthRequest(
-                    serde_json::from_slice::<PrintNannyCloudAuthRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.settings.file.load" => Ok(NatsRequest::SettingsFileLoadRequest),
-            "pi.{pi_id}.settings.file.apply" => Ok(NatsRequest::SettingsFileApplyRequest(
-                serde_json::from_slice::<SettingsFileApplyRequest>(payload.as_ref())?,
-            )),
-            "pi.{pi_id}.settings.file.revert" => Ok(NatsRequest::SettingsFileRevertRequest(
-                serde_json::from_slice::<SettingsFileRevertRequest>(payload.as_ref())?,
-            )),
-            "pi.{pi_id}.settings.camera.apply" => Ok(NatsRequest::CameraSettingsFileApplyRequest(
-                serde_json::from_slice::<VideoStreamSettings>(payload.as_ref())?,
-            )),
-            "pi.{pi_id}.settings.camera.load" => Ok(NatsRequest::CameraSettingsFileLoadRequest),
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.DisableUnit" => {
-                Ok(NatsRequest::SystemdManagerDisableUnitsRequest(
-                    serde_json::from_slice::<SystemdManagerUnitFilesRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.EnableUnit" => {
-                Ok(NatsRequest::SystemdManagerEnableUnitsRequest(
-                    serde_json::from_slice::<SystemdManagerUnitFilesRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnit" => {
-                Ok(NatsRequest::SystemdManagerGetUnitRequest(
-                    serde_json::from_slice::<SystemdManagerGetUnitRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnitFileState" => {
-                Ok(NatsRequest::SystemdManagerGetUnitFileStateRequest(
-                    serde_json::from_slice::<SystemdManagerGetUnitRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.RestartUnit" => {
-                Ok(NatsRequest::SystemdManagerRestartUnitRequest(
-                    serde_json::from_slice::<SystemdManagerRestartUnitRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StartUnit" => {
-                Ok(NatsRequest::SystemdManagerStartUnitRequest(
-                    serde_json::from_slice::<SystemdManagerStartUnitRequest>(payload.as_ref())?,
-                ))
-            }
-            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit" => {
-                Ok(NatsRequest::SystemdManagerStopUnitRequest(
-                    serde_json::from_slice::<SystemdManagerStopUnitRequest>(payload.as_ref())?,
-                ))
-            }
-            _ => Err(anyhow!(
-                "NATS message handler not implemented for subject pattern {}",
-                subject_pattern
-            )),
+        let result = deserialize_payload_inner(subject_pattern, payload);
+        if result.is_err() {
+            counter!(
+                "nats_requests_total",
+                "subject_pattern" => subject_pattern.to_string(),
+                "outcome" => "deserialize_error"
+            )
+            .increment(1);
         }
+        result
     }
 
     // Request handlers with blocking I/O should be run with tokio::task::spawn_blocking
     async fn handle(&self) -> Result<Self::Reply> {
+        ensure_metrics_exporter_installed();
+        let subject = self.subject_pattern();
+        let start = std::time::Instant::now();
+        let result = self.dispatch().await;
+        let elapsed = start.elapsed();
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+
+        counter!("nats_requests_total", "subject_pattern" => subject, "outcome" => outcome)
+            .increment(1);
+        histogram!("nats_request_duration_ms", "subject_pattern" => subject)
+            .record(elapsed.as_secs_f64() * 1000.0);
+
+        if request_logging_enabled() {
+            info!(
+                "nats request completed subject_pattern={} outcome={} elapsed_ms={}",
+                subject,
+                outcome,
+                elapsed.as_millis()
+            );
+        }
+
+        result
+    }
+}
+
+impl NatsRequest {
+    // returns the static subject_pattern string for this request variant, the same
+    // string used as the serde tag - kept in sync with the #[serde(rename = ...)]
+    // attributes above so metrics/logs can be grouped per-subject
+    fn subject_pattern(&self) -> &'static str {
+        match self {
+            NatsRequest::CameraRecordingLoadRequest => "pi.{pi_id}.command.camera.recording.load",
+            NatsRequest::CameraRecordingStartRequest => {
+                "pi.{pi_id}.command.camera.recording.start"
+            }
+            NatsRequest::CameraRecordingStopRequest => "pi.{pi_id}.command.camera.recording.stop",
+            NatsRequest::CameraLoadRequest => "pi.{pi_id}.cameras.load",
+            NatsRequest::PrintNannyCloudSyncRequest => "pi.{pi_id}.command.cloud.sync",
+            NatsRequest::CameraRecordingSyncProgressRequest => {
+                "pi.{pi_id}.command.camera.recording.sync.progress"
+            }
+            NatsRequest::CloudSyncEnqueueRequest(_) => {
+                "pi.{pi_id}.command.camera.recording.sync.enqueue"
+            }
+            NatsRequest::CloudSyncQueueStateRequest => {
+                "pi.{pi_id}.command.camera.recording.sync.queue"
+            }
+            NatsRequest::CloudSyncCancelRequest(_) => {
+                "pi.{pi_id}.command.camera.recording.sync.cancel"
+            }
+            NatsRequest::JobStatusRequest(_) => "pi.{pi_id}.command.job.status",
+            NatsRequest::JobProgressRequest => "pi.{pi_id}.command.job.progress",
+            NatsRequest::CrashReportOsLogsRequest(_) => "pi.{pi_id}.crash_reports.os",
+            NatsRequest::DeviceInfoLoadRequest => "pi.{pi_id}.device_info.load",
+            NatsRequest::PrintNannyCloudAuthRequest(_) => {
+                "pi.{pi_id}.settings.printnanny.cloud.auth"
+            }
+            NatsRequest::SettingsFileLoadRequest => "pi.{pi_id}.settings.file.load",
+            NatsRequest::SettingsFileApplyRequest(_) => "pi.{pi_id}.settings.file.apply",
+            NatsRequest::SettingsFileApplyBatchRequest(_) => {
+                "pi.{pi_id}.settings.file.apply.batch"
+            }
+            NatsRequest::SettingsFileRevertRequest(_) => "pi.{pi_id}.settings.file.revert",
+            NatsRequest::SettingsFileDiffRequest(_) => "pi.{pi_id}.settings.file.diff",
+            NatsRequest::SettingsRemoteSyncRequest(_) => "pi.{pi_id}.settings.remote.sync",
+            NatsRequest::SettingsRemotePushRequest(_) => "pi.{pi_id}.settings.remote.push",
+            NatsRequest::SettingsRemotePullRequest(_) => "pi.{pi_id}.settings.remote.pull",
+            NatsRequest::SettingsWatcherStartRequest(_) => "pi.{pi_id}.settings.watcher.start",
+            NatsRequest::SettingsWatcherStopRequest(_) => "pi.{pi_id}.settings.watcher.stop",
+            NatsRequest::CameraSettingsFileApplyRequest(_) => "pi.{pi_id}.settings.camera.apply",
+            NatsRequest::CameraSettingsFileLoadRequest => "pi.{pi_id}.settings.camera.load",
+            NatsRequest::SystemdManagerDisableUnitsRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.DisableUnit"
+            }
+            NatsRequest::SystemdManagerEnableUnitsRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.EnableUnit"
+            }
+            NatsRequest::SystemdManagerGetUnitRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnit"
+            }
+            NatsRequest::SystemdManagerGetUnitFileStateRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnitFileState"
+            }
+            NatsRequest::SystemdManagerRestartUnitRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.RestartUnit"
+            }
+            NatsRequest::SystemdManagerStartUnitRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StartUnit"
+            }
+            NatsRequest::SystemdManagerStopUnitRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit"
+            }
+            NatsRequest::SystemdManagerStreamUnitLogsRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs"
+            }
+            NatsRequest::SystemdManagerApplyUnitsRequest(_) => {
+                "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits"
+            }
+            NatsRequest::RecordingSupervisorStartRequest => {
+                "pi.{pi_id}.command.recording.supervisor.start"
+            }
+            NatsRequest::CapabilitiesRequest => "pi.{pi_id}.capabilities",
+            NatsRequest::UnsupportedCapabilityRequest(_) => "pi.{pi_id}.unsupported_capability",
+        }
+    }
+
+    // every subject_pattern this build of the daemon implements - kept in sync with
+    // the #[serde(rename = ...)] attributes on NatsRequest by hand, same discipline as
+    // subject_pattern() above. Backs both CapabilitiesRequest and the
+    // UnsupportedCapabilityReply sent when a subject_pattern isn't recognized.
+    fn supported_capabilities() -> Vec<&'static str> {
+        vec![
+            "pi.{pi_id}.command.camera.recording.load",
+            "pi.{pi_id}.command.camera.recording.start",
+            "pi.{pi_id}.command.camera.recording.stop",
+            "pi.{pi_id}.cameras.load",
+            "pi.{pi_id}.command.cloud.sync",
+            "pi.{pi_id}.command.camera.recording.sync.progress",
+            "pi.{pi_id}.command.camera.recording.sync.enqueue",
+            "pi.{pi_id}.command.camera.recording.sync.queue",
+            "pi.{pi_id}.command.camera.recording.sync.cancel",
+            "pi.{pi_id}.command.job.status",
+            "pi.{pi_id}.command.job.progress",
+            "pi.{pi_id}.crash_reports.os",
+            "pi.{pi_id}.device_info.load",
+            "pi.{pi_id}.settings.printnanny.cloud.auth",
+            "pi.{pi_id}.settings.file.load",
+            "pi.{pi_id}.settings.file.apply",
+            "pi.{pi_id}.settings.file.apply.batch",
+            "pi.{pi_id}.settings.file.revert",
+            "pi.{pi_id}.settings.file.diff",
+            "pi.{pi_id}.settings.remote.sync",
+            "pi.{pi_id}.settings.remote.push",
+            "pi.{pi_id}.settings.remote.pull",
+            "pi.{pi_id}.settings.watcher.start",
+            "pi.{pi_id}.settings.watcher.stop",
+            "pi.{pi_id}.settings.camera.apply",
+            "pi.{pi_id}.settings.camera.load",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.DisableUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.EnableUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.GetUnitFileState",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.RestartUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StartUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StopUnit",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.StreamUnitLogs",
+            "pi.{pi_id}.dbus.org.freedesktop.systemd1.Manager.ApplyUnits",
+            "pi.{pi_id}.command.recording.supervisor.start",
+            "pi.{pi_id}.capabilities",
+        ]
+    }
+
+    async fn dispatch(&self) -> Result<NatsReply> {
         match self {
             // pi.{pi_id}.command.camera.recording.start
             NatsRequest::CameraRecordingStartRequest => Self::handle_camera_recording_start().await,
@@ -960,6 +4036,18 @@ impl NatsRequestHandler for NatsRequest {
             }
             // pi.{pi_id}.command.cloud.sync
             NatsRequest::PrintNannyCloudSyncRequest => Self::handle_cloud_sync().await,
+            NatsRequest::CameraRecordingSyncProgressRequest => {
+                Self::handle_camera_recording_sync_progress()
+            }
+            NatsRequest::CloudSyncEnqueueRequest(request) => {
+                Self::handle_cloud_sync_enqueue(request).await
+            }
+            NatsRequest::CloudSyncQueueStateRequest => Self::handle_cloud_sync_queue_state().await,
+            NatsRequest::CloudSyncCancelRequest(request) => {
+                Self::handle_cloud_sync_cancel(request).await
+            }
+            NatsRequest::JobStatusRequest(request) => Self::handle_job_status(request).await,
+            NatsRequest::JobProgressRequest => Self::handle_job_progress(),
             // pi.{pi_id}.cameras.load
             NatsRequest::CameraLoadRequest => {
                 tokio::task::spawn_blocking(Self::handle_cameras_load).await?
@@ -979,9 +4067,30 @@ impl NatsRequestHandler for NatsRequest {
             NatsRequest::SettingsFileApplyRequest(request) => {
                 Self::handle_settings_apply(request).await
             }
+            NatsRequest::SettingsFileApplyBatchRequest(request) => {
+                Self::handle_settings_apply_batch(request).await
+            }
             NatsRequest::SettingsFileRevertRequest(request) => {
                 Self::handle_settings_revert(request).await
             }
+            NatsRequest::SettingsFileDiffRequest(request) => {
+                Self::handle_settings_file_diff(request).await
+            }
+            NatsRequest::SettingsRemoteSyncRequest(request) => {
+                Self::handle_settings_remote_sync(request).await
+            }
+            NatsRequest::SettingsRemotePushRequest(request) => {
+                Self::handle_settings_remote_push(request).await
+            }
+            NatsRequest::SettingsRemotePullRequest(request) => {
+                Self::handle_settings_remote_pull(request).await
+            }
+            NatsRequest::SettingsWatcherStartRequest(request) => {
+                Self::handle_settings_watcher_start(request).await
+            }
+            NatsRequest::SettingsWatcherStopRequest(request) => {
+                Self::handle_settings_watcher_stop(request).await
+            }
 
             NatsRequest::CameraSettingsFileLoadRequest => Self::handle_camera_settings_load().await,
 
@@ -1010,6 +4119,19 @@ impl NatsRequestHandler for NatsRequest {
             NatsRequest::SystemdManagerStopUnitRequest(request) => {
                 Self::handle_stop_unit_request(request).await
             }
+            NatsRequest::SystemdManagerStreamUnitLogsRequest(request) => {
+                Self::handle_stream_unit_logs_request(request).await
+            }
+            NatsRequest::SystemdManagerApplyUnitsRequest(request) => {
+                Self::handle_apply_units_request(request).await
+            }
+            NatsRequest::RecordingSupervisorStartRequest => {
+                Self::handle_recording_supervisor_start().await
+            }
+            NatsRequest::CapabilitiesRequest => Self::handle_capabilities().await,
+            NatsRequest::UnsupportedCapabilityRequest(subject_pattern) => {
+                Self::handle_unsupported_capability(subject_pattern).await
+            }
         }
     }
 }
@@ -1125,7 +4247,7 @@ mod tests {
             let reply = Runtime::new().unwrap().block_on(request.handle()).unwrap();
 
             if let NatsReply::CameraSettingsFileApplyReply(reply) = reply {
-                assert_eq!(reply.hls.enabled, false);
+                assert_eq!(reply.video_stream.hls.enabled, false);
                 let settings = PrintNannySettings::new().unwrap();
                 assert_eq!(settings.video_stream.hls.enabled, false);
             } else {
@@ -1207,6 +4329,161 @@ mod tests {
         })
     }
 
+    #[cfg(feature = "systemd")]
+    #[test_log::test]
+    fn test_settings_apply_batch_atomic_commit() {
+        figment::Jail::expect_with(|jail| {
+            // init git repo in jail tmp dir
+            make_settings_repo(jail);
+
+            let mut settings = PrintNannySettings::new().unwrap();
+            let git_head_commit = settings.get_git_head_commit().unwrap().oid;
+
+            let mut printnanny_file = settings.to_payload(SettingsApp::Printnanny).unwrap();
+            settings.paths.log_dir = "/path/to/batch/testing".into();
+            printnanny_file.content = settings.to_toml_string().unwrap();
+
+            let octoprint_file = settings
+                .octoprint
+                .to_payload(SettingsApp::Octoprint)
+                .unwrap();
+
+            let git_commit_msg = "batch testing".to_string();
+            let request =
+                NatsRequest::SettingsFileApplyBatchRequest(SettingsFileApplyBatchRequest {
+                    files: vec![printnanny_file.clone(), octoprint_file.clone()],
+                    git_head_commit,
+                    git_commit_msg: git_commit_msg.clone(),
+                });
+            let reply = Runtime::new().unwrap().block_on(request.handle()).unwrap();
+
+            if let NatsReply::SettingsFileApplyBatchReply(reply) = reply {
+                // both files landed in the same, single commit
+                assert_eq!(reply.git_history[0].message, git_commit_msg);
+                assert_eq!(reply.files.len(), 2);
+                assert_eq!(reply.files[0].content, printnanny_file.content);
+                let settings = PrintNannySettings::new().unwrap();
+                assert_eq!(
+                    settings.get_git_head_commit().unwrap().oid,
+                    reply.git_head_commit
+                );
+            } else {
+                panic!("Expected NatsReply::SettingsFileApplyBatchReply")
+            }
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "systemd")]
+    #[test_log::test]
+    fn test_settings_apply_batch_rollback_on_invalid_head_commit() {
+        figment::Jail::expect_with(|jail| {
+            make_settings_repo(jail);
+
+            let settings = PrintNannySettings::new().unwrap();
+            let file = settings.to_payload(SettingsApp::Printnanny).unwrap();
+
+            let request =
+                NatsRequest::SettingsFileApplyBatchRequest(SettingsFileApplyBatchRequest {
+                    files: vec![file],
+                    // an oid that doesn't resolve to any commit in this repo, simulating a
+                    // stale client-supplied head - batch apply must bail out before writing
+                    // anything
+                    git_head_commit: "0000000000000000000000000000000000beef".to_string(),
+                    git_commit_msg: "should not be written".to_string(),
+                });
+            let reply = Runtime::new().unwrap().block_on(request.handle());
+            assert!(reply.is_err());
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "systemd")]
+    #[test_log::test]
+    fn test_settings_file_diff() {
+        figment::Jail::expect_with(|jail| {
+            make_settings_repo(jail);
+
+            let settings = PrintNannySettings::new().unwrap();
+            let from_commit = settings.get_git_head_commit().unwrap().oid;
+
+            let mut file = settings.to_payload(SettingsApp::Printnanny).unwrap();
+            file.content = settings.to_toml_string().unwrap() + "\n# a comment\n";
+            let request_apply = NatsRequest::SettingsFileApplyRequest(SettingsFileApplyRequest {
+                file: Box::new(file),
+                git_head_commit: from_commit.clone(),
+                git_commit_msg: "add a comment".to_string(),
+            });
+            Runtime::new()
+                .unwrap()
+                .block_on(request_apply.handle())
+                .unwrap();
+            let to_commit = settings.get_git_head_commit().unwrap().oid;
+
+            let request_diff = NatsRequest::SettingsFileDiffRequest(SettingsFileDiffRequest {
+                app: Box::new(SettingsApp::Printnanny),
+                from_commit: from_commit.clone(),
+                to_commit: to_commit.clone(),
+            });
+            let reply = Runtime::new()
+                .unwrap()
+                .block_on(request_diff.handle())
+                .unwrap();
+            if let NatsReply::SettingsFileDiffReply(reply) = reply {
+                assert_eq!(reply.from_commit, from_commit);
+                assert_eq!(reply.to_commit, to_commit);
+                assert_eq!(reply.files.len(), 1);
+                assert!(reply.files[0].lines_added > 0);
+            } else {
+                panic!("Expected NatsReply::SettingsFileDiffReply")
+            }
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "systemd")]
+    #[test_log::test]
+    fn test_settings_remote_sync() {
+        figment::Jail::expect_with(|jail| {
+            make_settings_repo(jail);
+
+            let request = NatsRequest::SettingsRemoteSyncRequest(SettingsRemoteSyncRequest {
+                remote_name: "backup".to_string(),
+                remote_url: "git@example.com:printnanny/settings-backup.git".to_string(),
+            });
+            let reply = Runtime::new().unwrap().block_on(request.handle()).unwrap();
+            if let NatsReply::SettingsRemoteSyncReply(reply) = reply {
+                assert_eq!(reply.remote_name, "backup");
+                assert_eq!(
+                    reply.remote_url,
+                    "git@example.com:printnanny/settings-backup.git"
+                );
+            } else {
+                panic!("Expected NatsReply::SettingsRemoteSyncReply")
+            }
+
+            // re-syncing the same name repoints the remote instead of erroring
+            let request = NatsRequest::SettingsRemoteSyncRequest(SettingsRemoteSyncRequest {
+                remote_name: "backup".to_string(),
+                remote_url: "git@example.com:printnanny/settings-backup-2.git".to_string(),
+            });
+            let reply = Runtime::new().unwrap().block_on(request.handle()).unwrap();
+            if let NatsReply::SettingsRemoteSyncReply(reply) = reply {
+                assert_eq!(
+                    reply.remote_url,
+                    "git@example.com:printnanny/settings-backup-2.git"
+                );
+            } else {
+                panic!("Expected NatsReply::SettingsRemoteSyncReply")
+            }
+
+            Ok(())
+        })
+    }
+
     #[cfg(feature = "systemd")]
     #[test_log::test]
     fn test_octoprint_settings_apply_load_revert() {
@@ -0,0 +1,156 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use printnanny_edge_db::video_recording::VideoRecordingPart;
+
+// matches S3_MULTIPART_PART_SIZE in message_v2.rs's whole-recording uploader
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+const MAX_CHUNK_ATTEMPTS: u32 = 5;
+
+// one PUT-per-chunk part URL handed back by PrintNanny Cloud for a given upload_id
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartUploadUrl {
+    pub chunk_number: i32,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ChunkCompletion {
+    chunk_number: i32,
+    etag: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CompleteUploadRequest {
+    upload_id: String,
+    chunks: Vec<ChunkCompletion>,
+}
+
+// base_delay * 2^attempt capped at 30s, mirroring RetryPolicy::backoff in
+// src/services/generic.rs's task executor
+fn chunk_backoff(attempt: u32) -> Duration {
+    let exp = Duration::from_millis(250).saturating_mul(1u32 << attempt.min(16));
+    exp.min(Duration::from_secs(30))
+}
+
+async fn put_chunk_with_retry(client: &reqwest::Client, url: &str, body: Vec<u8>) -> Result<String> {
+    let mut attempt = 0u32;
+    loop {
+        attempt += 1;
+        match client.put(url).body(body.clone()).send().await {
+            Ok(resp) => match resp.error_for_status() {
+                Ok(resp) => {
+                    let etag = resp
+                        .headers()
+                        .get("etag")
+                        .and_then(|v| v.to_str().ok())
+                        .ok_or_else(|| anyhow!("upload part response for {} had no ETag header", url))?
+                        .to_string();
+                    return Ok(etag);
+                }
+                Err(e) => {
+                    if attempt >= MAX_CHUNK_ATTEMPTS {
+                        return Err(e.into());
+                    }
+                }
+            },
+            Err(e) => {
+                if attempt >= MAX_CHUNK_ATTEMPTS {
+                    return Err(e.into());
+                }
+            }
+        }
+        let delay = chunk_backoff(attempt);
+        warn!(
+            "chunk PUT to {} failed, retrying attempt {}/{} in {:?}",
+            url, attempt, MAX_CHUNK_ATTEMPTS, delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Uploads `part`'s mp4 file to `chunk_urls` (one PUT per fixed-size chunk), skipping
+/// chunks already recorded in `part.completed_chunks` so a sync interrupted by a crash
+/// or network drop resumes from the first missing chunk instead of restarting. Only
+/// stamps `sync_end` once the completion call listing every `(chunk_number, etag)`
+/// pair succeeds.
+pub async fn upload_part(
+    connection_str: &str,
+    part: &VideoRecordingPart,
+    file_path: &Path,
+    upload_id: &str,
+    chunk_urls: &[PartUploadUrl],
+    complete_url: &str,
+) -> Result<()> {
+    let client = reqwest::Client::new();
+
+    if part.sync_start.is_none() {
+        VideoRecordingPart::mark_sync_started(connection_str, &part.id)?;
+    }
+
+    let already_completed: std::collections::HashMap<i32, String> = part
+        .completed_chunks_with_etags()
+        .into_iter()
+        .collect();
+    let mut file = File::open(file_path)
+        .await
+        .with_context(|| format!("failed to open {}", file_path.display()))?;
+
+    let mut completions: Vec<ChunkCompletion> = Vec::with_capacity(chunk_urls.len());
+    for upload_url in chunk_urls {
+        let offset = (upload_url.chunk_number as u64 - 1) * CHUNK_SIZE as u64;
+
+        let etag = if let Some(etag) = already_completed.get(&upload_url.chunk_number) {
+            // resuming: replay the etag we persisted when this chunk was first
+            // acked, so the completion call still lists every chunk even though we
+            // don't re-PUT it
+            info!(
+                "Part {} chunk {} already uploaded for upload_id {}, skipping",
+                part.id, upload_url.chunk_number, upload_id
+            );
+            etag.clone()
+        } else {
+            file.seek(SeekFrom::Start(offset)).await?;
+            let mut buf = vec![0u8; CHUNK_SIZE];
+            let n = file.read(&mut buf).await?;
+            buf.truncate(n);
+            let etag = put_chunk_with_retry(&client, &upload_url.url, buf).await?;
+            VideoRecordingPart::record_chunk_complete(
+                connection_str,
+                &part.id,
+                upload_id,
+                upload_url.chunk_number,
+                &etag,
+            )?;
+            etag
+        };
+        completions.push(ChunkCompletion {
+            chunk_number: upload_url.chunk_number,
+            etag,
+        });
+    }
+    completions.sort_by_key(|c| c.chunk_number);
+
+    client
+        .post(complete_url)
+        .json(&CompleteUploadRequest {
+            upload_id: upload_id.to_string(),
+            chunks: completions,
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+    VideoRecordingPart::mark_sync_complete(connection_str, &part.id)?;
+    info!(
+        "Completed multipart upload for VideoRecordingPart {} (upload_id {})",
+        part.id, upload_id
+    );
+    Ok(())
+}
@@ -9,6 +9,8 @@ pub mod octoprint;
 pub mod os_release;
 pub mod paths;
 pub mod printnanny_api;
+pub mod rtmp_ingest;
+pub mod settings;
 pub mod swupdate;
 pub mod systemd;
 
@@ -1,13 +1,59 @@
-use log::debug;
+use log::{debug, warn};
+use miette::Diagnostic;
+use std::collections::HashMap;
 use std::process::Command;
+use thiserror::Error;
 
 use printnanny_api_client::models;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
 use super::error::PrintNannyCloudConfigError;
 
 pub const OCTOPRINT_BASE_PATH: &str = "/home/octoprint/.octoprint";
 pub const PYTHON_BIN: &str = "/usr/bin/python3";
+const PYPI_JSON_API: &str = "https://pypi.org/pypi";
+
+// rendered (via miette) and attached to PrintNannyCloudConfigError's existing
+// stdout/stderr/detail string fields, rather than a new error variant, so callers
+// that already match on PrintNannyCloudConfigError don't need to change
+#[derive(Debug, Error, Diagnostic)]
+enum OctoPrintDiagnostic {
+    #[error("python interpreter not found at {python_path:?}")]
+    #[diagnostic(
+        code(printnanny::octoprint::missing_interpreter),
+        help(
+            "expected a venv python at {python_path:?}; check that OctoPrint is installed under OCTOPRINT_BASE_PATH ({OCTOPRINT_BASE_PATH})"
+        )
+    )]
+    MissingInterpreter { python_path: String },
+
+    #[error("{name:?} not found in `pip list` output")]
+    #[diagnostic(
+        code(printnanny::octoprint::package_not_found),
+        help("pip list found: {}", found.join(", "))
+    )]
+    PackageNotFound { name: String, found: Vec<String> },
+}
+
+// renders an OctoPrintDiagnostic the way a terminal would, for embedding in
+// PrintNannyCloudConfigError's plain-string fields
+fn render_diagnostic(diagnostic: OctoPrintDiagnostic) -> String {
+    format!("{:?}", miette::Report::new(diagnostic))
+}
+
+fn missing_interpreter_diagnostic(python_path: &str) -> String {
+    render_diagnostic(OctoPrintDiagnostic::MissingInterpreter {
+        python_path: python_path.to_string(),
+    })
+}
+
+fn package_not_found_diagnostic(name: &str, found: &[PipPackage]) -> String {
+    render_diagnostic(OctoPrintDiagnostic::PackageNotFound {
+        name: name.to_string(),
+        found: found.iter().map(|p| p.name.clone()).collect(),
+    })
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PipPackage {
@@ -15,6 +61,65 @@ pub struct PipPackage {
     version: String,
 }
 
+// a single pinned (or unpinned) entry from a lockfile-style desired package set, e.g.
+// what a PrintNanny OS image build declares the OctoPrint-Nanny venv should contain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipRequirement {
+    pub name: String,
+    pub version: Option<VersionReq>,
+}
+
+// the outcome of diffing a &[PipRequirement] against pip_packages() - returned up front
+// so a caller can preview the plan (dry-run) before sync_packages() actually executes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub to_install: Vec<PipRequirement>,
+    pub to_reinstall: Vec<PipRequirement>,
+    pub unchanged: Vec<PipRequirement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipSyncAction {
+    Installed,
+    Reinstalled,
+    Unchanged,
+}
+
+// per-package outcome of sync_packages() - collected rather than returned as a single
+// Result so one broken package doesn't stop the rest of the plan from being applied
+#[derive(Debug, Clone)]
+pub struct PipSyncReport {
+    pub name: String,
+    pub action: PipSyncAction,
+    pub error: Option<PrintNannyCloudConfigError>,
+}
+
+// a package installed in the venv that's behind the newest non-yanked release on PyPI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub current: Version,
+    pub latest: Version,
+    pub yanked_skipped: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyPiResponse {
+    info: PyPiInfo,
+    releases: HashMap<String, Vec<PyPiReleaseFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyPiInfo {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyPiReleaseFile {
+    #[serde(default)]
+    yanked: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OctoPrintHelper {
     pub octoprint_server: models::OctoPrintServer,
@@ -42,6 +147,35 @@ pub fn parse_pip_version(stdout: &str) -> Option<String> {
     split.map(|v| v.to_string())
 }
 
+// diffs `desired` against `installed` into missing (install), version-mismatched
+// (reinstall), and already-satisfied (unchanged) sets
+pub fn diff_packages(installed: &[PipPackage], desired: &[PipRequirement]) -> SyncPlan {
+    let mut to_install = vec![];
+    let mut to_reinstall = vec![];
+    let mut unchanged = vec![];
+
+    for req in desired {
+        match installed.iter().find(|pkg| pkg.name == req.name) {
+            None => to_install.push(req.clone()),
+            Some(pkg) => match &req.version {
+                None => unchanged.push(req.clone()),
+                Some(version_req) => match Version::parse(&pkg.version) {
+                    Ok(installed_version) if version_req.matches(&installed_version) => {
+                        unchanged.push(req.clone())
+                    }
+                    _ => to_reinstall.push(req.clone()),
+                },
+            },
+        }
+    }
+
+    SyncPlan {
+        to_install,
+        to_reinstall,
+        unchanged,
+    }
+}
+
 impl OctoPrintHelper {
     pub fn new(octoprint_server: models::OctoPrintServer) -> Self {
         return Self { octoprint_server };
@@ -63,7 +197,12 @@ impl OctoPrintHelper {
             .arg("pip")
             .arg("--version")
             .output()
-            .expect(&msg);
+            .map_err(|_| PrintNannyCloudConfigError::CommandError {
+                cmd: msg.clone(),
+                stdout: String::new(),
+                stderr: missing_interpreter_diagnostic(&self.octoprint_server.python_path),
+                code: None,
+            })?;
         let stdout = String::from_utf8_lossy(&output.stdout);
         match output.status.success() {
             true => {
@@ -97,7 +236,16 @@ impl OctoPrintHelper {
             .arg("--include-editable") // handle dev environment, where pip install -e . is used for plugin setup
             .arg("--format")
             .arg("json")
-            .output()?;
+            .output()
+            .map_err(|_| PrintNannyCloudConfigError::CommandError {
+                cmd: format!(
+                    "{:?} -m pip list --format json",
+                    &self.octoprint_server.python_path
+                ),
+                stdout: String::new(),
+                stderr: missing_interpreter_diagnostic(&self.octoprint_server.python_path),
+                code: None,
+            })?;
         let stdout = String::from_utf8_lossy(&output.stdout);
         match output.status.success() {
             true => {
@@ -132,7 +280,12 @@ impl OctoPrintHelper {
         let output = Command::new(&self.octoprint_server.python_path)
             .arg("--version")
             .output()
-            .expect(&msg);
+            .map_err(|_| PrintNannyCloudConfigError::CommandError {
+                cmd: msg.clone(),
+                stdout: String::new(),
+                stderr: missing_interpreter_diagnostic(&self.octoprint_server.python_path),
+                code: None,
+            })?;
         let stdout = String::from_utf8_lossy(&output.stdout);
         match output.status.success() {
             true => {
@@ -168,7 +321,7 @@ impl OctoPrintHelper {
             Some(p) => Ok(p.version.clone()),
             None => Err(PrintNannyCloudConfigError::OctoPrintServerConfigError {
                 field: "octoprint_version".into(),
-                detail: None,
+                detail: Some(package_not_found_diagnostic("OctoPrint", packages)),
             }),
         }?;
         debug!(
@@ -190,7 +343,7 @@ impl OctoPrintHelper {
             Some(p) => Ok(p.version.clone()),
             None => Err(PrintNannyCloudConfigError::OctoPrintServerConfigError {
                 field: "printnanny_plugin_version".into(),
-                detail: None,
+                detail: Some(package_not_found_diagnostic("OctoPrint-Nanny", packages)),
             }),
         }?;
         debug!(
@@ -199,6 +352,182 @@ impl OctoPrintHelper {
         );
         Ok(Some(result))
     }
+
+    // diffs `desired` against the installed venv without changing anything, so a caller
+    // can preview what sync_packages() would do
+    pub fn plan_sync(
+        &self,
+        desired: &[PipRequirement],
+    ) -> Result<SyncPlan, PrintNannyCloudConfigError> {
+        let installed = self.pip_packages()?;
+        let plan = diff_packages(&installed, desired);
+
+        debug!(
+            "plan_sync for venv {:?}: {} to install, {} to reinstall, {} unchanged",
+            &self.octoprint_server.python_path,
+            plan.to_install.len(),
+            plan.to_reinstall.len(),
+            plan.unchanged.len()
+        );
+
+        Ok(plan)
+    }
+
+    // reconciles the venv against `desired` the way a lockfile-driven installer does:
+    // installs missing packages, force-reinstalls version-mismatched ones, and leaves
+    // already-satisfied packages untouched. Collects a per-package report rather than
+    // bailing on the first failure, so one broken package doesn't block the rest.
+    pub fn sync_packages(
+        &self,
+        desired: &[PipRequirement],
+    ) -> Result<Vec<PipSyncReport>, PrintNannyCloudConfigError> {
+        let plan = self.plan_sync(desired)?;
+        let mut reports = vec![];
+
+        for req in &plan.to_install {
+            reports.push(self.pip_install(req, false));
+        }
+        for req in &plan.to_reinstall {
+            reports.push(self.pip_install(req, true));
+        }
+        for req in &plan.unchanged {
+            reports.push(PipSyncReport {
+                name: req.name.clone(),
+                action: PipSyncAction::Unchanged,
+                error: None,
+            });
+        }
+
+        Ok(reports)
+    }
+
+    fn pip_install(&self, req: &PipRequirement, force_reinstall: bool) -> PipSyncReport {
+        let spec = match &req.version {
+            // VersionReq's Display renders e.g. "=1.2.3" for an exact pin - strip the
+            // leading "=" so the spec reads as the `name==version` pip expects
+            Some(version_req) => format!(
+                "{}=={}",
+                req.name,
+                version_req.to_string().trim_start_matches('=')
+            ),
+            None => req.name.clone(),
+        };
+        let action = if force_reinstall {
+            PipSyncAction::Reinstalled
+        } else {
+            PipSyncAction::Installed
+        };
+
+        let mut cmd = Command::new(&self.octoprint_server.python_path);
+        cmd.arg("-m").arg("pip").arg("install");
+        if force_reinstall {
+            cmd.arg("--force-reinstall");
+        }
+        cmd.arg(&spec);
+
+        let error = match cmd.output() {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(PrintNannyCloudConfigError::CommandError {
+                cmd: format!(
+                    "{:?} -m pip install {}",
+                    &self.octoprint_server.python_path, &spec
+                ),
+                stdout: String::from_utf8_lossy(&output.stdout).into(),
+                stderr: String::from_utf8_lossy(&output.stderr).into(),
+                code: output.status.code(),
+            }),
+            Err(e) => Some(PrintNannyCloudConfigError::from(e)),
+        };
+
+        PipSyncReport {
+            name: req.name.clone(),
+            action,
+            error,
+        }
+    }
+
+    // compares every installed package (especially OctoPrint and OctoPrint-Nanny)
+    // against the newest release on PyPI. A package whose version can't be parsed as
+    // semver (PEP 440 epochs/local segments) or whose PyPI lookup fails is skipped
+    // rather than failing the whole scan.
+    pub async fn outdated(
+        &self,
+        include_prerelease: bool,
+    ) -> Result<Vec<OutdatedPackage>, PrintNannyCloudConfigError> {
+        let installed = self.pip_packages()?;
+        let mut result = vec![];
+        for pkg in &installed {
+            if let Some(outdated) = Self::check_outdated(pkg, include_prerelease).await {
+                result.push(outdated);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn check_outdated(pkg: &PipPackage, include_prerelease: bool) -> Option<OutdatedPackage> {
+        let current = Version::parse(&pkg.version).ok()?;
+
+        let url = format!("{}/{}/json", PYPI_JSON_API, pkg.name);
+        let response = match reqwest::Client::new().get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to check PyPI for outdated version of {} - {}", pkg.name, e);
+                return None;
+            }
+        };
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            // unknown to PyPI - treat as not outdated rather than erroring the scan
+            return None;
+        }
+        let body: PyPiResponse = match response.error_for_status() {
+            Ok(r) => match r.json().await {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("Failed to parse PyPI response for {} - {}", pkg.name, e);
+                    return None;
+                }
+            },
+            Err(e) => {
+                warn!("Failed to parse PyPI response for {} - {}", pkg.name, e);
+                return None;
+            }
+        };
+
+        // a prerelease candidate is only eligible when the caller opted in AND the
+        // installed version is itself a prerelease
+        let allow_prerelease = include_prerelease && !current.pre.is_empty();
+
+        let mut yanked_skipped = 0;
+        let mut latest: Option<Version> = None;
+        let mut consider = |version_str: &str| {
+            if let Ok(version) = Version::parse(version_str) {
+                if version.pre.is_empty() || allow_prerelease {
+                    if latest.as_ref().map_or(true, |l| version > *l) {
+                        latest = Some(version);
+                    }
+                }
+            }
+        };
+
+        for (version_str, files) in &body.releases {
+            if !files.is_empty() && files.iter().all(|f| f.yanked) {
+                yanked_skipped += 1;
+                continue;
+            }
+            consider(version_str);
+        }
+        consider(&body.info.version);
+
+        match latest {
+            Some(latest) if latest > current => Some(OutdatedPackage {
+                name: pkg.name.clone(),
+                current,
+                latest,
+                yanked_skipped,
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -224,4 +553,37 @@ mod tests {
 
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn test_diff_packages_computes_install_reinstall_unchanged() {
+        let installed = vec![
+            PipPackage {
+                name: "OctoPrint".into(),
+                version: "1.8.0".into(),
+            },
+            PipPackage {
+                name: "OctoPrint-Nanny".into(),
+                version: "0.1.0".into(),
+            },
+        ];
+        let desired = vec![
+            PipRequirement {
+                name: "OctoPrint".into(),
+                version: Some(VersionReq::parse("=1.8.0").unwrap()),
+            },
+            PipRequirement {
+                name: "OctoPrint-Nanny".into(),
+                version: Some(VersionReq::parse("=0.2.0").unwrap()),
+            },
+            PipRequirement {
+                name: "new-plugin".into(),
+                version: None,
+            },
+        ];
+
+        let plan = diff_packages(&installed, &desired);
+        assert_eq!(plan.unchanged, vec![desired[0].clone()]);
+        assert_eq!(plan.to_reinstall, vec![desired[1].clone()]);
+        assert_eq!(plan.to_install, vec![desired[2].clone()]);
+    }
 }
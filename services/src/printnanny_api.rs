@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::future::Future;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
@@ -41,6 +42,61 @@ pub fn save_model_json<T: serde::Serialize>(model: &T, path: &Path) -> Result<()
     Ok(())
 }
 
+// sidecar recording when a cached model at `path` was fetched and how long it's valid
+// for - kept alongside the model's own JSON rather than embedded in it, so the cached
+// value's shape stays exactly `T` with no envelope for callers that read it directly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ModelCacheMeta {
+    fetched_at_unix_secs: u64,
+    ttl_secs: u64,
+}
+
+fn model_cache_meta_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".meta");
+    path.with_file_name(file_name)
+}
+
+fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// a missing/corrupt/unreadable .meta sidecar counts as expired rather than an error,
+// so a cache file written before this TTL layer existed (or with a meta file that
+// failed to parse) is treated as a miss instead of served forever
+fn model_cache_expired(path: &Path) -> bool {
+    let meta_path = model_cache_meta_path(path);
+    let meta = std::fs::read_to_string(meta_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ModelCacheMeta>(&content).ok());
+    match meta {
+        Some(meta) => now_unix_secs().saturating_sub(meta.fetched_at_unix_secs) >= meta.ttl_secs,
+        None => true,
+    }
+}
+
+fn write_model_cache_meta(path: &Path, ttl: Duration) {
+    let meta_path = model_cache_meta_path(path);
+    let meta = ModelCacheMeta {
+        fetched_at_unix_secs: now_unix_secs(),
+        ttl_secs: ttl.as_secs(),
+    };
+    match serde_json::to_string(&meta) {
+        Ok(content) => {
+            if let Err(error) = std::fs::write(&meta_path, content) {
+                warn!(
+                    "Failed to write model cache metadata {:?} - {}",
+                    meta_path, error
+                );
+            }
+        }
+        Err(error) => warn!("Failed to serialize model cache metadata - {}", error),
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PrintNannyApiConfig {
     pub base_path: String,
@@ -241,36 +297,52 @@ impl ApiService {
     }
 
     // read <models::<T>>.json from disk cache @ /var/run/printnanny
-    // hydrate cache if not found using fallback fn f (must return a Future)
+    // hydrate cache if not found, unreadable, or older than `ttl` using fallback fn f
+    // (must return a Future)
     pub async fn load_model<T: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug>(
         &self,
         path: &Path,
+        ttl: Duration,
         f: impl Future<Output = Result<T, PrintNannySettingsError>>,
     ) -> Result<T, PrintNannySettingsError> {
-        let m = read_model_json::<T>(path);
-        match m {
-            Ok(v) => Ok(v),
-            Err(_e) => {
-                warn!(
-                    "Failed to read {:?} - falling back to load remote model",
-                    path
-                );
-                let res = f.await;
-                match res {
-                    Ok(v) => {
-                        match save_model_json::<T>(&v, path) {
-                            Ok(()) => Ok(()),
-                            Err(error) => Err(PrintNannySettingsError::WriteIOError {
-                                path: path.to_path_buf(),
-                                error,
-                            }),
-                        }?;
-                        info!("Saved model {:?} to {:?}", &v, path);
-                        Ok(v)
-                    }
-                    Err(e) => Err(e),
-                }
+        if !model_cache_expired(path) {
+            if let Ok(v) = read_model_json::<T>(path) {
+                return Ok(v);
             }
         }
+        warn!(
+            "Cache for {:?} is missing or older than ttl {:?} - falling back to load remote model",
+            path, ttl
+        );
+        self.refresh_model(path, ttl, f).await
+    }
+
+    // re-hydrates `path` from `f` and rewrites the cache unconditionally, bypassing
+    // any TTL check - used by `load_model` on a miss, and public for a caller that
+    // needs the freshly fetched value rather than whatever's still sitting in the
+    // on-disk cache
+    pub async fn refresh_model<T: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug>(
+        &self,
+        path: &Path,
+        ttl: Duration,
+        f: impl Future<Output = Result<T, PrintNannySettingsError>>,
+    ) -> Result<T, PrintNannySettingsError> {
+        let v = f.await?;
+        save_model_json::<T>(&v, path).map_err(|error| PrintNannySettingsError::WriteIOError {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        write_model_cache_meta(path, ttl);
+        info!("Saved model {:?} to {:?}", &v, path);
+        Ok(v)
+    }
+
+    // deletes both the cached model and its TTL sidecar, so the next `load_model` call
+    // is guaranteed to miss and re-hydrate from its fallback regardless of `ttl`. `T`
+    // isn't read here - it's kept so a call site reads the same as `load_model::<T>`
+    // and `refresh_model::<T>` rather than silently untyped.
+    pub fn invalidate_model<T>(&self, path: &Path) {
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(model_cache_meta_path(path));
     }
 }
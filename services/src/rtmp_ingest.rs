@@ -0,0 +1,274 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use rml_rtmp::sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use printnanny_edge_db::video_recording::{UpdateVideoRecording, VideoRecording, VideoRecordingPart};
+use printnanny_edge_db::video_recording_part_writer::VideoRecordingPartWriter;
+
+// rotation interval passed to VideoRecordingPart::rotate_current each time a segment opens
+const ROTATION_INTERVAL: Duration = Duration::from_secs(60);
+
+// per-segment part updates (one per ROTATION_INTERVAL per publisher) are buffered
+// through a shared VideoRecordingPartWriter rather than issued as one-off
+// establish_sqlite_connection + UPDATE calls
+const PART_WRITER_MAX_BATCH: usize = 16;
+const PART_WRITER_MAX_INTERVAL: Duration = Duration::from_secs(5);
+
+const FLV_TAG_TYPE_AUDIO: u8 = 8;
+const FLV_TAG_TYPE_VIDEO: u8 = 9;
+
+/// Listens on `addr` for RTMP publishers and records each published stream into the
+/// same VideoRecording/VideoRecordingPart pipeline the local camera capture uses, so
+/// a remote/networked camera feeds the same sync pipeline as a directly attached one.
+/// Only one publisher is served at a time, mirroring the local path's single
+/// `VideoRecording::get_current` invariant - a second connection waits for the first
+/// to disconnect.
+pub async fn serve(connection_str: String, addr: SocketAddr, reserve_bytes: i64) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("RTMP ingest listening on {}", addr);
+
+    // one writer for the lifetime of the listener - only one publisher is served at a
+    // time, so a single buffered writer is never contended across publishers
+    let writer = VideoRecordingPartWriter::new(
+        &connection_str,
+        PART_WRITER_MAX_BATCH,
+        PART_WRITER_MAX_INTERVAL,
+    );
+
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        info!("RTMP publisher connected from {}", peer);
+        match handle_publisher(&connection_str, &writer, socket, reserve_bytes).await {
+            Ok(recording_id) => info!("RTMP publisher {} finished recording {}", peer, recording_id),
+            Err(e) => error!("RTMP session from {} ended with error: {}", peer, e),
+        }
+    }
+}
+
+// one open segment's FLV staging file plus the VideoRecordingPart row tracking it.
+// RTMP audio/video message payloads are exactly FLV tag bodies, so they're framed
+// into `flv_path` as a proper FLV stream as they arrive; `flv_path` is remuxed into
+// `part.file_name` (a real, ffprobe-readable MP4) once the segment closes.
+struct OpenSegment {
+    part: VideoRecordingPart,
+    file: tokio::fs::File,
+    flv_path: PathBuf,
+    mp4_path: PathBuf,
+    opened_at: Instant,
+}
+
+fn flv_header() -> [u8; 13] {
+    [
+        0x46, 0x4C, 0x56, // "FLV"
+        0x01, // version 1
+        0x05, // audio + video tags present
+        0x00, 0x00, 0x00, 0x09, // DataOffset: size of this header
+        0x00, 0x00, 0x00, 0x00, // PreviousTagSize0
+    ]
+}
+
+// frames `data` (an FLV tag body, per RTMP's AudioDataReceived/VideoDataReceived
+// payload) as a complete FLV tag, including the trailing PreviousTagSize ffmpeg's
+// FLV demuxer uses to walk the tag list
+fn flv_tag(tag_type: u8, timestamp_ms: u32, data: &[u8]) -> Vec<u8> {
+    let mut tag = Vec::with_capacity(11 + data.len() + 4);
+    tag.push(tag_type);
+    let data_size = data.len() as u32;
+    tag.extend_from_slice(&data_size.to_be_bytes()[1..]); // 3-byte DataSize
+    tag.extend_from_slice(&timestamp_ms.to_be_bytes()[1..]); // 3-byte Timestamp
+    tag.push((timestamp_ms >> 24) as u8); // TimestampExtended
+    tag.extend_from_slice(&[0, 0, 0]); // StreamID, always 0
+    tag.extend_from_slice(data);
+    let tag_size = 11 + data_size;
+    tag.extend_from_slice(&tag_size.to_be_bytes());
+    tag
+}
+
+async fn open_segment(connection_str: &str, recording: &VideoRecording) -> Result<OpenSegment> {
+    let part = VideoRecordingPart::rotate_current(connection_str, ROTATION_INTERVAL)
+        .map_err(|e| anyhow!("failed to open segment for recording {}: {}", recording.id, e))?;
+    let mp4_path = PathBuf::from(&recording.dir).join(&part.file_name);
+    let flv_path = mp4_path.with_extension("flv");
+    let mut file = tokio::fs::File::create(&flv_path).await?;
+    file.write_all(&flv_header()).await?;
+    Ok(OpenSegment {
+        part,
+        file,
+        flv_path,
+        mp4_path,
+        opened_at: Instant::now(),
+    })
+}
+
+async fn handle_publisher(
+    connection_str: &str,
+    writer: &VideoRecordingPartWriter,
+    mut socket: TcpStream,
+    reserve_bytes: i64,
+) -> Result<String> {
+    rml_rtmp::handshake::perform_handshake(&mut socket).await?;
+
+    let config = ServerSessionConfig::new();
+    let (mut session, _initial_results) =
+        ServerSession::new(config).map_err(|e| anyhow!("failed to start RTMP session: {:?}", e))?;
+
+    // each publisher connection gets a fresh VideoRecording via start_new, the same way a
+    // local capture session does; it's created before the publish request arrives, and
+    // the publish's stream key is accepted without being checked or recorded
+    let recording = VideoRecording::start_new(connection_str, reserve_bytes)
+        .map_err(|e| anyhow!("failed to start VideoRecording for RTMP publish: {}", e))?;
+    let mut segment = open_segment(connection_str, &recording).await?;
+
+    let mut read_buf = vec![0u8; 8192];
+    loop {
+        let n = socket.read(&mut read_buf).await?;
+        if n == 0 {
+            info!("RTMP publisher for recording {} disconnected", recording.id);
+            break;
+        }
+
+        let results = session
+            .handle_input(&read_buf[..n])
+            .map_err(|e| anyhow!("RTMP protocol error: {:?}", e))?;
+
+        for result in results {
+            match result {
+                ServerSessionResult::OutboundResponse(packet) => {
+                    socket.write_all(&packet.bytes).await?;
+                }
+                ServerSessionResult::RaisedEvent(ServerSessionEvent::PublishStreamRequested {
+                    request_id,
+                    ..
+                }) => {
+                    for accepted in session
+                        .accept_request(request_id)
+                        .map_err(|e| anyhow!("failed to accept RTMP publish: {:?}", e))?
+                    {
+                        if let ServerSessionResult::OutboundResponse(packet) = accepted {
+                            socket.write_all(&packet.bytes).await?;
+                        }
+                    }
+                }
+                ServerSessionResult::RaisedEvent(ServerSessionEvent::AudioDataReceived {
+                    data,
+                    timestamp,
+                    ..
+                }) => {
+                    let tag = flv_tag(FLV_TAG_TYPE_AUDIO, timestamp.value, &data);
+                    segment.file.write_all(&tag).await?;
+
+                    if segment.opened_at.elapsed() >= ROTATION_INTERVAL {
+                        segment = rotate_segment(connection_str, writer, &recording, segment).await?;
+                    }
+                }
+                ServerSessionResult::RaisedEvent(ServerSessionEvent::VideoDataReceived {
+                    data,
+                    timestamp,
+                    ..
+                }) => {
+                    let tag = flv_tag(FLV_TAG_TYPE_VIDEO, timestamp.value, &data);
+                    segment.file.write_all(&tag).await?;
+
+                    if segment.opened_at.elapsed() >= ROTATION_INTERVAL {
+                        segment = rotate_segment(connection_str, writer, &recording, segment).await?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    close_segment(writer, segment).await?;
+    // flush this publisher's final part update before returning, since the shared
+    // writer otherwise only flushes on its own batch/interval threshold or on Drop
+    writer.flush()?;
+
+    let now = chrono::Utc::now();
+    VideoRecording::update(
+        connection_str,
+        &recording.id,
+        UpdateVideoRecording {
+            capture_done: Some(&true),
+            cloud_sync_done: None,
+            dir: None,
+            recording_start: None,
+            recording_end: Some(&now),
+            gcode_file_name: None,
+            storage_dir_id: None,
+        },
+    )?;
+
+    Ok(recording.id)
+}
+
+async fn rotate_segment(
+    connection_str: &str,
+    writer: &VideoRecordingPartWriter,
+    recording: &VideoRecording,
+    segment: OpenSegment,
+) -> Result<OpenSegment> {
+    close_segment(writer, segment).await?;
+    open_segment(connection_str, recording).await
+}
+
+// remuxes the segment's FLV staging file into its real MP4 part file via ffmpeg,
+// the same shelling-out convention mux_recording_segments uses for the local-capture
+// pipeline, then drops the staging file and queues the resulting MP4's byte size
+// through the shared VideoRecordingPartWriter instead of a one-off connection+UPDATE
+async fn close_segment(writer: &VideoRecordingPartWriter, mut segment: OpenSegment) -> Result<()> {
+    segment.file.flush().await?;
+    drop(segment.file);
+
+    let output = tokio::process::Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-i",
+            segment.flv_path.to_string_lossy().as_ref(),
+            "-c",
+            "copy",
+            segment.mp4_path.to_string_lossy().as_ref(),
+        ])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg exited with non-zero status remuxing RTMP segment {}: {}",
+            segment.flv_path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    if let Err(e) = tokio::fs::remove_file(&segment.flv_path).await {
+        warn!(
+            "failed to remove FLV staging file {}: {}",
+            segment.flv_path.display(),
+            e
+        );
+    }
+
+    let size = tokio::fs::metadata(&segment.mp4_path).await?.len() as i64;
+
+    writer.queue_update(
+        &segment.part.id,
+        &printnanny_edge_db::video_recording::UpdateVideoRecordingPart {
+            part: None,
+            size: Some(&size),
+            deleted: None,
+            sync_start: None,
+            sync_end: None,
+            file_name: None,
+            video_recording_id: None,
+            upload_id: None,
+            completed_chunks: None,
+            checksum: None,
+        },
+    )?;
+    Ok(())
+}
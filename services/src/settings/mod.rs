@@ -0,0 +1,37 @@
+pub mod printnanny;
+pub mod vcs;
+
+use std::fmt;
+
+// Settings fragments (PrintNannySettings itself, and conf.d overrides) can be read
+// and written in any of these formats. Each variant pulls in its own serializer dep
+// (serde_json / toml / serde_yaml), so it's gated behind a same-named cargo feature
+// - mirroring how rotz keeps format support opt-in - instead of every build paying
+// for formats it never reads or writes.
+#[cfg(not(any(feature = "json", feature = "toml", feature = "yaml")))]
+compile_error!(
+    "printnanny_settings requires at least one of the `json`, `toml`, or `yaml` features to be enabled"
+);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsFormat {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl fmt::Display for SettingsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => write!(f, "json"),
+            #[cfg(feature = "toml")]
+            Self::Toml => write!(f, "toml"),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => write!(f, "yaml"),
+        }
+    }
+}
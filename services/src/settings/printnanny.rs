@@ -1,16 +1,27 @@
 use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::fs::File;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use async_trait::async_trait;
-use figment::providers::{Env, Format, Json, Serialized, Toml};
+#[cfg(feature = "json")]
+use figment::providers::Json;
+#[cfg(feature = "toml")]
+use figment::providers::Toml;
+#[cfg(feature = "yaml")]
+use figment::providers::Yaml;
+use figment::providers::{Env, Format, Serialized};
 use figment::value::{Dict, Map};
 use figment::{Figment, Metadata, Profile, Provider};
 use glob::glob;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use crate::printnanny_api::ApiService;
 use printnanny_api_client::models;
@@ -36,6 +47,32 @@ const DEFAULT_PRINTNANNY_SETTINGS_GIT_REMOTE: &str =
 const DEFAULT_PRINTNANNY_SETTINGS_GIT_EMAIL: &str = "robots@printnanny.ai";
 const DEFAULT_PRINTNANNY_SETTINGS_GIT_NAME: &str = "PrintNanny";
 
+// selects the figment::Profile PrintNannySettings::figment() extracts with - "dev" unless
+// overridden, so a developer's local checkout never has to opt out of the production
+// readiness audit below
+const PROFILE_ENV_VAR: &str = "PRINTNANNY_PROFILE";
+pub const DEV_PROFILE: &str = "dev";
+pub const PROD_PROFILE: &str = "prod";
+
+// set to "1"/"true" to start in prod profile despite failed production_readiness_audit()
+// findings - the findings are still logged at error level either way
+const PRODUCTION_AUDIT_OVERRIDE_ENV_VAR: &str = "PRINTNANNY_PROFILE_AUDIT_OVERRIDE";
+
+// one "you forgot to harden this" recommendation surfaced by production_readiness_audit(),
+// keyed by the dotted settings path it applies to so a CLI can print it next to the
+// offending key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductionReadinessFinding {
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ProductionReadinessFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
 lazy_static! {
     static ref DEFAULT_SYSTEMD_UNITS: HashMap<String, SystemdUnit> = {
         let mut m = HashMap::new();
@@ -70,10 +107,16 @@ lazy_static! {
     };
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 pub struct NatsConfig {
     pub uri: String,
     pub require_tls: bool,
+    // port the Prometheus exporter installed by nats/src/message_v2.rs listens on
+    pub metrics_port: u16,
+    // gates the per-request completion log line emitted after each NATS dispatch
+    pub request_logging: bool,
+    // how long a SystemdManager*UnitRequest waits for its JobRemoved signal before giving up
+    pub systemd_job_timeout_secs: u64,
 }
 
 impl Default for NatsConfig {
@@ -81,11 +124,44 @@ impl Default for NatsConfig {
         Self {
             uri: "nats://localhost:4222".to_string(),
             require_tls: false,
+            metrics_port: 9927,
+            request_logging: false,
+            systemd_job_timeout_secs: 30,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+impl NatsConfig {
+    // returns one error per violation instead of failing on the first, so
+    // VersionControlledSettings::validate() can report every problem at once
+    fn validate(&self) -> Vec<VersionControlledSettingsError> {
+        let mut errors = vec![];
+        match Url::parse(&self.uri) {
+            Ok(url) if url.scheme() == "nats" || url.scheme() == "tls" => {
+                if self.require_tls && url.scheme() != "tls" {
+                    errors.push(VersionControlledSettingsError::InvalidNatsUri {
+                        uri: self.uri.clone(),
+                        reason: "require_tls is set but uri scheme is not tls://".into(),
+                    });
+                }
+            }
+            Ok(url) => errors.push(VersionControlledSettingsError::InvalidNatsUri {
+                uri: self.uri.clone(),
+                reason: format!(
+                    "unsupported scheme {:?}, expected nats:// or tls://",
+                    url.scheme()
+                ),
+            }),
+            Err(e) => errors.push(VersionControlledSettingsError::InvalidNatsUri {
+                uri: self.uri.clone(),
+                reason: e.to_string(),
+            }),
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct PrintNannyCloudProxy {
     pub hostname: String,
     pub base_path: String,
@@ -105,25 +181,45 @@ impl Default for PrintNannyCloudProxy {
     }
 }
 
-#[derive(Debug, Clone, clap::ValueEnum, Eq, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, clap::ValueEnum, Eq, Deserialize, Serialize, PartialEq, JsonSchema)]
 pub enum VideoSrcType {
     File,
     Device,
     Uri,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct SystemdUnit {
     unit: String,
     enabled: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct GitSettings {
     pub remote: String,
     pub email: String,
     pub name: String,
     pub default_branch: String,
+    // private key used to authenticate `remote` when it's an ssh:// / scp-like remote;
+    // ignored for https:// remotes, which authenticate with a bearer token via
+    // GIT_ASKPASS instead (see vcs.rs's git_spawn)
+    pub ssh_key: Option<PathBuf>,
+    // typed hosting-provider backend (GitHub/GitLab/Gitea/Forgejo); overrides `remote`
+    // when set, so a self-hosted Gitea/Forgejo instance is first-class rather than
+    // only reachable by hand-assembling its clone URL into `remote`
+    pub provider: Option<GitProviderConfig>,
+    // verify the remote's host key against the system's known_hosts before a git2-driven
+    // clone/fetch/push over ssh:// - disabled only for dev boxes bootstrapping against a
+    // host they haven't connected to yet; production devices should leave this on
+    pub known_hosts_verify: bool,
+    // sign settings commits so a revision can be proven to originate from an
+    // authorized device rather than trusting the committer name/email alone; unsigned
+    // (the default) until a device is enrolled with a signing key
+    pub signing: Option<GitSigningConfig>,
+    // opt-in push-to-email notifier - emails a format-patch style summary of each
+    // settings commit, giving an operator an audit trail without polling the cloud;
+    // disabled by leaving `recipients` empty (the default)
+    pub notify_email: GitEmailNotifyConfig,
 }
 
 impl Default for GitSettings {
@@ -133,19 +229,261 @@ impl Default for GitSettings {
             email: DEFAULT_PRINTNANNY_SETTINGS_GIT_EMAIL.into(),
             name: DEFAULT_PRINTNANNY_SETTINGS_GIT_NAME.into(),
             default_branch: "main".into(),
+            ssh_key: None,
+            provider: None,
+            known_hosts_verify: true,
+            signing: None,
+            notify_email: GitEmailNotifyConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct GitEmailNotifyConfig {
+    pub recipients: Vec<String>,
+    // path to the sendmail-compatible binary that actually delivers the message;
+    // overridable for boxes where it's not installed at the conventional path
+    pub sendmail_bin: String,
+}
+
+impl Default for GitEmailNotifyConfig {
+    fn default() -> Self {
+        Self {
+            recipients: vec![],
+            sendmail_bin: "/usr/sbin/sendmail".into(),
+        }
+    }
+}
+
+// which keypair format signs settings commits - Ssh reuses GitSettings.ssh_key (the
+// same key that already authenticates the remote); Gpg signs with a separately
+// managed key identified by GitSigningConfig.gpg_key_id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GitSigningFormat {
+    Ssh,
+    Gpg,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct GitSigningConfig {
+    pub format: GitSigningFormat,
+    // required when format is Gpg, ignored when format is Ssh
+    pub gpg_key_id: Option<String>,
+}
+
+// GitSettings.remote parsed into its authentication shape - selects the credential
+// strategy used when cloning/pushing/fetching (see vcs.rs's git_clone and git_spawn)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitRemote {
+    Https(Url),
+    // scp-like form, e.g. git@github.com:owner/repo.git - not a valid URL (no scheme),
+    // so it's parsed by hand rather than via Url::parse
+    Ssh {
+        user: String,
+        host: String,
+        path: String,
+    },
+}
+
+// parses `remote` as either an https:// URL or an scp-like ssh remote
+// (git@host:owner/repo.git); returns the unparseable reason as Err otherwise
+fn parse_git_remote(remote: &str) -> Result<GitRemote, String> {
+    match Url::parse(remote) {
+        Ok(url) if url.scheme() == "https" => Ok(GitRemote::Https(url)),
+        Ok(url) => Err(format!(
+            "unsupported scheme {:?}, expected https:// or git@host:path",
+            url.scheme()
+        )),
+        Err(url_err) => match remote.split_once('@') {
+            Some((user, host_and_path)) => match host_and_path.split_once(':') {
+                Some((host, path)) if !user.is_empty() && !host.is_empty() && !path.is_empty() => {
+                    Ok(GitRemote::Ssh {
+                        user: user.to_string(),
+                        host: host.to_string(),
+                        path: path.to_string(),
+                    })
+                }
+                _ => Err(url_err.to_string()),
+            },
+            None => Err(url_err.to_string()),
+        },
+    }
+}
+
+// hosting backend a GitSettings.provider config targets - lets a user keep settings
+// history on their own Gitea/Forgejo instance instead of only the default cloud
+// remote, analogous to how multi-host release tools model a typed API per provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GitProviderKind {
+    GitHub,
+    GitLab,
+    Gitea,
+    Forgejo,
+}
+
+impl GitProviderKind {
+    // GitHub/GitLab have one well-known SaaS endpoint; Gitea/Forgejo are almost always
+    // self-hosted, so they have no sensible default and require GitProviderConfig.endpoint
+    fn default_endpoint(&self) -> Option<&'static str> {
+        match self {
+            GitProviderKind::GitHub => Some("github.com"),
+            GitProviderKind::GitLab => Some("gitlab.com"),
+            GitProviderKind::Gitea | GitProviderKind::Forgejo => None,
+        }
+    }
+
+    // env var consulted for this provider's access token, read at remote_url()/clone
+    // time rather than stored in settings so the token never round-trips through
+    // `printnanny config show`
+    fn token_env_var(&self) -> &'static str {
+        match self {
+            GitProviderKind::GitHub => "PRINTNANNY_GITHUB_TOKEN",
+            GitProviderKind::GitLab => "PRINTNANNY_GITLAB_TOKEN",
+            GitProviderKind::Gitea => "PRINTNANNY_GITEA_TOKEN",
+            GitProviderKind::Forgejo => "PRINTNANNY_FORGEJO_TOKEN",
+        }
+    }
+}
+
+// a settings-history remote hosted by a typed provider rather than an opaque URL -
+// `endpoint` overrides GitProviderKind::default_endpoint(), and is required for
+// self-hosted Gitea/Forgejo instances
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct GitProviderConfig {
+    pub provider: GitProviderKind,
+    pub endpoint: Option<String>,
+    pub owner: String,
+    pub repository: String,
+}
+
+impl GitProviderConfig {
+    // the access token configured for this provider via its token_env_var, if any -
+    // absent for anonymous/public-read remotes
+    pub fn token(&self) -> Option<String> {
+        env::var(self.provider.token_env_var()).ok()
+    }
+
+    // builds the https:// clone/push URL implied by this provider config
+    pub fn remote_url(&self) -> Result<String, VersionControlledSettingsError> {
+        let endpoint = self
+            .endpoint
+            .as_deref()
+            .or_else(|| self.provider.default_endpoint())
+            .ok_or_else(|| VersionControlledSettingsError::InvalidGitRemote {
+                remote: format!("{:?}", self.provider),
+                reason: "no endpoint configured for a self-hosted provider".to_string(),
+            })?;
+        Ok(format!(
+            "https://{endpoint}/{}/{}.git",
+            self.owner, self.repository
+        ))
+    }
+}
+
+impl GitSettings {
+    // typed accessor over the raw `remote` string - rejects unparseable remotes rather
+    // than the bare String letting anything through to clone-time
+    pub fn remote(&self) -> Result<GitRemote, VersionControlledSettingsError> {
+        parse_git_remote(&self.remote).map_err(|reason| {
+            VersionControlledSettingsError::InvalidGitRemote {
+                remote: self.remote.clone(),
+                reason,
+            }
+        })
+    }
+
+    // the URL git_clone/git_push should use - a configured `provider` takes precedence
+    // over the opaque `remote` string, so typed provider config always wins once set
+    pub fn remote_url(&self) -> Result<String, VersionControlledSettingsError> {
+        match &self.provider {
+            Some(provider) => provider.remote_url(),
+            None => Ok(self.remote.clone()),
+        }
+    }
+
+    // returns one error per violation instead of failing on the first, so
+    // VersionControlledSettings::validate() can report every problem at once
+    fn validate(&self) -> Vec<VersionControlledSettingsError> {
+        let mut errors = vec![];
+        if let Err(e) = self.remote() {
+            errors.push(e);
+        }
+
+        if let Some(provider) = &self.provider {
+            if let Err(e) = provider.remote_url() {
+                errors.push(e);
+            }
         }
+
+        if self.default_branch.is_empty()
+            || self.default_branch.starts_with('-')
+            || self.default_branch.contains(' ')
+            || self.default_branch.contains("..")
+        {
+            errors.push(VersionControlledSettingsError::InvalidGitBranchName {
+                name: self.default_branch.clone(),
+            });
+        }
+
+        if let Some(signing) = &self.signing {
+            match signing.format {
+                GitSigningFormat::Ssh if self.ssh_key.is_none() => {
+                    errors.push(VersionControlledSettingsError::InvalidGitSigningConfig {
+                        reason: "ssh commit signing requires git.ssh_key to be set".into(),
+                    });
+                }
+                GitSigningFormat::Gpg if signing.gpg_key_id.is_none() => {
+                    errors.push(VersionControlledSettingsError::InvalidGitSigningConfig {
+                        reason: "gpg commit signing requires git.signing.gpg_key_id to be set"
+                            .into(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        errors
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+// controls the filesystem watcher that auto-commits out-of-band edits to managed
+// settings files (e.g. a user editing octoprint.yaml directly on disk) so the git
+// history doesn't silently diverge from on-disk state
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct SettingsWatcherSettings {
+    pub enabled: bool,
+    // modify events for the same file within this window are coalesced into a
+    // single auto-commit, so e.g. an editor's save-as-temp-then-rename doesn't
+    // produce a flurry of near-duplicate revisions
+    pub debounce_ms: u64,
+    // paths (relative to paths.settings_dir) to watch instead of the files
+    // VersionControlledSettings already manages; empty means "just the managed files"
+    pub watch_paths: Vec<PathBuf>,
+}
+
+impl Default for SettingsWatcherSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            debounce_ms: 2_000,
+            watch_paths: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct PrintNannySettings {
     pub cam: PrintNannyCamSettings,
     pub git: GitSettings,
+    pub nats: NatsConfig,
+    pub proxy: PrintNannyCloudProxy,
     pub paths: PrintNannyPaths,
     pub klipper: KlipperSettings,
     pub mainsail: MainsailSettings,
     pub moonraker: MoonrakerSettings,
     pub octoprint: OctoPrintSettings,
+    pub watcher: SettingsWatcherSettings,
 }
 
 impl Default for PrintNannySettings {
@@ -158,12 +496,57 @@ impl Default for PrintNannySettings {
             octoprint: OctoPrintSettings::default(),
             moonraker: MoonrakerSettings::default(),
             mainsail: MainsailSettings::default(),
+            watcher: SettingsWatcherSettings::default(),
+            nats: NatsConfig::default(),
+            proxy: PrintNannyCloudProxy::default(),
             git,
         }
     }
 }
 
 impl PrintNannySettings {
+    // "dev" unless PRINTNANNY_PROFILE is set, so figment() only enforces the production
+    // readiness audit on boxes explicitly configured as "prod"
+    pub fn profile() -> Profile {
+        Profile::new(&env::var(PROFILE_ENV_VAR).unwrap_or_else(|_| DEV_PROFILE.to_string()))
+    }
+
+    // flags insecure or development-only configuration that's fine on a dev box but
+    // shouldn't reach a production one - accumulates every finding instead of
+    // stopping at the first, so a CLI can print the whole checklist in one pass
+    pub fn production_readiness_audit(&self) -> Vec<ProductionReadinessFinding> {
+        let mut findings = vec![];
+
+        if !self.nats.require_tls {
+            findings.push(ProductionReadinessFinding {
+                key: "nats.require_tls".into(),
+                message: "require_tls is false - NATS traffic is unencrypted".into(),
+            });
+        }
+        if self.nats.uri.contains("localhost") || self.nats.uri.contains("127.0.0.1") {
+            findings.push(ProductionReadinessFinding {
+                key: "nats.uri".into(),
+                message: format!("{:?} points at localhost", self.nats.uri),
+            });
+        }
+        if self.proxy.hostname == "localhost" || self.proxy.url.contains("localhost") {
+            findings.push(ProductionReadinessFinding {
+                key: "proxy.url".into(),
+                message: format!("{:?} points at localhost", self.proxy.url),
+            });
+        }
+        if self.git.remote == DEFAULT_PRINTNANNY_SETTINGS_GIT_REMOTE {
+            findings.push(ProductionReadinessFinding {
+                key: "git.remote".into(),
+                message: format!(
+                    "still set to the public default template repo {:?}",
+                    self.git.remote
+                ),
+            });
+        }
+        findings
+    }
+
     pub fn new() -> Result<Self, PrintNannySettingsError> {
         let figment = Self::figment()?;
         let mut result: PrintNannySettings = figment.extract()?;
@@ -171,6 +554,36 @@ impl PrintNannySettings {
         result.octoprint = OctoPrintSettings::from_dir(&result.paths.settings_dir);
         debug!("Initialized config {:?}", result);
 
+        if Self::profile() == Profile::new(PROD_PROFILE) {
+            let findings = result.production_readiness_audit();
+            if !findings.is_empty() {
+                for finding in &findings {
+                    error!("production readiness audit: {}", finding);
+                }
+                if env::var(PRODUCTION_AUDIT_OVERRIDE_ENV_VAR).is_err() {
+                    return Err(figment::Error::from(format!(
+                        "refusing to start in {} profile with {} production-readiness finding(s) (set {}=1 to override):\n{}",
+                        PROD_PROFILE,
+                        findings.len(),
+                        PRODUCTION_AUDIT_OVERRIDE_ENV_VAR,
+                        findings
+                            .iter()
+                            .map(|f| format!("- {}", f))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    ))
+                    .into());
+                } else {
+                    warn!(
+                        "{} set - starting in {} profile despite {} production-readiness finding(s)",
+                        PRODUCTION_AUDIT_OVERRIDE_ENV_VAR,
+                        PROD_PROFILE,
+                        findings.len()
+                    );
+                }
+            }
+        }
+
         Ok(result)
     }
     pub fn dashboard_url(&self) -> String {
@@ -238,7 +651,7 @@ impl PrintNannySettings {
     //
     // 2) PRINTNANNY_SETTINGS .toml. configuration file
     //
-    // 3) Glob pattern of .toml and .json configuration file fragments in conf.d folder
+    // 3) Glob pattern of .toml, .json and .yaml/.yml configuration file fragments in conf.d folder
     //
     // 4) Defaults (from implement Default)
 
@@ -262,19 +675,40 @@ impl PrintNannySettings {
         }
     }
 
-    // load figment fragments from all *.toml and *.json files relative to base_dir
+    // load figment fragments from all *.toml, *.json and *.yaml/*.yml files relative
+    // to base_dir - each format is only globbed when its cargo feature is enabled,
+    // so a build without the `json`/`yaml` feature doesn't glob (or depend on the
+    // provider for) a format it can't otherwise read
     fn load_confd(base_dir: &Path, figment: Figment) -> Result<Figment, PrintNannySettingsError> {
-        let toml_glob = format!("{}/*.toml", &base_dir.display());
-        let json_glob = format!("{}/*.json", &base_dir.display());
+        #[cfg(feature = "json")]
+        let figment = {
+            let json_glob = format!("{}/*.json", &base_dir.display());
+            Self::read_path_glob::<Json>(&json_glob, figment)
+        };
 
-        let result = Self::read_path_glob::<Json>(&json_glob, figment);
-        let result = Self::read_path_glob::<Toml>(&toml_glob, result);
-        Ok(result)
+        #[cfg(feature = "toml")]
+        let figment = {
+            let toml_glob = format!("{}/*.toml", &base_dir.display());
+            Self::read_path_glob::<Toml>(&toml_glob, figment)
+        };
+
+        #[cfg(feature = "yaml")]
+        let figment = {
+            let yaml_glob = format!("{}/*.yaml", &base_dir.display());
+            let yml_glob = format!("{}/*.yml", &base_dir.display());
+            let figment = Self::read_path_glob::<Yaml>(&yaml_glob, figment);
+            Self::read_path_glob::<Yaml>(&yml_glob, figment)
+        };
+
+        Ok(figment)
     }
 
     pub fn figment() -> Result<Figment, PrintNannySettingsError> {
         // merge file in PRINTNANNY_SETTINGS env var (if set)
         let result = Figment::from(Self { ..Self::default() })
+            // dev vs prod, selected via PRINTNANNY_PROFILE - PrintNannySettings::new() runs
+            // production_readiness_audit() against this when it resolves to "prod"
+            .select(Self::profile())
             .merge(Toml::file(Env::var_or(
                 "PRINTNANNY_SETTINGS",
                 DEFAULT_PRINTNANNY_SETTINGS_FILE,
@@ -322,6 +756,18 @@ impl PrintNannySettings {
         Ok(result)
     }
 
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(f: PathBuf) -> Result<Self, PrintNannySettingsError> {
+        let figment = PrintNannySettings::figment()?.merge(Yaml::file(f));
+        Ok(figment.extract()?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml_string(&self) -> Result<String, PrintNannySettingsError> {
+        let result = serde_yaml::to_string(self)?;
+        Ok(result)
+    }
+
     fn read_path_glob<T: 'static + figment::providers::Format>(
         pattern: &str,
         figment: Figment,
@@ -371,14 +817,135 @@ impl PrintNannySettings {
         format: &SettingsFormat,
     ) -> Result<(), PrintNannySettingsError> {
         let content: String = match format {
+            #[cfg(feature = "json")]
             SettingsFormat::Json => serde_json::to_string_pretty(self)?,
+            #[cfg(feature = "toml")]
             SettingsFormat::Toml => toml::ser::to_string_pretty(self)?,
-            _ => unimplemented!("try_init is not implemented for format: {}", format),
+            #[cfg(feature = "yaml")]
+            SettingsFormat::Yaml => serde_yaml::to_string(self)?,
         };
         fs::write(&filename, content)?;
         Ok(())
     }
 
+    // JSON Schema for the entire settings tree, derived from the same serde structs
+    // figment extracts into - editors/dashboards can point at this for autocompletion
+    // and inline validation of hand-edited printnanny.toml / conf.d fragments.
+    //
+    // NOTE: crate::settings::{cam,klipper,mainsail,moonraker,octoprint} (not part of
+    // this checkout) must also derive schemars::JsonSchema for PrintNannyCamSettings,
+    // KlipperSettings, MainsailSettings, MoonrakerSettings and OctoPrintSettings for
+    // this to compile.
+    pub fn json_schema() -> String {
+        let schema = schemars::schema_for!(PrintNannySettings);
+        serde_json::to_string_pretty(&schema)
+            .expect("Failed to serialize PrintNannySettings json schema")
+    }
+
+    // write_init-adjacent emitter - writes json_schema() to `filename` so the
+    // dashboard/editor plugins have a schema.json to validate against
+    pub fn try_write_json_schema(&self, filename: &str) -> Result<(), PrintNannySettingsError> {
+        fs::write(filename, Self::json_schema())?;
+        info!("Wrote PrintNannySettings json schema to {}", filename);
+        Ok(())
+    }
+
+    // env var name fragments that mark a PRINTNANNY_SETTINGS_* value as a secret -
+    // collect_support_bundle redacts matches before writing env.txt into the zip
+    const REDACTED_ENV_VAR_PATTERNS: [&'static str; 3] = ["TOKEN", "SECRET", "PASSWORD"];
+
+    // python binaries collect_support_bundle runs `pip freeze --all` against - just the
+    // system interpreter for now, since crate::settings::octoprint (not part of this
+    // checkout) is what would otherwise supply OctoPrint's own venv path
+    fn python_virtualenvs(&self) -> Vec<PathBuf> {
+        vec![PathBuf::from(crate::octoprint::PYTHON_BIN)]
+    }
+
+    // writes a single zip capturing the running configuration state for bug reports:
+    // the effective merged settings, raw conf.d fragments, a redacted snapshot of
+    // PRINTNANNY_SETTINGS_* env vars, parsed os-release, a redacted PrintNannyCloudData
+    // snapshot, and `pip freeze --all` from every venv python_virtualenvs() knows about.
+    pub fn collect_support_bundle(&self, out: &File) -> Result<(), PrintNannySettingsError> {
+        let mut zip = zip::ZipWriter::new(out);
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        let io_err = |e: zip::result::ZipError| std::io::Error::new(std::io::ErrorKind::Other, e);
+
+        // effective merged settings
+        zip.start_file("printnanny.toml", options).map_err(io_err)?;
+        zip.write_all(self.to_toml_string()?.as_bytes())?;
+
+        // raw conf.d fragments, one subdirectory per source
+        for (label, dir) in [
+            ("conf.d/lib", self.paths.lib_confd()),
+            ("conf.d/user", self.paths.user_confd()),
+        ] {
+            for entry in glob(&format!("{}/*", dir.display())).into_iter().flatten() {
+                match entry {
+                    Ok(path) if path.is_file() => {
+                        let name = path.file_name().unwrap().to_string_lossy();
+                        zip.start_file(format!("{}/{}", label, name), options)
+                            .map_err(io_err)?;
+                        zip.write_all(&fs::read(&path)?)?;
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to read conf.d entry: {:?}", e),
+                }
+            }
+        }
+
+        // redacted snapshot of PRINTNANNY_SETTINGS_* env vars
+        zip.start_file("env.txt", options).map_err(io_err)?;
+        for (key, value) in env::vars() {
+            if !key.starts_with("PRINTNANNY_SETTINGS_") {
+                continue;
+            }
+            let value = if Self::REDACTED_ENV_VAR_PATTERNS
+                .iter()
+                .any(|pattern| key.contains(pattern))
+            {
+                "<redacted>".to_string()
+            } else {
+                value
+            };
+            writeln!(zip, "{}={}", key, value)?;
+        }
+
+        // parsed os-release
+        if let Ok(os_release) = self.paths.load_os_release() {
+            zip.start_file("os-release.json", options).map_err(io_err)?;
+            zip.write_all(serde_json::to_string_pretty(&os_release)?.as_bytes())?;
+        }
+
+        // state snapshot, with the API bearer token redacted
+        if let Ok(mut state) = PrintNannyCloudData::load(&self.paths.state_file()) {
+            if state.api.bearer_access_token.is_some() {
+                state.api.bearer_access_token = Some("<redacted>".to_string());
+            }
+            zip.start_file("state.json", options).map_err(io_err)?;
+            zip.write_all(serde_json::to_string_pretty(&state)?.as_bytes())?;
+        }
+
+        // pip freeze --all from each referenced venv
+        for python_bin in self.python_virtualenvs() {
+            let output = Command::new(&python_bin)
+                .args(["-m", "pip", "freeze", "--all"])
+                .output();
+            match output {
+                Ok(output) => {
+                    let venv_name = python_bin.display().to_string().replace('/', "_");
+                    zip.start_file(format!("pip-freeze/{}.txt", venv_name), options)
+                        .map_err(io_err)?;
+                    zip.write_all(&output.stdout)?;
+                }
+                Err(e) => warn!("Failed to run pip freeze for {:?}: {}", python_bin, e),
+            }
+        }
+
+        zip.finish().map_err(io_err)?;
+        Ok(())
+    }
+
     /// Extract a `Config` from `provider`, panicking if extraction fails.
     ///
     /// # Panics
@@ -435,15 +1002,32 @@ impl VersionControlledSettings for PrintNannySettings {
 
     async fn pre_save(&self) -> Result<(), VersionControlledSettingsError> {
         debug!("Running PrintNannySettings pre_save hook");
-        Ok(())
+        // reject an invalid settings tree before it's written to disk and committed to git
+        self.validate()
     }
 
     async fn post_save(&self) -> Result<(), VersionControlledSettingsError> {
         debug!("Running PrintNannySettings post_save hook");
+        self.git_notify_commit_email()?;
         Ok(())
     }
+
+    // walks each sub-settings struct and accumulates every violation rather than
+    // failing on the first, so a caller (e.g. a NATS settings.apply handler) can
+    // surface all of them to the user at once instead of a fix-one-retry loop.
+    //
+    // NOTE: PrintNannyCamSettings' VideoSrcType-tagged source validation (File -> path
+    // exists, Device -> /dev/video* exists, Uri -> parses as URL) is not implemented
+    // here - crate::settings::cam is not part of this checkout.
     fn validate(&self) -> Result<(), VersionControlledSettingsError> {
-        todo!("OctoPrintSettings validate hook is not yet implemented");
+        let mut errors = vec![];
+        errors.extend(self.git.validate());
+        errors.extend(self.nats.validate());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(VersionControlledSettingsError::Validation(errors))
+        }
     }
 }
 
@@ -451,6 +1035,7 @@ impl VersionControlledSettings for PrintNannySettings {
 mod tests {
     use super::*;
     use crate::paths::PRINTNANNY_SETTINGS_FILENAME;
+    use std::io::Read;
 
     #[test_log::test]
     fn test_config_file_not_found() {
@@ -697,4 +1282,174 @@ VARIANT_ID=printnanny-octoprint
             Ok(())
         });
     }
+
+    #[test_log::test]
+    fn test_confd_yaml_fragment() {
+        figment::Jail::expect_with(|jail| {
+            let output = jail.directory().to_str().unwrap();
+            jail.create_file(
+                "Local.toml",
+                &format!(
+                    r#"
+                profile = "local"
+
+                [paths]
+                state_dir = "{}"
+                "#,
+                    output
+                ),
+            )?;
+            jail.set_env("PRINTNANNY_SETTINGS", "Local.toml");
+
+            let figment = PrintNannySettings::figment().unwrap();
+            let settings: PrintNannySettings = figment.extract()?;
+            fs::create_dir(settings.paths.lib_confd()).unwrap();
+            jail.create_file(
+                settings.paths.lib_confd().join("octoprint.yaml").to_str().unwrap(),
+                r#"
+                octoprint:
+                  enabled: false
+                "#,
+            )?;
+
+            let figment = PrintNannySettings::figment().unwrap();
+            let settings: PrintNannySettings = figment.extract()?;
+            assert_eq!(settings.octoprint.enabled, false);
+            Ok(())
+        });
+    }
+
+    #[test_log::test]
+    fn test_validate_accumulates_all_errors() {
+        let mut settings = PrintNannySettings::default();
+        settings.git.remote = "not a remote".into();
+        settings.git.default_branch = "".into();
+        settings.nats.uri = "http://localhost:4222".into();
+
+        match settings.validate() {
+            Err(VersionControlledSettingsError::Validation(errors)) => {
+                assert_eq!(errors.len(), 3);
+            }
+            other => panic!("expected accumulated validation errors, got {:?}", other),
+        }
+    }
+
+    #[test_log::test]
+    fn test_validate_require_tls_rejects_plaintext_uri() {
+        let mut settings = PrintNannySettings::default();
+        settings.nats.uri = "nats://localhost:4222".into();
+        settings.nats.require_tls = true;
+
+        match settings.validate() {
+            Err(VersionControlledSettingsError::Validation(errors)) => {
+                assert_eq!(errors.len(), 1);
+            }
+            other => panic!("expected accumulated validation errors, got {:?}", other),
+        }
+    }
+
+    #[test_log::test]
+    fn test_validate_accepts_defaults_and_scp_remote() {
+        let mut settings = PrintNannySettings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.git.remote = "git@github.com:bitsy-ai/printnanny-settings.git".into();
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test_log::test]
+    fn test_git_remote_parses_https_and_scp_forms() {
+        let mut settings = GitSettings::default();
+
+        settings.remote = "https://github.com/bitsy-ai/printnanny-settings.git".into();
+        assert!(matches!(settings.remote(), Ok(GitRemote::Https(_))));
+
+        settings.remote = "git@github.com:bitsy-ai/printnanny-settings.git".into();
+        match settings.remote() {
+            Ok(GitRemote::Ssh { user, host, path }) => {
+                assert_eq!(user, "git");
+                assert_eq!(host, "github.com");
+                assert_eq!(path, "bitsy-ai/printnanny-settings.git");
+            }
+            other => panic!("expected GitRemote::Ssh, got {:?}", other),
+        }
+
+        settings.remote = "not a remote".into();
+        assert!(settings.remote().is_err());
+    }
+
+    #[test_log::test]
+    fn test_production_readiness_audit_flags_insecure_defaults() {
+        let findings = PrintNannySettings::default().production_readiness_audit();
+        // defaults are dev-friendly: plaintext nats, localhost proxy, template git remote
+        assert_eq!(findings.len(), 3);
+    }
+
+    #[test_log::test]
+    fn test_prod_profile_refuses_to_start_with_findings() {
+        figment::Jail::expect_with(|jail| {
+            jail.set_env("PRINTNANNY_PROFILE", PROD_PROFILE);
+            assert!(PrintNannySettings::new().is_err());
+
+            jail.set_env("PRINTNANNY_PROFILE_AUDIT_OVERRIDE", "1");
+            assert!(PrintNannySettings::new().is_ok());
+            Ok(())
+        });
+    }
+
+    #[test_log::test]
+    fn test_json_schema_describes_settings_tree() {
+        let schema = PrintNannySettings::json_schema();
+        let value: serde_json::Value = serde_json::from_str(&schema).unwrap();
+        assert_eq!(value["title"], "PrintNannySettings");
+        assert!(value["properties"]["git"].is_object());
+        assert!(value["properties"]["nats"].is_object());
+    }
+
+    #[test_log::test]
+    fn test_collect_support_bundle_redacts_secrets() {
+        figment::Jail::expect_with(|jail| {
+            let output = jail.directory().to_str().unwrap();
+            jail.create_file(
+                "Local.toml",
+                &format!(
+                    r#"
+                profile = "local"
+
+                [paths]
+                state_dir = "{}"
+
+                [nats]
+                uri = "nats://localhost:4222"
+                "#,
+                    output
+                ),
+            )?;
+            jail.set_env("PRINTNANNY_SETTINGS", "Local.toml");
+            jail.set_env("PRINTNANNY_SETTINGS_NATS__URI", "not-a-secret-value");
+            jail.set_env("PRINTNANNY_SETTINGS_EXTRA_PASSWORD", "value-to-redact");
+
+            let settings = PrintNannySettings::new().unwrap();
+            fs::create_dir(settings.paths.lib_confd()).unwrap();
+
+            let bundle_path = PathBuf::from(output).join("support-bundle.zip");
+            let file = File::create(&bundle_path).unwrap();
+            settings.collect_support_bundle(&file).unwrap();
+            drop(file);
+
+            let file = File::open(&bundle_path).unwrap();
+            let mut archive = zip::ZipArchive::new(file).unwrap();
+            let mut env_contents = String::new();
+            archive
+                .by_name("env.txt")
+                .unwrap()
+                .read_to_string(&mut env_contents)
+                .unwrap();
+            assert!(env_contents.contains("not-a-secret-value"));
+            assert!(!env_contents.contains("value-to-redact"));
+            assert!(env_contents.contains("PRINTNANNY_SETTINGS_EXTRA_PASSWORD=<redacted>"));
+            assert!(archive.by_name("printnanny.toml").is_ok());
+            Ok(())
+        });
+    }
 }
\ No newline at end of file
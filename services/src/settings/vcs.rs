@@ -1,9 +1,12 @@
+use std::env;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use async_trait::async_trait;
 use git2::{DiffFormat, DiffOptions, Repository};
-use log::info;
+use log::{info, warn};
 use printnanny_asyncapi_models::SettingsFile;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -12,9 +15,20 @@ use printnanny_dbus::zbus;
 
 use crate::error::PrintNannyCloudDataError;
 use crate::error::PrintNannySettingsError;
-use crate::settings::printnanny::PrintNannySettings;
+use crate::settings::printnanny::{GitRemote, GitSigningConfig, GitSigningFormat, PrintNannySettings};
 use crate::settings::SettingsFormat;
 
+// GIT_ASKPASS and SSH_ASKPASS helper installed alongside printnanny-cli - resolves
+// passphrase and host-key-confirmation prompts by calling back into this process's
+// control socket instead of reading a TTY, so `git push`/`git fetch` never block on
+// stdin when invoked from a NATS handler
+const GIT_ASKPASS_HELPER: &str = "/usr/share/printnanny/askpass";
+
+// passphrase for settings.git.ssh_key when it's an encrypted (bcrypt-pbkdf/aes-gcm)
+// OpenSSH private key - read at clone/fetch/push time rather than stored in settings,
+// the same reasoning GitProviderKind::token_env_var uses for provider tokens
+const GIT_SSH_KEY_PASSPHRASE_ENV_VAR: &str = "PRINTNANNY_GIT_SSH_KEY_PASSPHRASE";
+
 #[derive(Error, Debug)]
 pub enum VersionControlledSettingsError {
     #[error("Failed to write {path} - {error}")]
@@ -27,12 +41,48 @@ pub enum VersionControlledSettingsError {
         dest: PathBuf,
         error: std::io::Error,
     },
+    #[error("Failed to spawn git {args:?} - {error}")]
+    GitSpawnError {
+        args: Vec<String>,
+        error: std::io::Error,
+    },
+    #[error("git {args:?} exited with {status} - {stderr}")]
+    GitCommandError {
+        args: Vec<String>,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
     #[error(transparent)]
     GitError(#[from] git2::Error),
     #[error(transparent)]
     ZbusError(#[from] zbus::Error),
     #[error(transparent)]
     PrintNannyCloudDataError(#[from] PrintNannyCloudDataError),
+    #[error("invalid nats uri {uri:?} - {reason}")]
+    InvalidNatsUri { uri: String, reason: String },
+    #[error("invalid git remote {remote:?} - {reason}")]
+    InvalidGitRemote { remote: String, reason: String },
+    #[error("invalid git branch name {name:?} - branch names must be non-empty, contain no spaces, no \"..\", and not start with \"-\"")]
+    InvalidGitBranchName { name: String },
+    #[error("settings failed validation with {} error(s):\n{}", .0.len(), .0.iter().map(|e| format!("- {}", e)).collect::<Vec<_>>().join("\n"))]
+    Validation(Vec<VersionControlledSettingsError>),
+    #[error("merge conflict in {} file(s), local edits were not overwritten:\n{}\n\n{diff}", .files.len(), .files.join("\n"))]
+    MergeConflict { files: Vec<String>, diff: String },
+    #[error("invalid git commit signing config - {reason}")]
+    InvalidGitSigningConfig { reason: String },
+}
+
+// outcome of fetching a remote and checking whether the local branch can fast-forward
+// onto it - Diverged carries both oids so a caller can choose a three-way merge or a
+// hard revert rather than losing local commits to a silent overwrite
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GitFastForwardOutcome {
+    UpToDate,
+    FastForwarded(git2::Oid),
+    Diverged {
+        local_oid: git2::Oid,
+        remote_oid: git2::Oid,
+    },
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -41,6 +91,38 @@ pub struct GitCommit {
     pub header: String,
     pub message: String,
     pub ts: i64,
+    // whether this commit carries a gpgsig trailer - set from the raw header alone,
+    // so a plain `From<git2::Commit>` conversion doesn't need repo/network access;
+    // `signed` does not imply the signature verified, see `verify_commit`
+    pub signed: bool,
+    // principal/key id the signature verified against, filled in by `get_rev_list`
+    // (via `verify_commit`) rather than the `From<git2::Commit>` conversion
+    pub signer: Option<String>,
+}
+
+// one line of a GitDiffHunk - origin matches git2::DiffLine::origin(): '+' added,
+// '-' removed, ' ' unchanged context
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GitDiffLine {
+    pub origin: char,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GitDiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<GitDiffLine>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GitFileDiff {
+    pub file_name: String,
+    pub hunks: Vec<GitDiffHunk>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
 }
 
 #[async_trait]
@@ -68,8 +150,41 @@ pub trait VersionControlledSettings {
     }
     fn git_clone(&self) -> Result<Repository, PrintNannySettingsError> {
         let settings = PrintNannySettings::new()?;
-        let repo = Repository::clone(&settings.git.remote, settings.paths.settings_dir)?;
-        Ok(repo)
+        // a configured provider always resolves to an https:// URL; its token (if any)
+        // authenticates the clone the same way an ssh_key authenticates an ssh:// remote
+        let remote_url = settings
+            .git
+            .remote_url()
+            .unwrap_or_else(|_| settings.git.remote.clone());
+        match (&settings.git.provider, settings.git.remote(), &settings.git.ssh_key) {
+            (Some(provider), _, _) => {
+                let token = provider.token();
+                let mut callbacks = git2::RemoteCallbacks::new();
+                callbacks.credentials(move |_url, _username_from_url, _allowed_types| match &token
+                {
+                    Some(token) => git2::Cred::userpass_plaintext(token, ""),
+                    None => git2::Cred::default(),
+                });
+                let mut fetch_options = git2::FetchOptions::new();
+                fetch_options.remote_callbacks(callbacks);
+                let repo = git2::build::RepoBuilder::new()
+                    .fetch_options(fetch_options)
+                    .clone(&remote_url, settings.paths.settings_dir)?;
+                Ok(repo)
+            }
+            (None, Ok(GitRemote::Ssh { .. }), Some(_ssh_key)) => {
+                let mut fetch_options = git2::FetchOptions::new();
+                fetch_options.remote_callbacks(self.ssh_callbacks());
+                let repo = git2::build::RepoBuilder::new()
+                    .fetch_options(fetch_options)
+                    .clone(&remote_url, settings.paths.settings_dir)?;
+                Ok(repo)
+            }
+            _ => {
+                let repo = Repository::clone(&remote_url, settings.paths.settings_dir)?;
+                Ok(repo)
+            }
+        }
     }
 
     fn get_git_repo(&self) -> Result<Repository, git2::Error> {
@@ -94,6 +209,117 @@ pub trait VersionControlledSettings {
         )?;
         Ok(lines.join("\n"))
     }
+    // builds a per-file unified diff between two commits in the settings repo, e.g.
+    // so a caller can review pending changes before choosing to apply or revert.
+    // `pathspec` restricts the diff to a single managed settings file; None diffs
+    // everything committed under settings_dir.
+    fn git_diff_commits(
+        &self,
+        from_oid: git2::Oid,
+        to_oid: git2::Oid,
+        pathspec: Option<&str>,
+    ) -> Result<Vec<GitFileDiff>, git2::Error> {
+        let repo = self.get_git_repo()?;
+        let from_tree = repo.find_commit(from_oid)?.tree()?;
+        let to_tree = repo.find_commit(to_oid)?.tree()?;
+
+        let mut diffopts = DiffOptions::new();
+        diffopts.force_text(true).old_prefix("old").new_prefix("new");
+        if let Some(pathspec) = pathspec {
+            diffopts.pathspec(pathspec);
+        }
+
+        let diff =
+            repo.diff_tree_to_tree(Some(&from_tree), Some(&to_tree), Some(&mut diffopts))?;
+
+        let mut files: Vec<GitFileDiff> = vec![];
+        diff.foreach(
+            &mut |delta, _progress| {
+                files.push(GitFileDiff {
+                    file_name: delta
+                        .new_file()
+                        .path()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default(),
+                    hunks: vec![],
+                    lines_added: 0,
+                    lines_removed: 0,
+                });
+                true
+            },
+            None,
+            Some(&mut |delta, hunk| {
+                let file_name = delta
+                    .new_file()
+                    .path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                if let Some(file) = files.iter_mut().find(|f| f.file_name == file_name) {
+                    file.hunks.push(GitDiffHunk {
+                        old_start: hunk.old_start(),
+                        old_lines: hunk.old_lines(),
+                        new_start: hunk.new_start(),
+                        new_lines: hunk.new_lines(),
+                        lines: vec![],
+                    });
+                }
+                true
+            }),
+            Some(&mut |delta, _hunk, line| {
+                let file_name = delta
+                    .new_file()
+                    .path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                if let Some(file) = files.iter_mut().find(|f| f.file_name == file_name) {
+                    let origin = line.origin();
+                    match origin {
+                        '+' => file.lines_added += 1,
+                        '-' => file.lines_removed += 1,
+                        _ => {}
+                    }
+                    if matches!(origin, '+' | '-' | ' ') {
+                        if let Some(current_hunk) = file.hunks.last_mut() {
+                            current_hunk.lines.push(GitDiffLine {
+                                origin,
+                                content: std::str::from_utf8(line.content())
+                                    .unwrap_or_default()
+                                    .trim_end_matches('\n')
+                                    .to_string(),
+                            });
+                        }
+                    }
+                }
+                true
+            }),
+        )?;
+
+        Ok(files)
+    }
+
+    // unified diff between `oid` and its first parent (or, for a root commit, an empty
+    // tree), rendered the same way `git_diff` renders the index/workdir pair - used to
+    // attach a format-patch style body to the push-to-email notifier
+    fn git_commit_patch(&self, oid: git2::Oid) -> Result<String, git2::Error> {
+        let repo = self.get_git_repo()?;
+        let commit = repo.find_commit(oid)?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+
+        let mut diffopts = DiffOptions::new();
+        let diffopts = diffopts.force_text(true).old_prefix("old").new_prefix("new");
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(diffopts))?;
+
+        let mut lines: Vec<String> = vec![];
+        diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+            lines.push(std::str::from_utf8(line.content()).unwrap().to_string());
+            true
+        })?;
+        Ok(lines.join("\n"))
+    }
     fn read_settings(&self) -> Result<String, VersionControlledSettingsError> {
         let settings_file = self.get_settings_file();
         let result = match fs::read_to_string(&settings_file) {
@@ -170,11 +396,16 @@ pub trait VersionControlledSettings {
         revwalk.push_glob(&self.get_settings_file().display().to_string())?;
         let mut result: Vec<GitCommit> = vec![];
         for r in revwalk {
-            let commit = match r {
-                Ok(oid) => repo.find_commit(oid),
-                Err(e) => Err(e),
-            }?;
-            result.push(commit.into())
+            let oid = r?;
+            let commit = repo.find_commit(oid)?;
+            let mut entry: GitCommit = commit.into();
+            // verify_commit shells out to ssh-keygen/gpg, so a broken/missing binary
+            // degrades a rev-list entry to "unsigned" rather than failing the whole list
+            if let Ok((signed, signer)) = self.verify_commit(oid) {
+                entry.signed = signed;
+                entry.signer = signer;
+            }
+            result.push(entry)
         }
         Ok(result)
     }
@@ -188,18 +419,324 @@ pub trait VersionControlledSettings {
         let parent_commit = repo.head()?.peel_to_commit()?;
         let tree = repo.find_tree(oid)?;
         let commit_msg = commit_msg.unwrap_or_else(|| self.get_git_commit_message().unwrap());
-        let result = repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            &commit_msg,
-            &tree,
-            &[&parent_commit],
-        )?;
-        info!("Committed settings with msg: {} and {}", commit_msg, oid);
+
+        let settings = PrintNannySettings::new().unwrap();
+        let result = match &settings.git.signing {
+            Some(signing) => {
+                let buf = repo.commit_create_buffer(
+                    &signature,
+                    &signature,
+                    &commit_msg,
+                    &tree,
+                    &[&parent_commit],
+                )?;
+                let content = buf.as_str().ok_or_else(|| {
+                    git2::Error::from_str("commit_create_buffer produced non-utf8 content")
+                })?;
+                let sig = self
+                    .sign_commit_buffer(content, signing)
+                    .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+                let signed_oid = repo.commit_signed(content, &sig, Some("gpgsig"))?;
+                repo.head()?.set_target(signed_oid, &commit_msg)?;
+                signed_oid
+            }
+            None => repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &commit_msg,
+                &tree,
+                &[&parent_commit],
+            )?,
+        };
+        info!("Committed settings with msg: {} and {}", commit_msg, result);
         Ok(result)
     }
 
+    // signs a `commit_create_buffer` payload per GitSettings.signing, shelling out to
+    // ssh-keygen or gpg the same way git_spawn shells out to the real `git` binary for
+    // operations libgit2 doesn't implement itself
+    fn sign_commit_buffer(
+        &self,
+        content: &str,
+        signing: &GitSigningConfig,
+    ) -> Result<String, VersionControlledSettingsError> {
+        match signing.format {
+            GitSigningFormat::Ssh => {
+                let settings = PrintNannySettings::new().unwrap();
+                let ssh_key = settings.git.ssh_key.clone().ok_or_else(|| {
+                    VersionControlledSettingsError::InvalidGitSigningConfig {
+                        reason: "ssh commit signing requires git.ssh_key to be set".to_string(),
+                    }
+                })?;
+                let payload_path =
+                    env::temp_dir().join(format!("printnanny-commit-{}.payload", std::process::id()));
+                self.write_tmp_file(&payload_path, content)?;
+
+                let output = Command::new("ssh-keygen")
+                    .args(["-Y", "sign", "-n", "git", "-f"])
+                    .arg(&ssh_key)
+                    .arg(&payload_path)
+                    .output()
+                    .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                        args: vec!["ssh-keygen".into(), "-Y".into(), "sign".into()],
+                        error,
+                    })?;
+                let sig_path = payload_path.with_extension("payload.sig");
+                let sig_result = if output.status.success() {
+                    fs::read_to_string(&sig_path).map_err(|error| {
+                        VersionControlledSettingsError::ReadIOError {
+                            path: sig_path.display().to_string(),
+                            error,
+                        }
+                    })
+                } else {
+                    Err(VersionControlledSettingsError::GitCommandError {
+                        args: vec!["ssh-keygen".into(), "-Y".into(), "sign".into()],
+                        status: output.status,
+                        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                    })
+                };
+                let _ = fs::remove_file(&payload_path);
+                let _ = fs::remove_file(&sig_path);
+                sig_result
+            }
+            GitSigningFormat::Gpg => {
+                let key_id = signing.gpg_key_id.as_deref().ok_or_else(|| {
+                    VersionControlledSettingsError::InvalidGitSigningConfig {
+                        reason: "gpg commit signing requires git.signing.gpg_key_id to be set"
+                            .to_string(),
+                    }
+                })?;
+                let mut child = Command::new("gpg")
+                    .args(["--detach-sign", "--armor", "--local-user", key_id])
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped())
+                    .spawn()
+                    .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                        args: vec!["gpg".into(), "--detach-sign".into()],
+                        error,
+                    })?;
+                child
+                    .stdin
+                    .take()
+                    .unwrap()
+                    .write_all(content.as_bytes())
+                    .map_err(|error| VersionControlledSettingsError::WriteIOError {
+                        path: "gpg stdin".to_string(),
+                        error,
+                    })?;
+                let output = child.wait_with_output().map_err(|error| {
+                    VersionControlledSettingsError::GitSpawnError {
+                        args: vec!["gpg".into(), "--detach-sign".into()],
+                        error,
+                    }
+                })?;
+                if !output.status.success() {
+                    return Err(VersionControlledSettingsError::GitCommandError {
+                        args: vec!["gpg".into(), "--detach-sign".into()],
+                        status: output.status,
+                        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                    });
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            }
+        }
+    }
+
+    // verifies the signature on `oid`, if any - returns (signed, signer). A commit with
+    // no gpgsig trailer is reported as (false, None) rather than an error, so
+    // `get_rev_list` can call this uniformly for every commit it walks
+    fn verify_commit(
+        &self,
+        oid: git2::Oid,
+    ) -> Result<(bool, Option<String>), VersionControlledSettingsError> {
+        let repo = self.get_git_repo()?;
+        let (signature, signed_data) = match repo.extract_signature(&oid, None) {
+            Ok(parts) => parts,
+            Err(_) => return Ok((false, None)),
+        };
+        let signature = signature.as_str().unwrap_or_default().to_string();
+        let signed_data = signed_data.as_str().unwrap_or_default().to_string();
+
+        let data_path =
+            env::temp_dir().join(format!("printnanny-verify-{oid}.payload"));
+        self.write_tmp_file(&data_path, &signed_data)?;
+        let result = if signature.starts_with("-----BEGIN SSH SIGNATURE-----") {
+            self.verify_ssh_signature(&signature, &data_path)
+        } else {
+            self.verify_gpg_signature(&signature, &data_path)
+        };
+        let _ = fs::remove_file(&data_path);
+        result
+    }
+
+    // ssh-keygen has no "verify against this one key" shortcut, so a throwaway
+    // allowed_signers file naming settings.git.ssh_key's public key is written just
+    // for this check
+    fn verify_ssh_signature(
+        &self,
+        signature: &str,
+        data_path: &Path,
+    ) -> Result<(bool, Option<String>), VersionControlledSettingsError> {
+        let settings = PrintNannySettings::new().unwrap();
+        let ssh_key = match &settings.git.ssh_key {
+            Some(path) => path,
+            None => return Ok((false, None)),
+        };
+        let pubkey_path = ssh_key.with_extension("pub");
+        let pubkey = match fs::read_to_string(&pubkey_path) {
+            Ok(p) => p,
+            Err(_) => return Ok((false, None)),
+        };
+
+        const PRINCIPAL: &str = "git";
+        let allowed_signers_path =
+            env::temp_dir().join(format!("printnanny-allowed-signers-{}", std::process::id()));
+        self.write_tmp_file(&allowed_signers_path, &format!("{PRINCIPAL} {pubkey}"))?;
+        let sig_path = data_path.with_extension("payload.sig");
+        self.write_tmp_file(&sig_path, signature)?;
+
+        let data_file = fs::File::open(data_path).map_err(|error| {
+            VersionControlledSettingsError::ReadIOError {
+                path: data_path.display().to_string(),
+                error,
+            }
+        })?;
+        let output = Command::new("ssh-keygen")
+            .args(["-Y", "verify", "-f"])
+            .arg(&allowed_signers_path)
+            .args(["-I", PRINCIPAL, "-n", "git", "-s"])
+            .arg(&sig_path)
+            .stdin(data_file)
+            .output()
+            .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                args: vec!["ssh-keygen".into(), "-Y".into(), "verify".into()],
+                error,
+            })?;
+        let _ = fs::remove_file(&allowed_signers_path);
+        let _ = fs::remove_file(&sig_path);
+        let verified = output.status.success();
+        let signer = if verified { Some(PRINCIPAL.to_string()) } else { None };
+        Ok((verified, signer))
+    }
+
+    fn verify_gpg_signature(
+        &self,
+        signature: &str,
+        data_path: &Path,
+    ) -> Result<(bool, Option<String>), VersionControlledSettingsError> {
+        let sig_path = data_path.with_extension("payload.asc");
+        self.write_tmp_file(&sig_path, signature)?;
+
+        let output = Command::new("gpg")
+            .args(["--verify", "--status-fd", "1"])
+            .arg(&sig_path)
+            .arg(data_path)
+            .output()
+            .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                args: vec!["gpg".into(), "--verify".into()],
+                error,
+            })?;
+        let _ = fs::remove_file(&sig_path);
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let signer = stdout.lines().find_map(|line| {
+            line.strip_prefix("[GNUPG:] VALIDSIG ")
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(|s| s.to_string())
+        });
+        Ok((output.status.success(), signer))
+    }
+
+    fn write_tmp_file(&self, path: &Path, content: &str) -> Result<(), VersionControlledSettingsError> {
+        fs::write(path, content).map_err(|error| VersionControlledSettingsError::WriteIOError {
+            path: path.display().to_string(),
+            error,
+        })
+    }
+
+    // opt-in "push commits out as email" notifier - emails a format-patch style
+    // summary of the current HEAD commit to settings.git.notify_email.recipients, the
+    // same shell-out-to-sendmail delivery `git send-email` itself falls back to. A
+    // no-op when no recipients are configured, so this is safe to call unconditionally
+    // from a post_save hook after every commit.
+    fn git_notify_commit_email(&self) -> Result<(), VersionControlledSettingsError> {
+        let settings = PrintNannySettings::new().unwrap();
+        let notify = &settings.git.notify_email;
+        if notify.recipients.is_empty() {
+            return Ok(());
+        }
+
+        let commit = self.get_git_head_commit()?;
+        let oid = git2::Oid::from_str(&commit.oid)?;
+        let patch = self.git_commit_patch(oid)?;
+        let commit_msg = self
+            .get_git_commit_message()
+            .unwrap_or_else(|_| commit.message.clone());
+        // the commit message's first line doubles as the email Subject, the same way
+        // `git format-patch` derives a patch's Subject from it
+        let subject_line = commit.message.lines().next().unwrap_or_default();
+
+        let message = format!(
+            "From: PrintNanny <{from}>\nTo: {to}\nSubject: [PrintNanny] {subject_line}\n\n{commit_msg}\n---\ncommit {oid}\n{header}\n{patch}\n",
+            from = settings.git.email,
+            to = notify.recipients.join(", "),
+            subject_line = subject_line,
+            commit_msg = commit_msg,
+            oid = commit.oid,
+            header = commit.header,
+            patch = patch,
+        );
+
+        let mut child = Command::new(&notify.sendmail_bin)
+            .arg("-t")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                args: vec![notify.sendmail_bin.clone(), "-t".into()],
+                error,
+            })?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(message.as_bytes())
+            .map_err(|error| VersionControlledSettingsError::WriteIOError {
+                path: "sendmail stdin".to_string(),
+                error,
+            })?;
+        let output = child
+            .wait_with_output()
+            .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                args: vec![notify.sendmail_bin.clone(), "-t".into()],
+                error,
+            })?;
+        if !output.status.success() {
+            return Err(VersionControlledSettingsError::GitCommandError {
+                args: vec![notify.sendmail_bin.clone(), "-t".into()],
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+        info!(
+            "Emailed settings commit {} to {}",
+            commit.oid,
+            notify.recipients.join(", ")
+        );
+        Ok(())
+    }
+
+    fn git_reset_hard(&self, oid: git2::Oid) -> Result<(), git2::Error> {
+        let repo = self.get_git_repo()?;
+        let commit = repo.find_commit(oid)?;
+        repo.reset(commit.as_object(), git2::ResetType::Hard, None)?;
+        Ok(())
+    }
+
     fn git_revert(&self, oid: Option<git2::Oid>) -> Result<(), git2::Error> {
         let repo = self.get_git_repo()?;
         let commit = match oid {
@@ -209,16 +746,318 @@ pub trait VersionControlledSettings {
         repo.revert(&commit, None)
     }
 
+    // registers `name` pointing at `url`, or repoints it if it's already configured -
+    // used for off-device backup/restore of the settings history rather than the
+    // clone-time `settings.git.remote`
+    fn git_remote_add(&self, name: &str, url: &str) -> Result<(), git2::Error> {
+        let repo = self.get_git_repo()?;
+        if repo.find_remote(name).is_ok() {
+            repo.remote_set_url(name, url)?;
+        } else {
+            repo.remote(name, url)?;
+        }
+        Ok(())
+    }
+
+    fn git_current_branch(&self) -> Result<String, git2::Error> {
+        let repo = self.get_git_repo()?;
+        let head = repo.head()?;
+        Ok(head
+            .shorthand()
+            .unwrap_or(&PrintNannySettings::new().unwrap().git.default_branch)
+            .to_string())
+    }
+
+    // shells out to the real `git` binary (rather than libgit2) so GIT_ASKPASS/SSH_ASKPASS
+    // are honored for SSH key passphrases and HTTPS token auth without a TTY
+    fn git_spawn(&self, args: &[&str]) -> Result<(), VersionControlledSettingsError> {
+        let settings = PrintNannySettings::new().unwrap();
+        let mut command = Command::new("git");
+        command
+            .current_dir(&settings.paths.settings_dir)
+            .env("GIT_ASKPASS", GIT_ASKPASS_HELPER)
+            .env("SSH_ASKPASS", GIT_ASKPASS_HELPER)
+            .env("SSH_ASKPASS_REQUIRE", "force");
+
+        // SSH remotes authenticate with a configured key file rather than the
+        // GIT_ASKPASS/SSH_ASKPASS bearer-token flow used for https:// remotes
+        if let (Ok(GitRemote::Ssh { .. }), Some(ssh_key)) =
+            (settings.git.remote(), &settings.git.ssh_key)
+        {
+            command.env(
+                "GIT_SSH_COMMAND",
+                format!(
+                    "ssh -i {} -o IdentitiesOnly=yes",
+                    ssh_key.display()
+                ),
+            );
+        }
+
+        let output = command
+            .args(args)
+            .output()
+            .map_err(|error| VersionControlledSettingsError::GitSpawnError {
+                args: args.iter().map(|a| a.to_string()).collect(),
+                error,
+            })?;
+        if !output.status.success() {
+            return Err(VersionControlledSettingsError::GitCommandError {
+                args: args.iter().map(|a| a.to_string()).collect(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // credentials + host-key verification shared by every git2-driven network operation
+    // (git_clone's ssh:// branch, git2_fetch, git2_push) - reads settings.git.ssh_key and
+    // the GIT_SSH_KEY_PASSPHRASE_ENV_VAR passphrase for encrypted (bcrypt-pbkdf/aes-gcm)
+    // OpenSSH keys, and settings.git.known_hosts_verify to decide whether an unrecognized
+    // host key aborts the connection or is accepted
+    fn ssh_callbacks(&self) -> git2::RemoteCallbacks<'static> {
+        let settings = PrintNannySettings::new().unwrap();
+        let ssh_key = settings.git.ssh_key.clone();
+        let passphrase = env::var(GIT_SSH_KEY_PASSPHRASE_ENV_VAR).ok();
+        let verify_known_hosts = settings.git.known_hosts_verify;
+
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(move |_url, username_from_url, _allowed_types| match &ssh_key {
+            Some(path) => git2::Cred::ssh_key(
+                username_from_url.unwrap_or("git"),
+                None,
+                path,
+                passphrase.as_deref(),
+            ),
+            None => git2::Cred::default(),
+        });
+        // reuses whatever known_hosts file the system ssh client already trusts, rather
+        // than printnanny maintaining a second copy of host-key state
+        callbacks.certificate_check(move |cert, host| {
+            if !verify_known_hosts || cert.as_hostkey().is_none() {
+                return Ok(git2::CertificateCheckStatus::CertificateOk);
+            }
+            let known = Command::new("ssh-keygen")
+                .args(["-F", host])
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false);
+            if known {
+                Ok(git2::CertificateCheckStatus::CertificateOk)
+            } else {
+                Err(git2::Error::from_str(&format!(
+                    "host key for {host} is not in known_hosts - refusing to continue"
+                )))
+            }
+        });
+        callbacks
+    }
+
+    fn git_push(&self, remote_name: &str) -> Result<(), VersionControlledSettingsError> {
+        let branch = self.git_current_branch()?;
+        let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+        self.git_spawn(&["push", remote_name, &refspec])
+    }
+
+    fn git_fetch(&self, remote_name: &str) -> Result<(), VersionControlledSettingsError> {
+        self.git_spawn(&["fetch", remote_name])
+    }
+
+    // git2-driven counterpart to git_fetch - used where the shell-based GIT_ASKPASS flow
+    // isn't appropriate (e.g. enforcing known_hosts_verify before GIT_ASKPASS_HELPER is
+    // installed on a freshly provisioned device)
+    fn git2_fetch(&self, remote_name: &str) -> Result<(), VersionControlledSettingsError> {
+        let repo = self.get_git_repo()?;
+        let mut remote = repo.find_remote(remote_name)?;
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(self.ssh_callbacks());
+        remote.fetch::<&str>(&[], Some(&mut fetch_options), None)?;
+        Ok(())
+    }
+
+    // git2-driven counterpart to git_push, for the same reason as git2_fetch
+    fn git2_push(&self, remote_name: &str) -> Result<(), VersionControlledSettingsError> {
+        let branch = self.git_current_branch()?;
+        let repo = self.get_git_repo()?;
+        let mut remote = repo.find_remote(remote_name)?;
+        let mut push_options = git2::PushOptions::new();
+        push_options.remote_callbacks(self.ssh_callbacks());
+        let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+        remote.push(&[refspec], Some(&mut push_options))?;
+        Ok(())
+    }
+
+    // resolves "origin", registering it from settings.git.remote first if this repo
+    // hasn't been pointed at a remote yet (e.g. a settings dir seeded from a local
+    // factory-reset rather than `git_clone`)
+    fn resolve_origin_remote(&self) -> Result<String, VersionControlledSettingsError> {
+        let repo = self.get_git_repo()?;
+        if repo.find_remote("origin").is_err() {
+            let settings = PrintNannySettings::new().unwrap();
+            let remote_url = settings
+                .git
+                .remote_url()
+                .unwrap_or_else(|_| settings.git.remote.clone());
+            self.git_remote_add("origin", &remote_url)?;
+        }
+        Ok("origin".to_string())
+    }
+
+    // convenience wrapper used by `save_and_commit`'s push flag - pushes to "origin",
+    // falling back to settings.git.remote if "origin" isn't configured yet
+    fn git_push_origin(&self) -> Result<(), VersionControlledSettingsError> {
+        let remote_name = self.resolve_origin_remote()?;
+        self.git_push(&remote_name)
+    }
+
+    // fetches `remote_name` and fast-forwards onto it in one call, for callers that
+    // don't need to distinguish the fetch step from the fast-forward outcome
+    fn git_pull(&self, remote_name: &str) -> Result<GitFastForwardOutcome, VersionControlledSettingsError> {
+        self.git_fetch(remote_name)?;
+        Ok(self.git_fast_forward(remote_name)?)
+    }
+
+    fn git_pull_origin(&self) -> Result<GitFastForwardOutcome, VersionControlledSettingsError> {
+        let remote_name = self.resolve_origin_remote()?;
+        self.git_pull(&remote_name)
+    }
+
+    // fast-forwards the current branch onto `remote_name`'s tracking branch if possible,
+    // otherwise reports the diverging oids without touching the working tree
+    fn git_fast_forward(&self, remote_name: &str) -> Result<GitFastForwardOutcome, git2::Error> {
+        let repo = self.get_git_repo()?;
+        let branch = self.git_current_branch()?;
+        let remote_ref = format!("refs/remotes/{remote_name}/{branch}");
+        let remote_oid = repo.refname_to_id(&remote_ref)?;
+        let local_oid = repo.head()?.peel_to_commit()?.id();
+
+        if local_oid == remote_oid {
+            return Ok(GitFastForwardOutcome::UpToDate);
+        }
+
+        if repo.graph_descendant_of(remote_oid, local_oid)? {
+            let mut reference = repo.head()?;
+            reference.set_target(remote_oid, "fast-forward to remote")?;
+            repo.set_head(&format!("refs/heads/{branch}"))?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            Ok(GitFastForwardOutcome::FastForwarded(remote_oid))
+        } else {
+            Ok(GitFastForwardOutcome::Diverged {
+                local_oid,
+                remote_oid,
+            })
+        }
+    }
+
+    // three-way merges `remote_name`'s tracking branch into the working branch -
+    // settings can be edited both on the device and in the cloud, so a pull that
+    // diverged (see `git_fast_forward`'s Diverged outcome) is resolved here instead
+    // of clobbering local edits with a hard reset. A clean/auto-mergeable result is
+    // written as a merge commit with both parents and re-validated so the daemon
+    // re-applies the reconciled settings; a real conflict is reported rather than
+    // resolved, so the caller can surface it to the user instead of guessing.
+    async fn git_merge_remote(&self) -> Result<git2::Oid, VersionControlledSettingsError> {
+        let remote_name = self.resolve_origin_remote()?;
+        self.git_fetch(&remote_name)?;
+
+        let repo = self.get_git_repo()?;
+        let branch = self.git_current_branch()?;
+        let remote_ref = format!("refs/remotes/{remote_name}/{branch}");
+        let remote_oid = repo.refname_to_id(&remote_ref)?;
+        let remote_annotated = repo.find_annotated_commit(remote_oid)?;
+
+        repo.merge(&[&remote_annotated], None, None)?;
+
+        let mut index = repo.index()?;
+        if index.has_conflicts() {
+            let mut files: Vec<String> = index
+                .conflicts()?
+                .filter_map(|c| c.ok())
+                .filter_map(|c| c.our.or(c.their).or(c.ancestor))
+                .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+                .collect();
+            files.sort();
+            files.dedup();
+            let diff = self.git_diff()?;
+            repo.cleanup_state()?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            return Err(VersionControlledSettingsError::MergeConflict { files, diff });
+        }
+
+        let tree_oid = index.write_tree()?;
+        let tree = repo.find_tree(tree_oid)?;
+        let signature = repo.signature()?;
+        let local_commit = repo.head()?.peel_to_commit()?;
+        let pre_merge_oid = local_commit.id();
+        let remote_commit = repo.find_commit(remote_oid)?;
+        let commit_msg = format!("Merge remote-tracking branch '{remote_name}/{branch}'");
+        let merge_oid = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &commit_msg,
+            &tree,
+            &[&local_commit, &remote_commit],
+        )?;
+        repo.cleanup_state()?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+        info!("Merged {} into {} at {}", remote_ref, branch, merge_oid);
+
+        // mirrors handle_settings_apply_batch's rollback-on-failure pattern: a merge
+        // that reconciles cleanly but leaves settings that don't validate (or whose
+        // post_save side effects fail) must not get stuck committed and checked out
+        if let Err(e) = self.validate() {
+            warn!(
+                "Validation failed after merging {} into {}, resetting back to {}: {}",
+                remote_ref, branch, pre_merge_oid, e
+            );
+            self.git_reset_hard(pre_merge_oid)?;
+            let repo = self.get_git_repo()?;
+            repo.cleanup_state()?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            return Err(e);
+        }
+        if let Err(e) = self.post_save().await {
+            warn!(
+                "post_save failed after merging {} into {}, resetting back to {}: {}",
+                remote_ref, branch, pre_merge_oid, e
+            );
+            self.git_reset_hard(pre_merge_oid)?;
+            let repo = self.get_git_repo()?;
+            repo.cleanup_state()?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            return Err(e);
+        }
+
+        Ok(merge_oid)
+    }
+
     async fn save_and_commit(
         &self,
         content: &str,
         commit_msg: Option<String>,
+    ) -> Result<(), VersionControlledSettingsError> {
+        self.save_and_commit_with_push(content, commit_msg, false)
+            .await
+    }
+
+    // same as `save_and_commit`, but when `push` is set the commit is also pushed to
+    // "origin" after `post_save` runs, so a local revision reaches the cloud without a
+    // separate `SettingsRemotePush` round trip
+    async fn save_and_commit_with_push(
+        &self,
+        content: &str,
+        commit_msg: Option<String>,
+        push: bool,
     ) -> Result<(), VersionControlledSettingsError> {
         self.pre_save().await?;
         self.write_settings(content)?;
         self.git_add_all()?;
         self.git_commit(commit_msg)?;
         self.post_save().await?;
+        if push {
+            self.git_push_origin()?;
+        }
         Ok(())
     }
 
@@ -239,6 +1078,8 @@ impl<'repo> From<&git2::Commit<'repo>> for GitCommit {
             header: commit.raw_header().unwrap().to_string(),
             message: commit.message().unwrap().to_string(),
             ts: commit.time().seconds(),
+            signed: commit.header_field_bytes("gpgsig").is_ok(),
+            signer: None,
         }
     }
 }
@@ -249,6 +1090,8 @@ impl<'repo> From<git2::Commit<'repo>> for GitCommit {
             header: commit.raw_header().unwrap().to_string(),
             message: commit.message().unwrap().to_string(),
             ts: commit.time().seconds(),
+            signed: commit.header_field_bytes("gpgsig").is_ok(),
+            signer: None,
         }
     }
 }
@@ -260,6 +1103,10 @@ impl From<&printnanny_asyncapi_models::GitCommit> for GitCommit {
             header: commit.header.clone(),
             message: commit.message.clone(),
             ts: commit.ts.clone(),
+            // the asyncapi model predates commit signing - a commit relayed through it
+            // round-trips as unsigned rather than guessing at its original state
+            signed: false,
+            signer: None,
         }
     }
 }
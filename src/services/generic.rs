@@ -2,13 +2,14 @@
 use std::fs::{ read_to_string, OpenOptions };
 use std::path::{ PathBuf };
 use std::convert::TryInto;
+use std::time::{ SystemTime, UNIX_EPOCH };
 use log::{ info };
 
 use anyhow::{ anyhow, Context, Result };
 use async_trait::async_trait;
+use rand::Rng;
 use serde::{ Serialize, Deserialize };
 
-use printnanny_api_client::models::print_nanny_api_config::PrintNannyApiConfig;
 use printnanny_api_client::apis::configuration::Configuration;
 use printnanny_api_client::apis::devices_api::{
     devices_active_license_retrieve,
@@ -16,14 +17,26 @@ use printnanny_api_client::apis::devices_api::{
     devices_tasks_status_create
 };
 
-use printnanny_api_client::models::{ 
+use printnanny_api_client::models::{
     Device, License, TaskType, TaskRequest, TaskStatusRequest, TaskStatus, TaskStatusType, Task
 };
 use crate::paths::{ PrintNannyPath };
 
+// persisted alongside license.json in printnanny_license.zip - carries the long-lived
+// refresh_token and the current bearer_access_token's expiry so PrintNannyService can
+// mint a fresh access token without asking the user to re-license the device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    pub base_path: String,
+    pub bearer_access_token: String,
+    pub refresh_token: String,
+    // unix timestamp (seconds) after which bearer_access_token must be refreshed
+    pub expires_at: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct PrintNannyService<T>{
-    pub api_config: PrintNannyApiConfig,
+    pub api_config: ApiConfig,
     pub request_config: Configuration,
     pub paths: PrintNannyPath,
     pub config: String,
@@ -39,18 +52,22 @@ fn read_model_json<T:serde::de::DeserializeOwned >(path: &PathBuf) -> Result<T>
     Ok(result)
 }
 
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
 impl<T> PrintNannyService<T> {
     pub fn new(config: &str) -> Result<PrintNannyService<T>> {
         let paths = PrintNannyPath::new(config);
 
         // api_config.json is bundled in printnanny_license.zip
-        let api_config = read_model_json::<PrintNannyApiConfig>(&paths.api_config_json)?;
-        
+        let api_config = read_model_json::<ApiConfig>(&paths.api_config_json)?;
+
         // license.json is bundled in printnanny_license.zip
         let mut license = read_model_json::<License>(&paths.license_json)?;
         // refresh license from remote
 
-        let request_config = Configuration{ 
+        let request_config = Configuration{
             base_path: api_config.base_path.clone(),
             bearer_access_token: Some(api_config.bearer_access_token.clone()),
             ..Configuration::default()
@@ -59,39 +76,114 @@ impl<T> PrintNannyService<T> {
         Ok(PrintNannyService{request_config, api_config, paths, license, item: None, config: config.to_string() })
     }
 
-    pub async fn retreive_active_license(&self) -> Result<License> {
-        let active_license = devices_active_license_retrieve(
-            &self.request_config,
-            self.license.device,
-        ).await?;
-        Ok(active_license)
+    fn save_api_config(&self) -> Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&self.paths.api_config_json)
+            .context(format!("Failed to open {:?}", &self.paths.api_config_json))?;
+        serde_json::to_writer(&file, &self.api_config)
+            .context(format!("Failed to save ApiConfig to {:?}", &self.paths.api_config_json))?;
+        Ok(())
+    }
+
+    /// Exchanges the refresh_token for a new bearer_access_token at the configured
+    /// base_path's token endpoint, then persists the refreshed credentials to
+    /// api_config.json so they survive process restarts.
+    pub async fn refresh_access_token(&mut self) -> Result<()> {
+        #[derive(Serialize)]
+        struct RefreshTokenRequest<'a> {
+            grant_type: &'a str,
+            refresh_token: &'a str,
+        }
+        #[derive(Deserialize)]
+        struct RefreshTokenResponse {
+            access_token: String,
+            refresh_token: String,
+            expires_in: i64,
+        }
+
+        let url = format!("{}/o/token/", self.api_config.base_path);
+        let request = RefreshTokenRequest {
+            grant_type: "refresh_token",
+            refresh_token: &self.api_config.refresh_token,
+        };
+        let res: RefreshTokenResponse = reqwest::Client::new()
+            .post(&url)
+            .form(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.api_config.bearer_access_token = res.access_token;
+        self.api_config.refresh_token = res.refresh_token;
+        self.api_config.expires_at = unix_now() + res.expires_in;
+        self.request_config.bearer_access_token = Some(self.api_config.bearer_access_token.clone());
+
+        self.save_api_config()?;
+        info!("Refreshed PrintNanny Cloud access token, expires_at={}", self.api_config.expires_at);
+        Ok(())
+    }
+
+    // calls `f` once; if it fails with what looks like a 401 Unauthorized, refreshes
+    // the access token exactly once and retries before surfacing the error. `f` is
+    // re-invoked with a freshly cloned Configuration since refresh_access_token
+    // replaces request_config.bearer_access_token in place.
+    async fn with_token_refresh<F, Fut, R>(&mut self, f: F) -> Result<R>
+    where
+        F: Fn(Configuration) -> Fut,
+        Fut: std::future::Future<Output = Result<R>>,
+    {
+        match f(self.request_config.clone()).await {
+            Ok(v) => Ok(v),
+            Err(e) if format!("{:#}", e).contains("401") => {
+                info!("Access token rejected with 401, refreshing and retrying once");
+                self.refresh_access_token().await?;
+                f(self.request_config.clone()).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn retreive_active_license(&mut self) -> Result<License> {
+        let device_id = self.license.device;
+        self.with_token_refresh(move |config| async move {
+            Ok(devices_active_license_retrieve(&config, device_id).await?)
+        }).await
     }
 
     pub async fn update_task_status(
-        &self, 
+        &mut self,
         task_id: i32,
         status: TaskStatusType,
         wiki_url: Option<String>,
         detail: Option<String>
     ) -> Result<Task> {
-        let request = TaskStatusRequest{detail, wiki_url, task: task_id, status};
-        let task = devices_tasks_status_create(
-            &self.request_config,
-            self.license.device,
-            task_id,
-            request
-        ).await?;
+        let device_id = self.license.device;
+        let task = self.with_token_refresh(move |config| {
+            let request = TaskStatusRequest{ detail: detail.clone(), wiki_url: wiki_url.clone(), task: task_id, status };
+            async move {
+                Ok(devices_tasks_status_create(&config, device_id, task_id, request).await?)
+            }
+        }).await?;
         info!("Updated task={:?}", task);
         Ok(task)
     }
 
-    pub async fn create_task(&self, task_type: TaskType, status: Option<TaskStatusType>, detail: Option<String>, wiki_url: Option<String>) -> Result<Task> {
-        let request = TaskRequest{
-            active: Some(true),
-            task_type: task_type,
-            device: self.license.device
-        };
-        let task = devices_tasks_create(&self.request_config, self.license.device, request).await?;
+    pub async fn create_task(&mut self, task_type: TaskType, status: Option<TaskStatusType>, detail: Option<String>, wiki_url: Option<String>) -> Result<Task> {
+        let device_id = self.license.device;
+        let task = self.with_token_refresh(move |config| {
+            let request = TaskRequest{
+                active: Some(true),
+                task_type: task_type.clone(),
+                device: device_id
+            };
+            async move {
+                Ok(devices_tasks_create(&config, device_id, request).await?)
+            }
+        }).await?;
         let task = match status {
             Some(s) => self.update_task_status(task.id, s, wiki_url, detail, ).await?,
             None => task
@@ -103,36 +195,105 @@ impl<T> PrintNannyService<T> {
 
     /// Check validity of license
     /// Manage state of latest Task.task_type=CheckLicense
-    pub async fn check_license(&self) -> Result<License> {
+    pub async fn check_license(&mut self) -> Result<License> {
         // get active license from remote
         let active_license = self.retreive_active_license().await?;
 
-        // handle various pending/running/failed/success states of last check task
-        // return active license check task in running state
-        let check_task: Option<Task> = match &active_license.last_check_task {
-            // check state of last task
-            Some(last_check_task) => {
-                match &last_check_task.last_status {
-                    Some(last_status) => {
-                        // assume failed state if task status can't be read
-                        match last_status.status {
-                            // task state is already started, no update needed
-                            TaskStatusType::Started => None,
-                            // task state is pending, awaiting acknowledgement from device. update to started to ack.
-                            TaskStatusType::Pending => Some(self.update_task_status(last_check_task.id, TaskStatusType::Started, None, None).await?),
-                            // for Failed, Success, and Timeout states create a new task
-                            _ => Some(self.create_task(TaskType::CheckLicense, Some(TaskStatusType::Started), None, None).await?)
-                        }
-                    },
-                    None => Some(self.create_task(TaskType::CheckLicense, Some(TaskStatusType::Started), None, None).await?)
-                }
+        // resume the check task if one is already pending/running, otherwise run_task
+        // will start a fresh one
+        let resume_task = active_license.last_check_task.as_ref().and_then(|task| {
+            match task.last_status.as_ref().map(|s| s.status) {
+                Some(TaskStatusType::Started) | Some(TaskStatusType::Pending) => Some(task.clone()),
+                _ => None,
+            }
+        });
+
+        let license = active_license.clone();
+        self.run_task(
+            TaskType::CheckLicense,
+            resume_task.as_ref(),
+            RetryPolicy::default(),
+            move || {
+                let license = license.clone();
+                async move { Ok(license) }
             },
-            // no license check task found, create one in a running state
-            None => Some(self.create_task(TaskType::CheckLicense, Some(TaskStatusType::Started), None, None).await?)
+        ).await
+    }
+
+    /// Drives the full Task lifecycle around `work`: resumes `existing_task` (if given)
+    /// or creates a fresh `task_type` task, marks it Started, runs `work`, and on
+    /// failure posts Failed with the error detail, sleeps an exponentially increasing
+    /// backoff, and retries up to `policy.max_attempts` before marking Timeout. Success
+    /// posts TaskStatusType::Success. Lets every device task (setup, license check,
+    /// future OTA tasks) share one retry machine instead of a one-shot branch.
+    pub async fn run_task<F, Fut, R>(
+        &mut self,
+        task_type: TaskType,
+        existing_task: Option<&Task>,
+        policy: RetryPolicy,
+        work: F,
+    ) -> Result<R>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<R>>,
+    {
+        let task = match existing_task {
+            Some(t) => self.update_task_status(t.id, TaskStatusType::Started, None, None).await?,
+            None => self.create_task(task_type.clone(), Some(TaskStatusType::Started), None, None).await?,
         };
-        
-        // check task is in running state
-        Ok(active_license)
+
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            match work().await {
+                Ok(result) => {
+                    self.update_task_status(task.id, TaskStatusType::Success, None, None).await?;
+                    return Ok(result);
+                }
+                Err(e) => {
+                    self.update_task_status(task.id, TaskStatusType::Failed, None, Some(e.to_string())).await?;
+                    if attempt >= policy.max_attempts {
+                        self.update_task_status(task.id, TaskStatusType::Timeout, None, Some(e.to_string())).await?;
+                        return Err(e);
+                    }
+                    let delay = policy.backoff(attempt);
+                    info!(
+                        "Task {} (type={:?}) attempt {}/{} failed, retrying in {:?}: {}",
+                        task.id, task_type, attempt, policy.max_attempts, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// Retry policy driving [`PrintNannyService::run_task`]'s backoff between attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: std::time::Duration::from_secs(1),
+            max_delay: std::time::Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // base_delay * 2^attempt, capped at max_delay, plus up to 25% jitter so retrying
+    // tasks across many devices don't all wake up and hammer the API at once
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << attempt.min(16));
+        let capped = exp.min(self.max_delay);
+        let jitter_ms = rand::thread_rng().gen_range(0..=(capped.as_millis() as u64 / 4).max(1));
+        capped + std::time::Duration::from_millis(jitter_ms)
     }
 }
 